@@ -0,0 +1,92 @@
+//! Embed the published event schemas under `schemas/`, so the API can serve every version of
+//! them without the directory being deployed alongside it.
+//!
+//! Each version directory only holds the event types that changed in it, so the unchanged ones
+//! are carried forward from the version before, and every version is embedded in full.
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn main() {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("schemas");
+
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    let mut files = BTreeMap::new();
+
+    for path in children(&root) {
+        if path.is_dir() {
+            collect_entity(&root, &path, &mut files);
+        } else if is_json(&path) {
+            files.insert(relative(&root, &path), path);
+        }
+    }
+
+    let entries: String = files
+        .iter()
+        .map(|(relative, path)| format!("    ({relative:?}, include_str!({path:?})),\n"))
+        .collect();
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("published_schemas.rs");
+
+    fs::write(out, format!("&[\n{entries}]\n")).unwrap();
+}
+
+/// Add every version of an entity's schemas, carrying each event type forward until a later
+/// version replaces it
+fn collect_entity(root: &Path, entity: &Path, files: &mut BTreeMap<String, PathBuf>) {
+    let mut versions: Vec<_> = children(entity)
+        .into_iter()
+        .filter(|path| path.is_dir())
+        .collect();
+    versions.sort_by_key(|path| version(path));
+
+    let mut current = BTreeMap::new();
+
+    for dir in versions {
+        for path in children(&dir).into_iter().filter(|path| is_json(path)) {
+            current.insert(path.file_name().unwrap().to_owned(), path);
+        }
+
+        for (name, path) in &current {
+            files.insert(relative(root, &dir.join(name)), path.clone());
+        }
+    }
+}
+
+/// List a directory, marking it so new versions and files trigger a rebuild
+fn children(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    entries.flatten().map(|entry| entry.path()).collect()
+}
+
+/// A version directory's name as numbers, so "1.10" comes after "1.9"
+fn version(dir: &Path) -> Vec<u64> {
+    dir.file_name()
+        .unwrap()
+        .to_string_lossy()
+        .split('.')
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
+
+fn is_json(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == "json")
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap()
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -25,4 +25,8 @@ module "app" {
   namespace   = var.namespace
   region      = var.region
   environment = var.environment
+
+  auth_jwks_url = var.auth_jwks_url
+  auth_issuer   = var.auth_issuer
+  auth_audience = var.auth_audience
 }
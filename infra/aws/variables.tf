@@ -15,6 +15,21 @@ variable "environment" {
   default = "dev"
 }
 
+variable "auth_jwks_url" {
+  type    = string
+  default = ""
+}
+
+variable "auth_issuer" {
+  type    = string
+  default = ""
+}
+
+variable "auth_audience" {
+  type    = string
+  default = ""
+}
+
 variable "developers" {
   type = map(object({
     path                 = optional(string, "/")
@@ -73,3 +73,222 @@ module "dynamodb_tasks_view" {
     }
   ]
 }
+
+module "label_tasks_activity" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "tasks-activity"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "dynamodb_tasks_activity" {
+  source = "terraform-aws-modules/dynamodb-table/aws"
+
+  name      = module.label_tasks_activity.id
+  hash_key  = "AggregateId"
+  range_key = "Sequence"
+
+  attributes = [
+    {
+      name = "AggregateId"
+      type = "S"
+    },
+    {
+      name = "Sequence"
+      type = "N"
+    }
+  ]
+}
+
+module "label_tasks_index" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "tasks-index"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "dynamodb_tasks_index" {
+  source = "terraform-aws-modules/dynamodb-table/aws"
+
+  name      = module.label_tasks_index.id
+  hash_key  = "Facet"
+  range_key = "TaskId"
+
+  attributes = [
+    {
+      name = "Facet"
+      type = "S"
+    },
+    {
+      name = "TaskId"
+      type = "S"
+    }
+  ]
+}
+
+module "label_tasks_inbox" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "tasks-inbox"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "dynamodb_tasks_inbox" {
+  source = "terraform-aws-modules/dynamodb-table/aws"
+
+  name      = module.label_tasks_inbox.id
+  hash_key  = "UserId"
+  range_key = "AggregateId"
+
+  attributes = [
+    {
+      name = "UserId"
+      type = "S"
+    },
+    {
+      name = "AggregateId"
+      type = "S"
+    }
+  ]
+
+  global_secondary_indexes = [
+    {
+      name            = "TaskIdIndex"
+      hash_key        = "AggregateId"
+      projection_type = "KEYS_ONLY"
+    }
+  ]
+}
+
+module "label_projects_view" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "projects-view"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "dynamodb_projects_view" {
+  source = "terraform-aws-modules/dynamodb-table/aws"
+
+  name     = module.label_projects_view.id
+  hash_key = "ViewId"
+
+  attributes = [
+    {
+      name = "ViewId"
+      type = "S"
+    }
+  ]
+}
+
+module "label_encryption_keys" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "encryption-keys"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "dynamodb_encryption_keys" {
+  source = "terraform-aws-modules/dynamodb-table/aws"
+
+  name     = module.label_encryption_keys.id
+  hash_key = "AggregateId"
+
+  attributes = [
+    {
+      name = "AggregateId"
+      type = "S"
+    }
+  ]
+}
+
+module "label_tasks_schedule" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "tasks-schedule"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "dynamodb_tasks_schedule" {
+  source = "terraform-aws-modules/dynamodb-table/aws"
+
+  name     = module.label_tasks_schedule.id
+  hash_key = "ScheduleId"
+
+  attributes = [
+    {
+      name = "ScheduleId"
+      type = "S"
+    },
+    {
+      name = "Bucket"
+      type = "S"
+    },
+    {
+      name = "DueAt"
+      type = "N"
+    }
+  ]
+
+  global_secondary_indexes = [
+    {
+      name            = "DueAtIndex"
+      hash_key        = "Bucket"
+      range_key       = "DueAt"
+      projection_type = "ALL"
+    }
+  ]
+}
+
+module "label_sagas" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "sagas"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "dynamodb_sagas" {
+  source = "terraform-aws-modules/dynamodb-table/aws"
+
+  name      = module.label_sagas.id
+  hash_key  = "Saga"
+  range_key = "CorrelationId"
+
+  attributes = [
+    {
+      name = "Saga"
+      type = "S"
+    },
+    {
+      name = "CorrelationId"
+      type = "S"
+    },
+    {
+      name = "TimeoutAt"
+      type = "N"
+    }
+  ]
+
+  global_secondary_indexes = [
+    {
+      name            = "TimeoutIndex"
+      hash_key        = "Saga"
+      range_key       = "TimeoutAt"
+      projection_type = "KEYS_ONLY"
+    }
+  ]
+}
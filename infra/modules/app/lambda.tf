@@ -1,3 +1,20 @@
+locals {
+  # Every function loads the same config, which needs each table name outside `local`
+  config_environment_variables = {
+    ENV                        = var.environment
+    EVENT_LOG_TABLE_NAME       = module.dynamodb_event_log.dynamodb_table_id
+    EVENT_SNAPSHOTS_TABLE_NAME = module.dynamodb_event_snapshots.dynamodb_table_id
+    TASKS_VIEW_TABLE_NAME      = module.dynamodb_tasks_view.dynamodb_table_id
+    TASKS_INDEX_TABLE_NAME     = module.dynamodb_tasks_index.dynamodb_table_id
+    TASKS_ACTIVITY_TABLE_NAME  = module.dynamodb_tasks_activity.dynamodb_table_id
+    TASKS_INBOX_TABLE_NAME     = module.dynamodb_tasks_inbox.dynamodb_table_id
+    TASKS_SCHEDULE_TABLE_NAME  = module.dynamodb_tasks_schedule.dynamodb_table_id
+    PROJECTS_VIEW_TABLE_NAME   = module.dynamodb_projects_view.dynamodb_table_id
+    ENCRYPTION_KEYS_TABLE_NAME = module.dynamodb_encryption_keys.dynamodb_table_id
+    SAGAS_TABLE_NAME           = module.dynamodb_sagas.dynamodb_table_id
+  }
+}
+
 module "label_http_api" {
   source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
   namespace = var.namespace
@@ -19,12 +36,11 @@ module "lambda_http_api" {
 
   source_path = "../../target/lambda/event-driven-architecture"
 
-  environment_variables = {
-    ENV                        = var.environment
-    EVENT_LOG_TABLE_NAME       = module.dynamodb_event_log.dynamodb_table_id
-    EVENT_SNAPSHOTS_TABLE_NAME = module.dynamodb_event_snapshots.dynamodb_table_id
-    TASKS_VIEW_TABLE_NAME      = module.dynamodb_tasks_view.dynamodb_table_id
-  }
+  environment_variables = merge(local.config_environment_variables, {
+    AUTH_JWKS_URL = var.auth_jwks_url
+    AUTH_ISSUER   = var.auth_issuer
+    AUTH_AUDIENCE = var.auth_audience
+  })
 
   allowed_triggers = {
     apigateway = {
@@ -48,6 +64,23 @@ module "lambda_http_api" {
         "dynamodb:BatchWriteItem",
         "dynamodb:ConditionCheckItem"
       ]
+      resources = [
+        module.dynamodb_event_log.dynamodb_table_arn,
+        module.dynamodb_event_snapshots.dynamodb_table_arn,
+        module.dynamodb_tasks_view.dynamodb_table_arn,
+        module.dynamodb_projects_view.dynamodb_table_arn,
+        module.dynamodb_encryption_keys.dynamodb_table_arn,
+        module.dynamodb_tasks_schedule.dynamodb_table_arn,
+        module.dynamodb_tasks_activity.dynamodb_table_arn,
+        module.dynamodb_tasks_index.dynamodb_table_arn,
+        module.dynamodb_tasks_inbox.dynamodb_table_arn
+      ]
+    }
+    dynamodb_readiness = {
+      effect = "Allow",
+      actions = [
+        "dynamodb:DescribeTable"
+      ]
       resources = [
         module.dynamodb_event_log.dynamodb_table_arn,
         module.dynamodb_event_snapshots.dynamodb_table_arn,
@@ -93,9 +126,9 @@ module "lambda_publisher_kinesis" {
 
   source_path = "../../target/lambda/publisher_kinesis"
 
-  environment_variables = {
+  environment_variables = merge(local.config_environment_variables, {
     EVENT_STREAM_NAME = aws_kinesis_stream.event_stream.name
-  }
+  })
 
   attach_dead_letter_policy = true
   dead_letter_target_arn    = module.sqs_publisher_kinesis_dead_letter.queue_arn
@@ -121,6 +154,11 @@ module "lambda_publisher_kinesis" {
       ]
       resources = [module.dynamodb_event_log.dynamodb_table_stream_arn]
     }
+    encryption_keys = {
+      effect    = "Allow",
+      actions   = ["dynamodb:GetItem"]
+      resources = [module.dynamodb_encryption_keys.dynamodb_table_arn]
+    }
     kinesis = {
       effect = "Allow",
       actions = [
@@ -153,9 +191,9 @@ module "lambda_projector_s3_audit" {
 
   source_path = "../../target/lambda/projector_s3_audit"
 
-  environment_variables = {
+  environment_variables = merge(local.config_environment_variables, {
     AUDIT_BUCKET_NAME = module.s3_event_audit.s3_bucket_id
-  }
+  })
 
   attach_dead_letter_policy = true
   dead_letter_target_arn    = module.sqs_projector_s3_audit_dead_letter.queue_arn
@@ -185,6 +223,11 @@ module "lambda_projector_s3_audit" {
       ],
       resources = [aws_kinesis_stream.event_stream.arn]
     },
+    encryption_keys = {
+      effect    = "Allow",
+      actions   = ["dynamodb:GetItem"]
+      resources = [module.dynamodb_encryption_keys.dynamodb_table_arn]
+    },
     s3 = {
       effect = "Allow",
       actions = [
@@ -201,3 +244,311 @@ module "lambda_projector_s3_audit" {
 
   cloudwatch_logs_retention_in_days = 7
 }
+
+module "label_projector_inbox" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "projector-inbox"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "lambda_projector_inbox" {
+  source = "terraform-aws-modules/lambda/aws"
+
+  function_name = module.label_projector_inbox.id
+  description   = "The per-user Task Inbox Projector"
+  handler       = "bootstrap"
+  runtime       = "provided.al2023"
+
+  source_path = "../../target/lambda/projector_inbox"
+
+  environment_variables = local.config_environment_variables
+
+  attach_dead_letter_policy = true
+  dead_letter_target_arn    = module.sqs_projector_inbox_dead_letter.queue_arn
+
+  event_source_mapping = {
+    kinesis = {
+      event_source_arn           = resource.aws_kinesis_stream.event_stream.arn
+      starting_position          = "LATEST"
+      batch_size                 = 10
+      maximum_retry_attempts     = 5
+      function_response_types    = ["ReportBatchItemFailures"]
+      destination_arn_on_failure = module.sqs_projector_inbox_dead_letter.queue_arn
+    }
+  }
+
+  attach_policy_statements = true
+  policy_statements = {
+    kinesis = {
+      effect = "Allow",
+      actions = [
+        "kinesis:GetRecords",
+        "kinesis:GetShardIterator",
+        "kinesis:DescribeStream",
+        "kinesis:DescribeStreamSummary",
+        "kinesis:ListShards",
+        "kinesis:ListStreams"
+      ],
+      resources = [aws_kinesis_stream.event_stream.arn]
+    },
+    dynamodb = {
+      effect = "Allow",
+      actions = [
+        "dynamodb:Query",
+        "dynamodb:PutItem",
+        "dynamodb:UpdateItem",
+        "dynamodb:DeleteItem"
+      ]
+      resources = [
+        module.dynamodb_tasks_inbox.dynamodb_table_arn,
+        "${module.dynamodb_tasks_inbox.dynamodb_table_arn}/index/*"
+      ]
+    }
+  }
+
+  cloudwatch_logs_retention_in_days = 7
+}
+
+module "label_process_manager_recurrence" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "process-manager-recurrence"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "lambda_process_manager_recurrence" {
+  source = "terraform-aws-modules/lambda/aws"
+
+  function_name = module.label_process_manager_recurrence.id
+  description   = "The recurring Task Process Manager"
+  handler       = "bootstrap"
+  runtime       = "provided.al2023"
+
+  source_path = "../../target/lambda/process_manager_recurrence"
+
+  environment_variables = local.config_environment_variables
+
+  attach_dead_letter_policy = true
+  dead_letter_target_arn    = module.sqs_process_manager_recurrence_dead_letter.queue_arn
+
+  event_source_mapping = {
+    kinesis = {
+      event_source_arn           = resource.aws_kinesis_stream.event_stream.arn
+      starting_position          = "LATEST"
+      batch_size                 = 10
+      maximum_retry_attempts     = 5
+      function_response_types    = ["ReportBatchItemFailures"]
+      destination_arn_on_failure = module.sqs_process_manager_recurrence_dead_letter.queue_arn
+    }
+  }
+
+  attach_policy_statements = true
+  policy_statements = {
+    kinesis = {
+      effect = "Allow",
+      actions = [
+        "kinesis:GetRecords",
+        "kinesis:GetShardIterator",
+        "kinesis:DescribeStream",
+        "kinesis:DescribeStreamSummary",
+        "kinesis:ListShards",
+        "kinesis:ListStreams"
+      ],
+      resources = [aws_kinesis_stream.event_stream.arn]
+    },
+    dynamodb = {
+      effect = "Allow",
+      actions = [
+        "dynamodb:GetItem",
+        "dynamodb:Query",
+        "dynamodb:PutItem",
+        "dynamodb:UpdateItem",
+        "dynamodb:DeleteItem",
+        "dynamodb:BatchWriteItem",
+        "dynamodb:ConditionCheckItem"
+      ]
+      resources = [
+        module.dynamodb_event_log.dynamodb_table_arn,
+        module.dynamodb_event_snapshots.dynamodb_table_arn,
+        module.dynamodb_tasks_view.dynamodb_table_arn,
+        module.dynamodb_encryption_keys.dynamodb_table_arn,
+        module.dynamodb_tasks_schedule.dynamodb_table_arn,
+        module.dynamodb_tasks_activity.dynamodb_table_arn,
+        module.dynamodb_tasks_index.dynamodb_table_arn
+      ]
+    }
+  }
+
+  cloudwatch_logs_retention_in_days = 7
+}
+
+module "label_process_manager_sagas" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "process-manager-sagas"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "lambda_process_manager_sagas" {
+  source = "terraform-aws-modules/lambda/aws"
+
+  function_name = module.label_process_manager_sagas.id
+  description   = "The saga Process Manager"
+  handler       = "bootstrap"
+  runtime       = "provided.al2023"
+
+  source_path = "../../target/lambda/process_manager_sagas"
+
+  environment_variables = local.config_environment_variables
+
+  attach_dead_letter_policy = true
+  dead_letter_target_arn    = module.sqs_process_manager_sagas_dead_letter.queue_arn
+
+  event_source_mapping = {
+    kinesis = {
+      event_source_arn           = resource.aws_kinesis_stream.event_stream.arn
+      starting_position          = "LATEST"
+      batch_size                 = 10
+      maximum_retry_attempts     = 5
+      function_response_types    = ["ReportBatchItemFailures"]
+      destination_arn_on_failure = module.sqs_process_manager_sagas_dead_letter.queue_arn
+    }
+  }
+
+  allowed_triggers = {
+    schedule = {
+      principal  = "events.amazonaws.com"
+      source_arn = aws_cloudwatch_event_rule.process_manager_sagas.arn
+    }
+  }
+
+  attach_policy_statements = true
+  policy_statements = {
+    kinesis = {
+      effect = "Allow",
+      actions = [
+        "kinesis:GetRecords",
+        "kinesis:GetShardIterator",
+        "kinesis:DescribeStream",
+        "kinesis:DescribeStreamSummary",
+        "kinesis:ListShards",
+        "kinesis:ListStreams"
+      ],
+      resources = [aws_kinesis_stream.event_stream.arn]
+    },
+    dynamodb = {
+      effect = "Allow",
+      actions = [
+        "dynamodb:GetItem",
+        "dynamodb:Query",
+        "dynamodb:PutItem",
+        "dynamodb:UpdateItem",
+        "dynamodb:DeleteItem",
+        "dynamodb:BatchWriteItem",
+        "dynamodb:ConditionCheckItem"
+      ]
+      resources = [
+        module.dynamodb_event_log.dynamodb_table_arn,
+        module.dynamodb_event_snapshots.dynamodb_table_arn,
+        module.dynamodb_tasks_view.dynamodb_table_arn,
+        module.dynamodb_projects_view.dynamodb_table_arn,
+        module.dynamodb_encryption_keys.dynamodb_table_arn,
+        module.dynamodb_tasks_schedule.dynamodb_table_arn,
+        module.dynamodb_tasks_activity.dynamodb_table_arn,
+        module.dynamodb_tasks_index.dynamodb_table_arn,
+        module.dynamodb_sagas.dynamodb_table_arn,
+        "${module.dynamodb_sagas.dynamodb_table_arn}/index/*"
+      ]
+    }
+  }
+
+  cloudwatch_logs_retention_in_days = 7
+}
+
+resource "aws_cloudwatch_event_rule" "process_manager_sagas" {
+  name                = module.label_process_manager_sagas.id
+  description         = "Handle saga timeouts that have passed"
+  schedule_expression = "rate(1 minute)"
+  tags                = local.common_tags
+}
+
+resource "aws_cloudwatch_event_target" "process_manager_sagas" {
+  rule = aws_cloudwatch_event_rule.process_manager_sagas.name
+  arn  = module.lambda_process_manager_sagas.lambda_function_arn
+}
+
+module "label_scheduler_deadlines" {
+  source    = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace = var.namespace
+  stage     = var.environment
+  name      = "scheduler-deadlines"
+  tags      = local.common_tags
+  delimiter = "-"
+}
+
+module "lambda_scheduler_deadlines" {
+  source = "terraform-aws-modules/lambda/aws"
+
+  function_name = module.label_scheduler_deadlines.id
+  description   = "The Task deadline Scheduler"
+  handler       = "bootstrap"
+  runtime       = "provided.al2023"
+
+  source_path = "../../target/lambda/scheduler_deadlines"
+
+  environment_variables = local.config_environment_variables
+
+  allowed_triggers = {
+    schedule = {
+      principal  = "events.amazonaws.com"
+      source_arn = aws_cloudwatch_event_rule.scheduler_deadlines.arn
+    }
+  }
+
+  attach_policy_statements = true
+  policy_statements = {
+    dynamodb = {
+      effect = "Allow",
+      actions = [
+        "dynamodb:GetItem",
+        "dynamodb:Query",
+        "dynamodb:PutItem",
+        "dynamodb:UpdateItem",
+        "dynamodb:DeleteItem",
+        "dynamodb:BatchWriteItem",
+        "dynamodb:ConditionCheckItem"
+      ]
+      resources = [
+        module.dynamodb_event_log.dynamodb_table_arn,
+        module.dynamodb_event_snapshots.dynamodb_table_arn,
+        module.dynamodb_tasks_view.dynamodb_table_arn,
+        module.dynamodb_encryption_keys.dynamodb_table_arn,
+        module.dynamodb_tasks_schedule.dynamodb_table_arn,
+        "${module.dynamodb_tasks_schedule.dynamodb_table_arn}/index/*",
+        module.dynamodb_tasks_activity.dynamodb_table_arn,
+        module.dynamodb_tasks_index.dynamodb_table_arn
+      ]
+    }
+  }
+
+  cloudwatch_logs_retention_in_days = 7
+}
+
+resource "aws_cloudwatch_event_rule" "scheduler_deadlines" {
+  name                = module.label_scheduler_deadlines.id
+  description         = "Fire Task reminders and due dates that have passed"
+  schedule_expression = "rate(1 minute)"
+  tags                = local.common_tags
+}
+
+resource "aws_cloudwatch_event_target" "scheduler_deadlines" {
+  rule = aws_cloudwatch_event_rule.scheduler_deadlines.name
+  arn  = module.lambda_scheduler_deadlines.lambda_function_arn
+}
@@ -29,3 +29,51 @@ module "sqs_projector_s3_audit_dead_letter" {
 
   name = module.label_s3_audit_dead_letter.id
 }
+
+module "label_inbox_dead_letter" {
+  source     = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace  = var.namespace
+  stage      = var.environment
+  name       = "projector-inbox"
+  attributes = ["dead-letter"]
+  tags       = local.common_tags
+  delimiter  = "-"
+}
+
+module "sqs_projector_inbox_dead_letter" {
+  source = "terraform-aws-modules/sqs/aws"
+
+  name = module.label_inbox_dead_letter.id
+}
+
+module "label_recurrence_dead_letter" {
+  source     = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace  = var.namespace
+  stage      = var.environment
+  name       = "process-manager-recurrence"
+  attributes = ["dead-letter"]
+  tags       = local.common_tags
+  delimiter  = "-"
+}
+
+module "sqs_process_manager_recurrence_dead_letter" {
+  source = "terraform-aws-modules/sqs/aws"
+
+  name = module.label_recurrence_dead_letter.id
+}
+
+module "label_sagas_dead_letter" {
+  source     = "git::https://github.com/cloudposse/terraform-null-label.git?ref=tags/0.25.0"
+  namespace  = var.namespace
+  stage      = var.environment
+  name       = "process-manager-sagas"
+  attributes = ["dead-letter"]
+  tags       = local.common_tags
+  delimiter  = "-"
+}
+
+module "sqs_process_manager_sagas_dead_letter" {
+  source = "terraform-aws-modules/sqs/aws"
+
+  name = module.label_sagas_dead_letter.id
+}
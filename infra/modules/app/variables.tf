@@ -23,6 +23,22 @@ variable "enable_api_gateway" {
   default = true
 }
 
+variable "auth_jwks_url" {
+  type        = string
+  description = "The JWKS url of the identity provider that issues bearer tokens for the API"
+  default     = ""
+}
+
+variable "auth_issuer" {
+  type    = string
+  default = ""
+}
+
+variable "auth_audience" {
+  type    = string
+  default = ""
+}
+
 locals {
   common_tags = {
     ProvisionedBy = "terraform"
@@ -0,0 +1,310 @@
+use std::time::{Duration, Instant};
+
+use aide::{
+    gen::GenContext,
+    openapi::{
+        Operation, Parameter, ParameterData, ParameterSchemaOrContent, SchemaObject,
+        SecurityRequirement,
+    },
+    operation::add_parameters,
+    OperationInput,
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderName, StatusCode},
+};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use event_driven_architecture::{
+    config::Config,
+    domains::{self, tenants},
+};
+
+use crate::AppState;
+
+/// The name of the bearer token security scheme in the OpenAPI document
+pub const SECURITY_SCHEME: &str = "bearer";
+
+/// The header that selects a tenant in local development, where tokens signed with the static
+/// secret may not carry a tenant claim
+pub const TENANT_HEADER: HeaderName = HeaderName::from_static("x-tenant-id");
+
+/// Don't refetch the JWKS for unknown key ids more often than this
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The claims this API relies on from a validated token
+#[derive(Debug, Deserialize)]
+pub struct Claims {
+    /// The subject the token was issued to
+    pub sub: String,
+
+    /// The tenant the subject belongs to, if the identity provider assigns one
+    pub tenant: Option<String>,
+}
+
+/// How bearer token signatures are verified
+enum Keys {
+    /// A single shared secret, for local development and tests
+    Static(DecodingKey),
+
+    /// Keys published at a JWKS url by the identity provider, refreshed when an unknown key id
+    /// is seen
+    Jwks {
+        url: String,
+        client: reqwest::Client,
+        cache: RwLock<Option<(JwkSet, Instant)>>,
+    },
+}
+
+/// Validates bearer JWTs and extracts the acting subject
+pub struct Authenticator {
+    keys: Keys,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Authenticator {
+    /// Configure the Authenticator from the config
+    ///
+    /// Uses `AUTH_JWKS_URL` to validate tokens against the identity provider's published keys,
+    /// or `AUTH_STATIC_SECRET` to validate HS256 tokens signed with a shared secret for local
+    /// development and tests. `AUTH_ISSUER` and `AUTH_AUDIENCE` are validated when set.
+    pub fn init(config: &Config) -> anyhow::Result<Self> {
+        let auth = &config.auth;
+
+        let keys = match (&auth.jwks_url, &auth.static_secret) {
+            (Some(url), _) => Keys::Jwks {
+                url: url.clone(),
+                client: reqwest::Client::new(),
+                cache: RwLock::new(None),
+            },
+            (None, Some(secret)) => Keys::Static(DecodingKey::from_secret(secret.as_bytes())),
+            (None, None) => {
+                return Err(anyhow!(
+                    "Authentication is not configured. Set AUTH_JWKS_URL, or AUTH_STATIC_SECRET \
+                     for local development."
+                ))
+            }
+        };
+
+        Ok(Self {
+            keys,
+            issuer: auth.issuer.clone(),
+            audience: auth.audience.clone(),
+        })
+    }
+
+    /// Whether the tenant may be taken from the `X-Tenant-Id` header for tokens without a tenant
+    /// claim, which is only trusted for tokens signed with the static secret in local development
+    pub fn allows_tenant_header(&self) -> bool {
+        matches!(self.keys, Keys::Static(_))
+    }
+
+    /// Validate a bearer token and return its claims
+    pub async fn authenticate(&self, token: &str) -> Result<Claims, domains::Error> {
+        let header = decode_header(token).map_err(unauthorized)?;
+
+        let (key, algorithm) = match &self.keys {
+            Keys::Static(key) => (key.clone(), Algorithm::HS256),
+            Keys::Jwks { .. } => {
+                let kid = header
+                    .kid
+                    .ok_or_else(|| domains::Error::Unauthorized(Some(anyhow!("Missing kid"))))?;
+
+                let jwk = self.find_jwk(&kid).await?;
+
+                if let Some(key_algorithm) = jwk.common.key_algorithm {
+                    // Guard against algorithm confusion by requiring the token to use the
+                    // algorithm the key was published for
+                    if format!("{key_algorithm:?}") != format!("{:?}", header.alg) {
+                        return Err(domains::Error::Unauthorized(Some(anyhow!(
+                            "Token algorithm does not match the signing key"
+                        ))));
+                    }
+                }
+
+                (
+                    DecodingKey::from_jwk(&jwk).map_err(unauthorized)?,
+                    header.alg,
+                )
+            }
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_required_spec_claims(&["exp", "sub"]);
+
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let token = decode::<Claims>(token, &key, &validation).map_err(unauthorized)?;
+
+        Ok(token.claims)
+    }
+
+    async fn find_jwk(&self, kid: &str) -> Result<Jwk, domains::Error> {
+        let Keys::Jwks { url, client, cache } = &self.keys else {
+            return Err(domains::Error::Unauthorized(None));
+        };
+
+        let stale = {
+            let cache = cache.read().await;
+
+            match cache.as_ref() {
+                Some((keys, _)) if keys.find(kid).is_some() => {
+                    return Ok(keys.find(kid).cloned().expect("Key was just found"));
+                }
+                Some((_, fetched_at)) => fetched_at.elapsed() >= JWKS_REFRESH_INTERVAL,
+                None => true,
+            }
+        };
+
+        if !stale {
+            return Err(domains::Error::Unauthorized(Some(anyhow!(
+                "Unknown kid: {kid}"
+            ))));
+        }
+
+        let keys: JwkSet = client
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| domains::Error::Unauthorized(Some(err.into())))?
+            .json()
+            .await
+            .map_err(|err| domains::Error::Unauthorized(Some(err.into())))?;
+
+        let jwk = keys.find(kid).cloned();
+
+        *cache.write().await = Some((keys, Instant::now()));
+
+        jwk.ok_or_else(|| domains::Error::Unauthorized(Some(anyhow!("Unknown kid: {kid}"))))
+    }
+}
+
+fn unauthorized(err: jsonwebtoken::errors::Error) -> domains::Error {
+    domains::Error::Unauthorized(Some(err.into()))
+}
+
+/// The authenticated subject making a request and the tenant they're acting within
+///
+/// The subject comes from a bearer JWT, and the tenant from its `tenant` claim. Tokens without one
+/// are rejected, so callers can't choose which tenant to reach into, except with the static secret
+/// in local development, where the tenant is taken from the `X-Tenant-Id` header instead. A header
+/// that disagrees with the claim is rejected.
+pub struct Identity {
+    /// The authenticated subject
+    pub subject: String,
+
+    /// The tenant the request is scoped to
+    pub tenant: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Identity {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or((StatusCode::UNAUTHORIZED, "Missing bearer token".to_string()))?;
+
+        let claims = state.auth.authenticate(token).await.map_err(|err| {
+            debug!("Authentication failed: {:?}", err);
+
+            (StatusCode::UNAUTHORIZED, err.to_string())
+        })?;
+
+        let header = parts
+            .headers
+            .get(TENANT_HEADER)
+            .map(|value| value.to_str().unwrap_or_default().to_string());
+
+        let tenant = match (claims.tenant, header) {
+            (Some(claim), Some(header)) if claim != header => {
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    "The tenant header does not match the token".to_string(),
+                ))
+            }
+            (Some(claim), _) => claim,
+            (None, _) if !state.auth.allows_tenant_header() => {
+                return Err((
+                    StatusCode::UNAUTHORIZED,
+                    "The token has no tenant claim".to_string(),
+                ));
+            }
+            (None, Some(header)) => header,
+            (None, None) => {
+                return Err((StatusCode::BAD_REQUEST, "Missing tenant".to_string()));
+            }
+        };
+
+        if !tenants::is_valid(&tenant) {
+            return Err((StatusCode::BAD_REQUEST, "Invalid tenant".to_string()));
+        }
+
+        Ok(Identity {
+            subject: claims.sub,
+            tenant,
+        })
+    }
+}
+
+impl OperationInput for Identity {
+    fn operation_input(ctx: &mut GenContext, operation: &mut Operation) {
+        let mut requirement = SecurityRequirement::new();
+        requirement.insert(SECURITY_SCHEME.to_string(), Vec::new());
+
+        operation.security.push(requirement);
+
+        let schema = ctx.schema.subschema_for::<String>();
+
+        add_parameters(
+            ctx,
+            operation,
+            [Parameter::Header {
+                parameter_data: ParameterData {
+                    name: TENANT_HEADER.to_string(),
+                    description: Some(
+                        "The tenant to act within, for tokens signed with the local development \
+                         secret that have no tenant claim"
+                            .to_string(),
+                    ),
+                    required: false,
+                    format: ParameterSchemaOrContent::Schema(SchemaObject {
+                        json_schema: schema,
+                        example: None,
+                        external_docs: None,
+                    }),
+                    extensions: Default::default(),
+                    deprecated: None,
+                    example: None,
+                    examples: Default::default(),
+                    explode: None,
+                },
+                style: Default::default(),
+            }],
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Generate the published JSON Schemas for domain events, or check the current event types for
+//! backward-incompatible changes against them. Event types at a version that hasn't been
+//! published yet are checked against the version they were bumped from, and must be upcast from
+//! it if they break compatibility.
+//!
+//! Only the event types that changed are written for a new version, and the rest are carried
+//! forward when the schemas are embedded at build time. A version that changes no event type
+//! fails the check, since the version should only be bumped when the wire format changes.
+//!
+//! Usage: `event_schemas [generate|check]`
+
+use std::{cmp::Ordering, collections::BTreeMap, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use cqrs_es::persist::EventUpcaster;
+use event_driven_architecture::domains::{
+    self,
+    schemas::{self, EventSchema},
+    Upcasters,
+};
+use serde_json::Value;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+
+    let command = args.next().unwrap_or_else(|| "generate".to_string());
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas");
+
+    match command.as_str() {
+        "generate" => generate(&dir),
+        "check" => check(&dir),
+        other => Err(anyhow!(
+            "Unknown command `{other}`, expected `generate` or `check`"
+        )),
+    }
+}
+
+fn generate(dir: &Path) -> anyhow::Result<()> {
+    write(
+        &dir.join(format!("{}.json", schemas::ENVELOPE)),
+        &schemas::envelope(),
+    )?;
+
+    let registry = schemas::registry();
+    let published = schemas::published();
+
+    for schema in &registry {
+        let path = dir.join(schema.path());
+        let previous = schemas::previous(&published, &schema.event_type, &schema.event_version);
+
+        if previous.is_some_and(|previous| previous.unversioned() == schema.unversioned()) {
+            // Unchanged event types are carried forward from the version before
+            if path.exists() {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Unable to remove {}", path.display()))?;
+
+                println!("Removed {}", path.display());
+            }
+
+            continue;
+        }
+
+        write(&path, &schema.unversioned())?;
+    }
+
+    // Event types that were removed stop being carried forward
+    for removed in removed_since_previous(&published, &registry) {
+        write(&dir.join(removed.path()), &Value::Bool(false))?;
+    }
+
+    Ok(())
+}
+
+fn check(dir: &Path) -> anyhow::Result<()> {
+    let mut failures = Vec::new();
+
+    let envelope_path = dir.join(format!("{}.json", schemas::ENVELOPE));
+    if let Some(published) = read(&envelope_path)? {
+        failures.extend(report(
+            schemas::ENVELOPE,
+            schemas::check_compatibility(&published, &schemas::envelope()),
+        ));
+    }
+
+    let registry = schemas::registry();
+    let published = schemas::published();
+    let upcasters = domains::upcasters();
+
+    failures.extend(unchanged_versions(&published, &registry));
+
+    for schema in &registry {
+        let current = published.iter().find(|published| {
+            published.event_type == schema.event_type
+                && published.event_version == schema.event_version
+        });
+
+        if let Some(current) = current {
+            failures.extend(report(
+                &schema.event_type,
+                schemas::check_compatibility(&current.schema, &schema.schema),
+            ));
+
+            continue;
+        }
+
+        let Some(previous) =
+            schemas::previous(&published, &schema.event_type, &schema.event_version)
+        else {
+            println!(
+                "{} {} has not been published yet",
+                schema.event_type, schema.event_version
+            );
+
+            continue;
+        };
+
+        // A bumped version may break compatibility, as long as older events are upcast to it
+        let errors = schemas::check_compatibility(&previous.schema, &schema.schema);
+        let name = format!(
+            "{} {} -> {}",
+            schema.event_type, previous.event_version, schema.event_version
+        );
+
+        if upcasters.can_upcast(&schema.event_type, &previous.event_version) {
+            for error in errors {
+                println!("{name} {error}, handled by an upcaster");
+            }
+        } else {
+            failures.extend(report(&name, errors));
+        }
+    }
+
+    failures.extend(removed_event_types(&published, &registry, &upcasters));
+
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "Found {} problem(s) with the event schemas. Bump the event version and register an \
+             upcaster for backward-incompatible changes, and only bump it for changes to the \
+             schemas.",
+            failures.len()
+        ));
+    }
+
+    println!("All event schemas are compatible");
+
+    Ok(())
+}
+
+/// The latest version of each entity's current event types that was published before it, with
+/// the current event version
+fn previous_versions<'a>(
+    published: &'a [EventSchema],
+    registry: &'a [EventSchema],
+) -> BTreeMap<&'a str, (&'a str, &'a str)> {
+    let mut versions = BTreeMap::new();
+
+    for schema in registry {
+        let entity = entity_of(&schema.event_type);
+
+        let previous = published
+            .iter()
+            .filter(|published| entity_of(&published.event_type) == entity)
+            .map(|published| published.event_version.as_str())
+            .filter(|published| {
+                schemas::compare_versions(published, &schema.event_version) == Ordering::Less
+            })
+            .max_by(|a, b| schemas::compare_versions(a, b));
+
+        if let Some(previous) = previous {
+            versions.insert(entity, (previous, schema.event_version.as_str()));
+        }
+    }
+
+    versions
+}
+
+/// The event types published at the version before the current one that no longer exist, at the
+/// current version
+fn removed_since_previous(published: &[EventSchema], registry: &[EventSchema]) -> Vec<EventSchema> {
+    let mut removed = Vec::new();
+
+    for (entity, (previous, current)) in previous_versions(published, registry) {
+        for schema in published.iter().filter(|schema| {
+            entity_of(&schema.event_type) == entity && schema.event_version == previous
+        }) {
+            if !registry.iter().any(|r| r.event_type == schema.event_type) {
+                removed.push(EventSchema {
+                    event_type: schema.event_type.clone(),
+                    event_version: current.to_string(),
+                    schema: Value::Bool(false),
+                });
+            }
+        }
+    }
+
+    removed
+}
+
+/// Find entities whose current event version doesn't change any of their event schemas
+fn unchanged_versions(published: &[EventSchema], registry: &[EventSchema]) -> Vec<String> {
+    let mut failures = Vec::new();
+    let removed = removed_since_previous(published, registry);
+
+    for (entity, (previous, current)) in previous_versions(published, registry) {
+        let changed = registry
+            .iter()
+            .filter(|schema| entity_of(&schema.event_type) == entity)
+            .any(|schema| {
+                schemas::previous(published, &schema.event_type, current)
+                    .is_none_or(|previous| previous.unversioned() != schema.unversioned())
+            });
+
+        if changed
+            || removed
+                .iter()
+                .any(|schema| entity_of(&schema.event_type) == entity)
+        {
+            continue;
+        }
+
+        let message = format!(
+            "{entity} {current}: no event schema changed since {previous}, so the event version \
+             shouldn't have been bumped"
+        );
+        eprintln!("{message}");
+        failures.push(message);
+    }
+
+    failures
+}
+
+fn entity_of(event_type: &str) -> &str {
+    event_type
+        .split_once(':')
+        .map_or(event_type, |(entity, _)| entity)
+}
+
+/// Find event types published at the current version, or at the version before it, that no
+/// longer exist and aren't upcast to another event type
+fn removed_event_types(
+    published: &[EventSchema],
+    registry: &[EventSchema],
+    upcasters: &Upcasters,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    let mut versions: Vec<_> = registry
+        .iter()
+        .map(|schema| (schema.path(), &schema.event_version))
+        .filter_map(|(path, version)| Some((path.parent()?.parent()?.to_path_buf(), version)))
+        .collect();
+    versions.sort();
+    versions.dedup();
+
+    for (entity, version) in versions {
+        let in_entity = |schema: &&EventSchema| {
+            schema.path().parent().and_then(Path::parent) == Some(entity.as_path())
+        };
+
+        // Compare against the current version once it's published, or the one it was bumped from
+        let baseline = published
+            .iter()
+            .filter(in_entity)
+            .map(|schema| &schema.event_version)
+            .filter(|published| schemas::compare_versions(published, version) != Ordering::Greater)
+            .max_by(|a, b| schemas::compare_versions(a, b));
+
+        let Some(baseline) = baseline else {
+            continue;
+        };
+
+        for schema in published
+            .iter()
+            .filter(in_entity)
+            .filter(|schema| &schema.event_version == baseline)
+        {
+            let exists = registry
+                .iter()
+                .any(|current| current.event_type == schema.event_type);
+
+            if exists
+                || (baseline != version
+                    && upcasters.can_upcast(&schema.event_type, &schema.event_version))
+            {
+                continue;
+            }
+
+            let message = format!("{}: event type was removed", schema.path().display());
+            eprintln!("{message}");
+            failures.push(message);
+        }
+    }
+
+    failures
+}
+
+fn report(name: &str, errors: Vec<String>) -> Vec<String> {
+    errors
+        .into_iter()
+        .map(|error| {
+            let message = format!("{name} {error}");
+            eprintln!("{message}");
+            message
+        })
+        .collect()
+}
+
+fn read(path: &Path) -> anyhow::Result<Option<Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+fn write(path: &Path, schema: &Value) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, format!("{}\n", serde_json::to_string_pretty(schema)?))
+        .with_context(|| format!("Unable to write {}", path.display()))?;
+
+    println!("Wrote {}", path.display());
+
+    Ok(())
+}
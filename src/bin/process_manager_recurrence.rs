@@ -0,0 +1,33 @@
+//! The recurring Task process manager entry point
+
+use aws_lambda_events::event::kinesis::KinesisEvent;
+use event_driven_architecture::{
+    config::Config,
+    domains::tasks,
+    process_managers::Recurrences,
+    utils::{lambda, metrics, telemetry},
+};
+use lambda_runtime::{service_fn, Error, LambdaEvent};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    lambda::tracing_subscriber_fmt();
+
+    let config = Config::load()?;
+    let sdk_config = config.aws().await;
+    let client = aws_sdk_dynamodb::Client::new(&sdk_config);
+
+    let tasks_repo = tasks::cqrs::init_repo(client.clone(), &config);
+    let tasks_cqrs = tasks::cqrs::init(client, &config, tasks_repo.clone());
+
+    let handler = Recurrences::new(tasks_cqrs, tasks_repo);
+
+    lambda_runtime::run(service_fn(|event: LambdaEvent<KinesisEvent>| async {
+        let response = handler.handle(event).await;
+        telemetry::flush().await;
+        metrics::flush();
+
+        response
+    }))
+    .await
+}
@@ -0,0 +1,51 @@
+//! The saga process manager entry point, invoked by the Kinesis stream and on a fixed schedule
+//! to handle timeouts
+
+use std::sync::Arc;
+
+use event_driven_architecture::{
+    config::Config,
+    domains::{
+        projects,
+        tasks::{self, index::TaskIndex},
+    },
+    process_managers::{
+        saga::{CqrsBus, DynamoSagaStore, SagaRunner, Sagas},
+        ProjectArchive,
+    },
+    utils::{lambda, metrics, telemetry},
+};
+use lambda_runtime::{service_fn, Error, LambdaEvent};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    lambda::tracing_subscriber_fmt();
+
+    let config = Config::load()?;
+    let sdk_config = config.aws().await;
+    let client = aws_sdk_dynamodb::Client::new(&sdk_config);
+
+    let tasks_repo = tasks::cqrs::init_repo(client.clone(), &config);
+    let projects_repo = projects::cqrs::init_repo(client.clone(), &config);
+
+    let bus = Arc::new(CqrsBus::new(
+        tasks::cqrs::init(client.clone(), &config, tasks_repo),
+        projects::cqrs::init(client.clone(), &config, projects_repo),
+    ));
+    let store = DynamoSagaStore::init(client.clone(), &config);
+
+    let handler = Sagas::new(vec![Arc::new(SagaRunner::new(
+        ProjectArchive::new(TaskIndex::init(client, &config)),
+        store,
+        bus,
+    ))]);
+
+    lambda_runtime::run(service_fn(|event: LambdaEvent<serde_json::Value>| async {
+        let response = handler.handle(event).await;
+        telemetry::flush().await;
+        metrics::flush();
+
+        response
+    }))
+    .await
+}
@@ -0,0 +1,30 @@
+//! The Task Inbox projector entry point
+
+use aws_lambda_events::event::kinesis::KinesisEvent;
+use event_driven_architecture::{
+    config::Config,
+    domains::tasks::inbox::Inbox,
+    projectors::inbox::InboxProjector,
+    utils::{lambda, metrics, telemetry},
+};
+use lambda_runtime::{service_fn, Error, LambdaEvent};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    lambda::tracing_subscriber_fmt();
+
+    let config = Config::load()?;
+    let sdk_config = config.aws().await;
+    let dynamodb_client = aws_sdk_dynamodb::Client::new(&sdk_config);
+
+    let handler = InboxProjector::new(Inbox::init(dynamodb_client, &config));
+
+    lambda_runtime::run(service_fn(|event: LambdaEvent<KinesisEvent>| async {
+        let response = handler.handle(event).await;
+        telemetry::flush().await;
+        metrics::flush();
+
+        response
+    }))
+    .await
+}
@@ -1,21 +1,53 @@
 //! The S3 Audit projector entry point
 
-use aws_config::BehaviorVersion;
+use std::sync::Arc;
+
 use aws_lambda_events::event::kinesis::KinesisEvent;
-use event_driven_architecture::{projectors::s3_audit::S3Audit, utils::lambda};
+use event_driven_architecture::{
+    config::Config,
+    domains::shredding::Shredder,
+    projectors::{objects::S3ObjectStore, s3_audit::S3Audit},
+    runners::{KinesisStream, Runner},
+    utils::{lambda, metrics, telemetry},
+};
 use lambda_runtime::{service_fn, Error, LambdaEvent};
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     lambda::tracing_subscriber_fmt();
 
-    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let s3_client = aws_sdk_s3::Client::new(&config);
+    let config = Config::load()?;
+    let sdk_config = config.aws().await;
+    let s3_client = aws_sdk_s3::Client::new(&sdk_config);
+
+    let dynamodb_client = aws_sdk_dynamodb::Client::new(&sdk_config);
+
+    let handler = S3Audit::new(
+        Arc::new(S3ObjectStore::new(s3_client, config.audit_bucket_name()?)),
+        Shredder::init(dynamodb_client, &config),
+    );
+
+    if lambda::is_running_on_lambda() {
+        return lambda_runtime::run(service_fn(|event: LambdaEvent<KinesisEvent>| async {
+            let response = handler.handle(event).await;
+            telemetry::flush().await;
+            metrics::flush();
+
+            response
+        }))
+        .await;
+    }
+
+    // Poll the event stream directly, so the pipeline runs without Lambda
+    let stream = KinesisStream::new(&sdk_config, config.event_stream_name()?);
+    let mut runner = Runner::new("projector_s3_audit", stream, &config)?;
+
+    tokio::select! {
+        result = runner.run(|event| handler.handle(event)) => result?,
+        _ = tokio::signal::ctrl_c() => {},
+    }
 
-    let handler = S3Audit::new(s3_client);
+    telemetry::shutdown().await;
 
-    lambda_runtime::run(service_fn(|event: LambdaEvent<KinesisEvent>| async {
-        handler.handle(event).await
-    }))
-    .await
+    Ok(())
 }
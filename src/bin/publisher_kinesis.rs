@@ -1,20 +1,50 @@
 //! The Kinesis publisher entry point
 
-use aws_config::BehaviorVersion;
 use aws_lambda_events::event::dynamodb::Event;
-use event_driven_architecture::{publishers, utils::lambda};
+use event_driven_architecture::{
+    config::Config,
+    domains::shredding::Shredder,
+    publishers,
+    runners::{DynamoDbStream, Runner},
+    utils::{lambda, metrics, telemetry},
+};
 use lambda_runtime::{service_fn, Error, LambdaEvent};
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     lambda::tracing_subscriber_fmt();
 
-    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let kinesis_client = aws_sdk_kinesis::Client::new(&config);
-    let handler = publishers::Kinesis::new(kinesis_client);
+    let config = Config::load()?;
+    let sdk_config = config.aws().await;
+    let kinesis_client = aws_sdk_kinesis::Client::new(&sdk_config);
+    let dynamodb_client = aws_sdk_dynamodb::Client::new(&sdk_config);
+    let handler = publishers::Kinesis::new(
+        kinesis_client,
+        config.event_stream_name()?.to_string(),
+        Shredder::init(dynamodb_client, &config),
+    );
 
-    lambda_runtime::run(service_fn(|event: LambdaEvent<Event>| async {
-        handler.handle(event).await
-    }))
-    .await
+    if lambda::is_running_on_lambda() {
+        return lambda_runtime::run(service_fn(|event: LambdaEvent<Event>| async {
+            let response = handler.handle(event).await;
+            telemetry::flush().await;
+            metrics::flush();
+
+            response
+        }))
+        .await;
+    }
+
+    // Poll the event log's stream directly, so the pipeline runs without Lambda
+    let stream = DynamoDbStream::for_table(&sdk_config, &config.tables.event_log).await?;
+    let mut runner = Runner::new("publisher_kinesis", stream, &config)?;
+
+    tokio::select! {
+        result = runner.run(|event| handler.handle(event)) => result?,
+        _ = tokio::signal::ctrl_c() => {},
+    }
+
+    telemetry::shutdown().await;
+
+    Ok(())
 }
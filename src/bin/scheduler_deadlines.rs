@@ -0,0 +1,32 @@
+//! The Task deadline scheduler entry point, invoked on a fixed schedule
+
+use event_driven_architecture::{
+    config::Config,
+    domains::tasks::{self, schedule::DynamoSchedule},
+    schedulers::Deadlines,
+    utils::{lambda, metrics, telemetry},
+};
+use lambda_runtime::{service_fn, Error, LambdaEvent};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    lambda::tracing_subscriber_fmt();
+
+    let config = Config::load()?;
+    let sdk_config = config.aws().await;
+    let client = aws_sdk_dynamodb::Client::new(&sdk_config);
+
+    let tasks_repo = tasks::cqrs::init_repo(client.clone(), &config);
+    let tasks_cqrs = tasks::cqrs::init(client.clone(), &config, tasks_repo);
+
+    let handler = Deadlines::new(tasks_cqrs, DynamoSchedule::init(client, &config));
+
+    lambda_runtime::run(service_fn(|event: LambdaEvent<serde_json::Value>| async {
+        let response = handler.handle(event).await;
+        telemetry::flush().await;
+        metrics::flush();
+
+        response
+    }))
+    .await
+}
@@ -0,0 +1,710 @@
+use std::{env, fmt, fs, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
+
+use aws_config::{BehaviorVersion, SdkConfig};
+use serde::Deserialize;
+
+/// The environment variable naming an optional JSON config file
+pub const CONFIG_FILE_VAR: &str = "CONFIG_FILE";
+
+/// The settings shared by the API and every Lambda function
+///
+/// Settings are read from the JSON file named by `CONFIG_FILE`, if there is one, and then from
+/// the environment, which takes precedence. Anything missing from both falls back to the
+/// defaults for a local `dev` deployment, except that the table names must be given whenever
+/// `ENV` isn't `local`. Values are validated as they're loaded, so a typo in a deployment fails
+/// at startup rather than on the first request that needs it.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The deployment environment (`ENV`), which prefixes the API routes unless it's `local`
+    pub environment: String,
+
+    /// The LocalStack endpoint (`LOCALSTACK_ENDPOINT`) AWS clients connect to instead of AWS
+    pub localstack_endpoint: Option<String>,
+
+    /// The address the API listens on when it isn't running on Lambda (`BIND_ADDRESS`)
+    pub bind_address: SocketAddr,
+
+    /// How long the API waits for in-flight requests to finish once it's told to shut down, in
+    /// seconds (`SHUTDOWN_DRAIN_TIMEOUT_SECONDS`)
+    pub shutdown_drain_timeout_seconds: u64,
+
+    /// Where the local stream runners keep their checkpoints (`LOCAL_CHECKPOINT_DIR`)
+    pub checkpoint_dir: PathBuf,
+
+    /// Whether the API also runs the publisher and the projectors in-process, connected by an
+    /// in-memory stream (`ALL_IN_ONE`)
+    pub all_in_one: bool,
+
+    /// Where the audit projector writes events when it runs in-process (`LOCAL_AUDIT_DIR`)
+    pub audit_dir: PathBuf,
+
+    /// How many events are committed between aggregate snapshots (`SNAPSHOT_SIZE`)
+    pub snapshot_size: usize,
+
+    /// How long a deleted Task can still be restored, in days
+    /// (`TASK_RESTORE_GRACE_PERIOD_DAYS`), or the domain's default when unset
+    pub task_restore_grace_period_days: Option<i64>,
+
+    /// The Kinesis stream Domain Events are published to (`EVENT_STREAM_NAME`)
+    pub event_stream_name: Option<String>,
+
+    /// The S3 bucket Domain Events are audited in (`AUDIT_BUCKET_NAME`)
+    pub audit_bucket_name: Option<String>,
+
+    /// The DynamoDB tables
+    pub tables: Tables,
+
+    /// How the API authenticates bearer tokens
+    pub auth: Auth,
+}
+
+/// The names of the DynamoDB tables, which are left empty until they're given or filled in with
+/// the local `dev` tables
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tables {
+    /// The event log (`EVENT_LOG_TABLE_NAME`)
+    pub event_log: String,
+
+    /// Aggregate snapshots (`EVENT_SNAPSHOTS_TABLE_NAME`)
+    pub event_snapshots: String,
+
+    /// The Task view (`TASKS_VIEW_TABLE_NAME`)
+    pub tasks_view: String,
+
+    /// The Task search index (`TASKS_INDEX_TABLE_NAME`)
+    pub tasks_index: String,
+
+    /// The Task activity feed (`TASKS_ACTIVITY_TABLE_NAME`)
+    pub tasks_activity: String,
+
+    /// Each subject's assigned Tasks (`TASKS_INBOX_TABLE_NAME`)
+    pub tasks_inbox: String,
+
+    /// Task deadlines and reminders (`TASKS_SCHEDULE_TABLE_NAME`)
+    pub tasks_schedule: String,
+
+    /// The Project view (`PROJECTS_VIEW_TABLE_NAME`)
+    pub projects_view: String,
+
+    /// Per-aggregate encryption keys for sensitive fields (`ENCRYPTION_KEYS_TABLE_NAME`)
+    pub encryption_keys: String,
+
+    /// Saga instances (`SAGAS_TABLE_NAME`)
+    pub sagas: String,
+}
+
+/// How the API authenticates bearer tokens
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Auth {
+    /// The identity provider's published signing keys (`AUTH_JWKS_URL`)
+    pub jwks_url: Option<String>,
+
+    /// A shared secret for HS256 tokens, for local development and tests (`AUTH_STATIC_SECRET`)
+    pub static_secret: Option<String>,
+
+    /// The issuer tokens must have, if any (`AUTH_ISSUER`)
+    pub issuer: Option<String>,
+
+    /// The audience tokens must have, if any (`AUTH_AUDIENCE`)
+    pub audience: Option<String>,
+}
+
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auth")
+            .field("jwks_url", &self.jwks_url)
+            .field("static_secret", &self.static_secret.as_ref().map(|_| ".."))
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .finish()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            environment: "local".to_string(),
+            localstack_endpoint: None,
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            shutdown_drain_timeout_seconds: 30,
+            checkpoint_dir: PathBuf::from(".checkpoints"),
+            all_in_one: false,
+            audit_dir: PathBuf::from(".audit"),
+            snapshot_size: 5,
+            task_restore_grace_period_days: None,
+            event_stream_name: None,
+            audit_bucket_name: None,
+            tables: Tables::default(),
+            auth: Auth::default(),
+        }
+    }
+}
+
+impl Tables {
+    /// The tables of the local `dev` deployment
+    fn local() -> Self {
+        let table = |name: &str| format!("event-driven-dev-{name}");
+
+        Self {
+            event_log: table("event-log"),
+            event_snapshots: table("event-snapshots"),
+            tasks_view: table("tasks-view"),
+            tasks_index: table("tasks-index"),
+            tasks_activity: table("tasks-activity"),
+            tasks_inbox: table("tasks-inbox"),
+            tasks_schedule: table("tasks-schedule"),
+            projects_view: table("projects-view"),
+            encryption_keys: table("encryption-keys"),
+            sagas: table("sagas"),
+        }
+    }
+
+    /// Each table with its environment variable
+    fn named(&self) -> [(&'static str, &String); 10] {
+        [
+            ("EVENT_LOG_TABLE_NAME", &self.event_log),
+            ("EVENT_SNAPSHOTS_TABLE_NAME", &self.event_snapshots),
+            ("TASKS_VIEW_TABLE_NAME", &self.tasks_view),
+            ("TASKS_INDEX_TABLE_NAME", &self.tasks_index),
+            ("TASKS_ACTIVITY_TABLE_NAME", &self.tasks_activity),
+            ("TASKS_INBOX_TABLE_NAME", &self.tasks_inbox),
+            ("TASKS_SCHEDULE_TABLE_NAME", &self.tasks_schedule),
+            ("PROJECTS_VIEW_TABLE_NAME", &self.projects_view),
+            ("ENCRYPTION_KEYS_TABLE_NAME", &self.encryption_keys),
+            ("SAGAS_TABLE_NAME", &self.sagas),
+        ]
+    }
+
+    /// Use the local `dev` table for any table that wasn't given
+    fn or_local(&mut self) {
+        let local = Self::local();
+
+        for (table, default) in [
+            (&mut self.event_log, local.event_log),
+            (&mut self.event_snapshots, local.event_snapshots),
+            (&mut self.tasks_view, local.tasks_view),
+            (&mut self.tasks_index, local.tasks_index),
+            (&mut self.tasks_activity, local.tasks_activity),
+            (&mut self.tasks_inbox, local.tasks_inbox),
+            (&mut self.tasks_schedule, local.tasks_schedule),
+            (&mut self.projects_view, local.projects_view),
+            (&mut self.encryption_keys, local.encryption_keys),
+            (&mut self.sagas, local.sagas),
+        ] {
+            if table.is_empty() {
+                *table = default;
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from the optional `CONFIG_FILE` and the environment, and validate it
+    pub fn load() -> Result<Self, Error> {
+        Self::load_from(&|name| env::var(name).ok())
+    }
+
+    /// Load the config with environment variables looked up by `env`
+    fn load_from(env: &Env) -> Result<Self, Error> {
+        let mut config = match var(env, CONFIG_FILE_VAR) {
+            Some(path) => Self::from_file(PathBuf::from(path))?,
+            None => Self::default(),
+        };
+
+        config.apply_env(env)?;
+
+        if config.is_local() {
+            config.tables.or_local();
+        }
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn from_file(path: PathBuf) -> Result<Self, Error> {
+        let contents = fs::read_to_string(&path).map_err(|source| Error::File {
+            path: path.clone(),
+            source,
+        })?;
+
+        serde_json::from_str(&contents).map_err(|source| Error::Parse { path, source })
+    }
+
+    /// Override the file's settings with any that are set in the environment
+    fn apply_env(&mut self, env: &Env) -> Result<(), Error> {
+        set(env, &mut self.environment, "ENV");
+        set_optional(env, &mut self.localstack_endpoint, "LOCALSTACK_ENDPOINT");
+        set_parsed(env, &mut self.bind_address, "BIND_ADDRESS")?;
+        set_parsed(
+            env,
+            &mut self.shutdown_drain_timeout_seconds,
+            "SHUTDOWN_DRAIN_TIMEOUT_SECONDS",
+        )?;
+        set_parsed(env, &mut self.checkpoint_dir, "LOCAL_CHECKPOINT_DIR")?;
+        set_parsed(env, &mut self.all_in_one, "ALL_IN_ONE")?;
+        set_parsed(env, &mut self.audit_dir, "LOCAL_AUDIT_DIR")?;
+        set_parsed(env, &mut self.snapshot_size, "SNAPSHOT_SIZE")?;
+        set_optional_parsed(
+            env,
+            &mut self.task_restore_grace_period_days,
+            "TASK_RESTORE_GRACE_PERIOD_DAYS",
+        )?;
+        set_optional(env, &mut self.event_stream_name, "EVENT_STREAM_NAME");
+        set_optional(env, &mut self.audit_bucket_name, "AUDIT_BUCKET_NAME");
+
+        let tables = &mut self.tables;
+        set(env, &mut tables.event_log, "EVENT_LOG_TABLE_NAME");
+        set(
+            env,
+            &mut tables.event_snapshots,
+            "EVENT_SNAPSHOTS_TABLE_NAME",
+        );
+        set(env, &mut tables.tasks_view, "TASKS_VIEW_TABLE_NAME");
+        set(env, &mut tables.tasks_index, "TASKS_INDEX_TABLE_NAME");
+        set(env, &mut tables.tasks_activity, "TASKS_ACTIVITY_TABLE_NAME");
+        set(env, &mut tables.tasks_inbox, "TASKS_INBOX_TABLE_NAME");
+        set(env, &mut tables.tasks_schedule, "TASKS_SCHEDULE_TABLE_NAME");
+        set(env, &mut tables.projects_view, "PROJECTS_VIEW_TABLE_NAME");
+        set(
+            env,
+            &mut tables.encryption_keys,
+            "ENCRYPTION_KEYS_TABLE_NAME",
+        );
+        set(env, &mut tables.sagas, "SAGAS_TABLE_NAME");
+
+        let auth = &mut self.auth;
+        set_optional(env, &mut auth.jwks_url, "AUTH_JWKS_URL");
+        set_optional(env, &mut auth.static_secret, "AUTH_STATIC_SECRET");
+        set_optional(env, &mut auth.issuer, "AUTH_ISSUER");
+        set_optional(env, &mut auth.audience, "AUTH_AUDIENCE");
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let valid_segment = |value: &str| {
+            !value.is_empty()
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+
+        if !valid_segment(&self.environment) {
+            return Err(Error::invalid(
+                "ENV",
+                &self.environment,
+                "expected letters, digits, '-' or '_'",
+            ));
+        }
+
+        if let Some(endpoint) = &self.localstack_endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                return Err(Error::invalid(
+                    "LOCALSTACK_ENDPOINT",
+                    endpoint,
+                    "expected an http:// or https:// URL",
+                ));
+            }
+        }
+
+        if self.snapshot_size == 0 {
+            return Err(Error::invalid("SNAPSHOT_SIZE", "0", "expected at least 1"));
+        }
+
+        if let Some(days) = self.task_restore_grace_period_days {
+            if days < 0 {
+                return Err(Error::invalid(
+                    "TASK_RESTORE_GRACE_PERIOD_DAYS",
+                    &days.to_string(),
+                    "expected zero or more days",
+                ));
+            }
+        }
+
+        for (name, value) in [
+            ("EVENT_STREAM_NAME", &self.event_stream_name),
+            ("AUDIT_BUCKET_NAME", &self.audit_bucket_name),
+            ("AUTH_STATIC_SECRET", &self.auth.static_secret),
+            ("AUTH_ISSUER", &self.auth.issuer),
+            ("AUTH_AUDIENCE", &self.auth.audience),
+        ] {
+            if value.as_deref().is_some_and(str::is_empty) {
+                return Err(Error::invalid(name, "", "expected a name"));
+            }
+        }
+
+        if let Some(url) = &self.auth.jwks_url {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(Error::invalid(
+                    "AUTH_JWKS_URL",
+                    url,
+                    "expected an http:// or https:// URL",
+                ));
+            }
+        }
+
+        for (name, value) in self.tables.named() {
+            // Deployments must name their own tables, rather than falling back to `dev`'s
+            if value.is_empty() {
+                return Err(Error::Missing(name));
+            }
+
+            // DynamoDB table names are 3 to 255 of these characters
+            let valid = (3..=255).contains(&value.len())
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+            if !valid {
+                return Err(Error::invalid(
+                    name,
+                    value,
+                    "expected a DynamoDB table name of 3 to 255 letters, digits, '-', '_' or '.'",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The Kinesis stream to publish to, which the publisher can't run without
+    pub fn event_stream_name(&self) -> Result<&str, Error> {
+        self.event_stream_name
+            .as_deref()
+            .ok_or(Error::Missing("EVENT_STREAM_NAME"))
+    }
+
+    /// The S3 bucket to audit events in, which the audit projector can't run without
+    pub fn audit_bucket_name(&self) -> Result<&str, Error> {
+        self.audit_bucket_name
+            .as_deref()
+            .ok_or(Error::Missing("AUDIT_BUCKET_NAME"))
+    }
+
+    /// How long the API waits for in-flight requests to finish once it's told to shut down
+    pub fn shutdown_drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_drain_timeout_seconds)
+    }
+
+    /// Whether the API is served from the root, rather than under the environment's name
+    pub fn is_local(&self) -> bool {
+        self.environment == "local"
+    }
+
+    /// Load the AWS SDK config, connecting to LocalStack when an endpoint is configured
+    pub async fn aws(&self) -> SdkConfig {
+        let mut config = aws_config::defaults(BehaviorVersion::latest());
+
+        if let Some(endpoint) = &self.localstack_endpoint {
+            config = config.endpoint_url(endpoint);
+        }
+
+        config.load().await
+    }
+}
+
+/// Looks up an environment variable
+type Env = dyn Fn(&str) -> Option<String>;
+
+/// A non-empty environment variable
+fn var(env: &Env, name: &str) -> Option<String> {
+    env(name).filter(|value| !value.is_empty())
+}
+
+fn set(env: &Env, field: &mut String, name: &str) {
+    if let Some(value) = var(env, name) {
+        *field = value;
+    }
+}
+
+fn set_optional(env: &Env, field: &mut Option<String>, name: &str) {
+    if let Some(value) = var(env, name) {
+        *field = Some(value);
+    }
+}
+
+fn set_parsed<T: FromStr>(env: &Env, field: &mut T, name: &'static str) -> Result<(), Error>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Some(value) = var(env, name) {
+        *field = value
+            .parse()
+            .map_err(|error: T::Err| Error::invalid(name, &value, &error.to_string()))?;
+    }
+
+    Ok(())
+}
+
+fn set_optional_parsed<T: FromStr>(
+    env: &Env,
+    field: &mut Option<T>,
+    name: &'static str,
+) -> Result<(), Error>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Some(value) = var(env, name) {
+        *field = Some(
+            value
+                .parse()
+                .map_err(|error: T::Err| Error::invalid(name, &value, &error.to_string()))?,
+        );
+    }
+
+    Ok(())
+}
+
+/// Config errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The config file couldn't be read
+    #[error("Unable to read the config file {path:?}: {source}")]
+    File {
+        /// The path from `CONFIG_FILE`
+        path: PathBuf,
+
+        /// The underlying error
+        source: std::io::Error,
+    },
+
+    /// The config file isn't valid
+    #[error("Unable to parse the config file {path:?}: {source}")]
+    Parse {
+        /// The path from `CONFIG_FILE`
+        path: PathBuf,
+
+        /// The underlying error
+        source: serde_json::Error,
+    },
+
+    /// A setting has a value that can't be used
+    #[error("Invalid {name} {value:?}: {reason}")]
+    Invalid {
+        /// The setting's environment variable
+        name: &'static str,
+
+        /// The value given
+        value: String,
+
+        /// What was expected instead
+        reason: String,
+    },
+
+    /// A setting this process needs wasn't given
+    #[error("{0} is required, but isn't set")]
+    Missing(&'static str),
+}
+
+impl Error {
+    fn invalid(name: &'static str, value: &str, reason: &str) -> Self {
+        Self::Invalid {
+            name,
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn load(vars: &[(&str, &str)]) -> Result<Config, Error> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        Config::load_from(&move |name| vars.get(name).cloned())
+    }
+
+    /// A config file under the temporary directory, removed when it's dropped
+    struct ConfigFile(PathBuf);
+
+    impl ConfigFile {
+        fn new(contents: &str) -> Self {
+            let path = env::temp_dir().join(format!("config-{}.json", ulid::Ulid::new()));
+            fs::write(&path, contents).unwrap();
+
+            Self(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for ConfigFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// The field each table is set with in a config file, and the name it's given in a
+    /// deployment, such as `event_log` and `prod-event-log`, by environment variable
+    fn tables(environment: &str) -> Vec<(&'static str, String, String)> {
+        Tables::default()
+            .named()
+            .into_iter()
+            .map(|(name, _)| {
+                let field = name.trim_end_matches("_TABLE_NAME").to_lowercase();
+                let table = format!("{environment}-{}", field.replace('_', "-"));
+
+                (name, field, table)
+            })
+            .collect()
+    }
+
+    /// The environment variables naming every table in a deployment
+    fn table_vars<'a>(
+        tables: &'a [(&'static str, String, String)],
+    ) -> Vec<(&'static str, &'a str)> {
+        tables
+            .iter()
+            .map(|(name, _, table)| (*name, table.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn local_development_needs_no_settings() {
+        let config = load(&[]).unwrap();
+
+        assert!(config.is_local());
+        assert_eq!(
+            config.bind_address,
+            SocketAddr::from(([127, 0, 0, 1], 3000))
+        );
+        assert_eq!(config.tables.event_log, "event-driven-dev-event-log");
+        assert_eq!(config.tables.sagas, "event-driven-dev-sagas");
+    }
+
+    #[test]
+    fn the_environment_overrides_the_file() {
+        let file = ConfigFile::new(
+            &json!({
+                "environment": "staging",
+                "bind_address": "0.0.0.0:8080",
+                "snapshot_size": 10,
+                "event_stream_name": "staging-events",
+                "tables": tables("staging")
+                    .into_iter()
+                    .map(|(_, field, table)| (field, table))
+                    .collect::<HashMap<_, _>>(),
+            })
+            .to_string(),
+        );
+
+        let config = load(&[
+            (CONFIG_FILE_VAR, file.path()),
+            ("SNAPSHOT_SIZE", "20"),
+            ("EVENT_LOG_TABLE_NAME", "staging-event-log-v2"),
+            // Empty variables are treated as unset
+            ("EVENT_STREAM_NAME", ""),
+        ])
+        .unwrap();
+
+        assert_eq!(config.environment, "staging");
+        assert_eq!(config.bind_address, SocketAddr::from(([0, 0, 0, 0], 8080)));
+        assert_eq!(config.snapshot_size, 20);
+        assert_eq!(config.event_stream_name().unwrap(), "staging-events");
+        assert_eq!(config.tables.event_log, "staging-event-log-v2");
+        assert_eq!(config.tables.sagas, "staging-sagas");
+    }
+
+    #[test]
+    fn deployments_must_name_their_tables() {
+        assert!(matches!(
+            load(&[("ENV", "prod")]),
+            Err(Error::Missing("EVENT_LOG_TABLE_NAME"))
+        ));
+
+        let tables = tables("prod");
+        let mut vars = table_vars(&tables);
+        vars.push(("ENV", "prod"));
+
+        let config = load(&vars).unwrap();
+        assert_eq!(config.tables.event_log, "prod-event-log");
+        assert_eq!(config.tables.tasks_index, "prod-tasks-index");
+    }
+
+    #[test]
+    fn the_stream_and_bucket_are_required_where_theyre_used() {
+        let tables = tables("prod");
+        let mut vars = table_vars(&tables);
+        vars.push(("ENV", "prod"));
+
+        let config = load(&vars).unwrap();
+        assert!(matches!(
+            config.event_stream_name(),
+            Err(Error::Missing("EVENT_STREAM_NAME"))
+        ));
+        assert!(matches!(
+            config.audit_bucket_name(),
+            Err(Error::Missing("AUDIT_BUCKET_NAME"))
+        ));
+
+        vars.extend([
+            ("EVENT_STREAM_NAME", "prod-events"),
+            ("AUDIT_BUCKET_NAME", "prod-audit"),
+        ]);
+
+        let config = load(&vars).unwrap();
+        assert_eq!(config.event_stream_name().unwrap(), "prod-events");
+        assert_eq!(config.audit_bucket_name().unwrap(), "prod-audit");
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        for (name, value) in [
+            ("BIND_ADDRESS", "localhost"),
+            ("BIND_ADDRESS", "127.0.0.1:http"),
+            ("SNAPSHOT_SIZE", "0"),
+            ("SNAPSHOT_SIZE", "-1"),
+            ("ENV", "prod/eu"),
+            ("TASKS_VIEW_TABLE_NAME", "t!"),
+        ] {
+            assert!(
+                matches!(
+                    load(&[(name, value)]),
+                    Err(Error::Invalid { name: invalid, .. }) if invalid == name
+                ),
+                "{name}={value}"
+            );
+        }
+    }
+
+    #[test]
+    fn config_files_must_be_readable() {
+        let missing = env::temp_dir().join(format!("config-{}.json", ulid::Ulid::new()));
+
+        assert!(matches!(
+            load(&[(CONFIG_FILE_VAR, missing.to_str().unwrap())]),
+            Err(Error::File { path, .. }) if path == missing
+        ));
+
+        for contents in [
+            "{",
+            r#"{ "snapshot_size": "ten" }"#,
+            r#"{ "snapshots": 10 }"#,
+        ] {
+            let file = ConfigFile::new(contents);
+
+            assert!(
+                matches!(
+                    load(&[(CONFIG_FILE_VAR, file.path())]),
+                    Err(Error::Parse { .. })
+                ),
+                "{contents}"
+            );
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::convert::Infallible;
+
+use aide::{
+    gen::GenContext,
+    openapi::{Operation, Parameter, ParameterData, ParameterSchemaOrContent, SchemaObject},
+    operation::add_parameters,
+    OperationInput,
+};
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{request::Parts, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::{global, propagation::Extractor};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use ulid::Ulid;
+
+/// The header a caller can use to choose the correlation ID for a request
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The W3C Trace Context header, whose trace ID is used when there's no `X-Request-Id`
+pub const TRACEPARENT_HEADER: HeaderName = HeaderName::from_static("traceparent");
+
+/// The response header carrying the correlation ID
+pub const CORRELATION_ID_HEADER: HeaderName = HeaderName::from_static("x-correlation-id");
+
+/// The response header carrying the causation ID
+pub const CAUSATION_ID_HEADER: HeaderName = HeaderName::from_static("x-causation-id");
+
+/// The longest `X-Request-Id` accepted as a correlation ID
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// The IDs that tie a request to everything that happens because of it
+///
+/// The correlation ID is shared by every command, event, and reaction that follows from a single
+/// user action. It comes from the `X-Request-Id` header, or the trace ID of a `traceparent`
+/// header, and is generated when neither is given. The causation ID identifies this request, as
+/// the direct cause of the commands it issues.
+#[derive(Clone, Debug)]
+pub struct Correlation {
+    /// The ID shared by everything that follows from the user action
+    pub correlation_id: String,
+
+    /// The ID of the request itself
+    pub causation_id: String,
+}
+
+impl Correlation {
+    /// Read the correlation ID from the request headers, generating one if there's none
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let causation_id = Ulid::new().to_string();
+
+        let correlation_id = request_id(headers)
+            .or_else(|| trace_id(headers))
+            .unwrap_or_else(|| causation_id.clone());
+
+        Correlation {
+            correlation_id,
+            causation_id,
+        }
+    }
+}
+
+/// A usable `X-Request-Id`, which is any short run of visible ASCII characters
+fn request_id(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+
+    let valid = !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LENGTH
+        && value.chars().all(|c| c.is_ascii_graphic());
+
+    valid.then(|| value.to_string())
+}
+
+/// The trace ID from a `traceparent` header, formatted as `version-trace_id-parent_id-flags`
+fn trace_id(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(TRACEPARENT_HEADER)?.to_str().ok()?;
+    let trace_id = value.trim().split('-').nth(1)?;
+
+    let valid = trace_id.len() == 32
+        && trace_id.chars().all(|c| c.is_ascii_hexdigit())
+        && trace_id.chars().any(|c| c != '0');
+
+    valid.then(|| trace_id.to_ascii_lowercase())
+}
+
+/// Reads W3C trace context from request headers
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key)?.to_str().ok()
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// Attach a Correlation to every request, log within its span, and return its IDs in the
+/// response headers. The span continues the caller's trace when a `traceparent` is given.
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let correlation = Correlation::from_headers(request.headers());
+
+    request.extensions_mut().insert(correlation.clone());
+
+    let span = tracing::info_span!(
+        "http.request",
+        method = %request.method(),
+        path = request.uri().path(),
+        correlation_id = correlation.correlation_id,
+        causation_id = correlation.causation_id,
+    );
+
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    span.set_parent(parent);
+
+    let mut response = next.run(request).instrument(span).await;
+
+    let headers = response.headers_mut();
+
+    for (name, value) in [
+        (CORRELATION_ID_HEADER, &correlation.correlation_id),
+        (CAUSATION_ID_HEADER, &correlation.causation_id),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(name, value);
+        }
+    }
+
+    response
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Correlation {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // The middleware attaches one to every request, but fall back to the headers without it
+        Ok(parts
+            .extensions
+            .get::<Correlation>()
+            .cloned()
+            .unwrap_or_else(|| Correlation::from_headers(&parts.headers)))
+    }
+}
+
+impl OperationInput for Correlation {
+    fn operation_input(ctx: &mut GenContext, operation: &mut Operation) {
+        let schema = ctx.schema.subschema_for::<String>();
+
+        let header = |name: HeaderName, description: &str| Parameter::Header {
+            parameter_data: ParameterData {
+                name: name.to_string(),
+                description: Some(description.to_string()),
+                required: false,
+                format: ParameterSchemaOrContent::Schema(SchemaObject {
+                    json_schema: schema.clone(),
+                    example: None,
+                    external_docs: None,
+                }),
+                extensions: Default::default(),
+                deprecated: None,
+                example: None,
+                examples: Default::default(),
+                explode: None,
+            },
+            style: Default::default(),
+        };
+
+        add_parameters(
+            ctx,
+            operation,
+            [
+                header(
+                    REQUEST_ID_HEADER,
+                    "The correlation ID to trace this request by, returned as X-Correlation-Id",
+                ),
+                header(
+                    TRACEPARENT_HEADER,
+                    "A W3C Trace Context, whose trace ID is used as the correlation ID when there's \
+                     no X-Request-Id",
+                ),
+            ],
+        );
+    }
+}
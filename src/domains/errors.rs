@@ -14,9 +14,19 @@ pub enum Error {
 
     /// Unauthorized error
     #[error("Unauthorized")]
-    #[allow(dead_code)]
     Unauthorized(#[source] Option<anyhow::Error>),
 
+    /// The command isn't valid for the entity's current state
+    #[error("Invalid state: {reason}")]
+    InvalidState {
+        /// Why the command was rejected
+        reason: String,
+    },
+
+    /// A related entity couldn't be loaded to check the command
+    #[error("Unavailable: {0}")]
+    Unavailable(#[source] anyhow::Error),
+
     /// A uniquness conflict
     #[error("The field `{field}` must be unique")]
     Uniqueness {
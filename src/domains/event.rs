@@ -1,8 +1,12 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
 use derive_new::new;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Domain events formatted in a cosistent way so that they can be shared across teams
-#[derive(Clone, Debug, Serialize, Deserialize, new)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, new)]
 pub struct DomainEvent {
     /// The Aggregate ID
     pub id: String,
@@ -24,4 +28,70 @@ pub struct DomainEvent {
 
     /// The event metadata
     pub metadata: String,
+
+    /// The tenant the Aggregate belongs to (empty for events recorded before multi-tenancy)
+    #[serde(default)]
+    #[new(default)]
+    pub tenant: String,
+
+    /// The ID shared by everything that follows from a single user action (empty for events
+    /// recorded before correlation IDs)
+    #[serde(default)]
+    #[new(default)]
+    pub correlation_id: String,
+
+    /// The ID of the request or event that caused the command which recorded this event (empty
+    /// for events recorded before correlation IDs)
+    #[serde(default)]
+    #[new(default)]
+    pub causation_id: String,
+
+    /// When the event was recorded in the event store, used to measure how far behind its
+    /// consumers are (empty for events published before it was recorded)
+    #[serde(default)]
+    #[new(default)]
+    pub recorded_at: Option<DateTime<Utc>>,
+}
+
+impl DomainEvent {
+    /// Read a single metadata value, such as the `subject` or `command_id`
+    pub fn metadata_value(&self, key: &str) -> Option<String> {
+        serde_json::from_str::<HashMap<String, String>>(&self.metadata)
+            .ok()?
+            .remove(key)
+    }
+
+    /// A reference to this event, used as the causation ID of commands issued in reaction to it
+    pub fn reference(&self) -> String {
+        format!("{}/{}", self.id, self.sequence)
+    }
+
+    /// The correlation ID carried on to commands issued in reaction to this event. Events
+    /// recorded before correlation IDs start a new correlation of their own.
+    pub fn correlation(&self) -> String {
+        if self.correlation_id.is_empty() {
+            self.reference()
+        } else {
+            self.correlation_id.clone()
+        }
+    }
+
+    /// The key Domain Events are partitioned by on the stream, so each tenant's events are ordered
+    /// together. Events recorded before multi-tenancy have no tenant, and keep the original
+    /// partitioning by type.
+    pub fn partition_key(&self) -> &str {
+        if self.tenant.is_empty() {
+            &self.entity
+        } else {
+            &self.tenant
+        }
+    }
+
+    /// The metadata that ties a command issued in reaction to this event back to it
+    pub fn reaction_metadata(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("correlation_id".to_string(), self.correlation()),
+            ("causation_id".to_string(), self.reference()),
+        ])
+    }
 }
@@ -1,13 +1,29 @@
 /// The Tasks domain
 pub mod tasks;
 
+/// The Projects domain, which groups Tasks
+pub mod projects;
+
 /// The Domain Event type
 pub mod event;
 
 /// Domain Errors
 pub mod errors;
 
+/// Event upcasting for schema migrations
+pub mod upcasters;
+
+/// JSON Schemas for published Domain Events
+pub mod schemas;
+
+/// Tenant scoping for aggregates
+pub mod tenants;
+
+/// Crypto-shredding of personal data in events
+pub mod shredding;
+
 pub use errors::Error;
 
 #[allow(unused_imports)]
 pub use event::DomainEvent;
+pub use upcasters::{upcasters, Upcasters};
@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cqrs_es::Aggregate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domains,
+    utils::Update::{Empty, Unchanged, Value},
+};
+
+use super::{Command, Event};
+
+use Command::{Archive, Create, Delete, Unarchive, Update};
+use Event::{Archived, Created, Deleted, Unarchived, Updated};
+
+/// A Project that groups Tasks, as aggregated within the Event Store
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct Project {
+    /// A unique ID
+    pub id: String,
+
+    /// The created date
+    pub created_at: DateTime<Utc>,
+
+    /// The last updated date
+    pub updated_at: DateTime<Utc>,
+
+    /// The subject that created this Project, and is allowed to change it or add Tasks to it
+    pub owner: String,
+
+    /// A name
+    pub name: String,
+
+    /// An optional description
+    pub description: Option<String>,
+
+    /// Whether this Project is closed to new Tasks
+    pub archived: bool,
+
+    /// Whether this Project is is active or has been removed
+    pub deleted: bool,
+}
+
+/// The Aggregate Type constant
+pub const AGGREGATE_TYPE: &str = "Project";
+
+#[async_trait]
+impl Aggregate for Project {
+    type Command = Command;
+    type Event = Event;
+    type Error = domains::Error;
+    type Services = ();
+
+    fn aggregate_type() -> String {
+        AGGREGATE_TYPE.to_string()
+    }
+
+    async fn handle(
+        &self,
+        command: Self::Command,
+        _services: &Self::Services,
+    ) -> Result<Vec<Self::Event>, Self::Error> {
+        match command {
+            Create { id, owner, input } => {
+                self.validate_new()?;
+
+                let created_at = Utc::now();
+
+                Ok(vec![Created {
+                    id: id.clone(),
+                    created_at,
+                    project: Project {
+                        id,
+                        created_at,
+                        updated_at: created_at,
+                        owner,
+                        name: input.name,
+                        description: input.description,
+                        archived: false,
+                        deleted: false,
+                    },
+                }])
+            }
+
+            Update { subject, input } => {
+                self.validate_existing()?;
+                self.authorize(&subject)?;
+
+                Ok(vec![Updated {
+                    id: self.id.clone(),
+                    update: input,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Archive { subject } => {
+                self.validate_existing()?;
+                self.authorize(&subject)?;
+
+                if self.archived {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The Project is already archived".to_string(),
+                    });
+                }
+
+                Ok(vec![Archived {
+                    id: self.id.clone(),
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Unarchive { subject } => {
+                self.validate_existing()?;
+                self.authorize(&subject)?;
+
+                if !self.archived {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The Project isn't archived".to_string(),
+                    });
+                }
+
+                Ok(vec![Unarchived {
+                    id: self.id.clone(),
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Delete { subject } => {
+                self.validate_existing()?;
+                self.authorize(&subject)?;
+
+                Ok(vec![Deleted {
+                    id: self.id.clone(),
+                    updated_at: Utc::now(),
+                }])
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            Created {
+                id,
+                project,
+                created_at,
+            } => {
+                self.id = id;
+                self.created_at = created_at;
+                self.updated_at = created_at;
+                self.owner = project.owner;
+                self.name = project.name;
+                self.description = project.description;
+                self.archived = project.archived;
+                self.deleted = project.deleted;
+            }
+
+            Updated {
+                update, updated_at, ..
+            } => {
+                if let Some(name) = update.name {
+                    self.name = name;
+                }
+
+                match update.description {
+                    Unchanged => {
+                        // Leave unchanged
+                    }
+                    Empty => {
+                        self.description = None;
+                    }
+                    Value(description) => {
+                        self.description = Some(description);
+                    }
+                }
+
+                self.updated_at = updated_at;
+            }
+
+            Archived { updated_at, .. } => {
+                self.archived = true;
+                self.updated_at = updated_at;
+            }
+
+            Unarchived { updated_at, .. } => {
+                self.archived = false;
+                self.updated_at = updated_at;
+            }
+
+            Deleted { updated_at, .. } => {
+                self.deleted = true;
+                self.updated_at = updated_at;
+            }
+        }
+    }
+}
+
+impl Project {
+    /// Return `Forbidden` unless the subject owns this Project
+    pub fn authorize(&self, subject: &str) -> Result<(), domains::Error> {
+        if self.owner == subject {
+            return Ok(());
+        }
+
+        Err(domains::Error::Forbidden)
+    }
+
+    /// Check that Tasks can be added to this Project
+    pub fn validate_accepts_tasks(&self) -> Result<(), domains::Error> {
+        self.validate_existing()?;
+
+        if self.archived {
+            return Err(domains::Error::InvalidState {
+                reason: "Tasks can't be added to an archived Project".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_new(&self) -> Result<(), domains::Error> {
+        if !self.id.is_empty() {
+            // A Project with this ID already exists, so there is a uniqueness conflict
+            return Err(domains::Error::Uniqueness {
+                field: "id".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_existing(&self) -> Result<(), domains::Error> {
+        if self.id.is_empty() || self.deleted {
+            return Err(domains::Error::NotFound {
+                entity: AGGREGATE_TYPE.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::metrics::CommandType;
+
+use super::inputs;
+
+/// Project Aggregate Commands
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Command {
+    /// Create a new Project
+    Create {
+        /// The Project ID to create (auto-generated in the http Create handler)
+        id: String,
+
+        /// The authenticated subject creating the Project, who becomes its owner
+        owner: String,
+
+        /// The Create input
+        input: inputs::Create,
+    },
+
+    /// Update an existing Project
+    Update {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The Update input
+        input: inputs::Update,
+    },
+
+    /// Close a Project to new Tasks
+    Archive {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Open an archived Project to new Tasks again
+    Unarchive {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Remove an existing Project
+    Delete {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+}
+
+impl CommandType for Command {
+    fn command_type(&self) -> &'static str {
+        match self {
+            Command::Create { .. } => "Create",
+            Command::Update { .. } => "Update",
+            Command::Archive { .. } => "Archive",
+            Command::Unarchive { .. } => "Unarchive",
+            Command::Delete { .. } => "Delete",
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use cqrs_es::{
+    persist::{PersistedEventStore, ViewRepository},
+    CqrsFramework,
+};
+use dynamo_es::{DynamoEventRepository, DynamoViewRepository};
+
+use crate::{
+    config::Config,
+    domains::{
+        self,
+        shredding::{Shredder, ShreddingRepository},
+    },
+    utils::{metrics::MeasuredEventStore, telemetry::TracedViewRepository},
+};
+
+use super::{Project, Query, View};
+
+/// The Projects Event Store, which shares the event log and snapshots with every other aggregate
+pub type EventStore =
+    MeasuredEventStore<PersistedEventStore<ShreddingRepository<DynamoEventRepository>, Project>>;
+
+/// Initialize the Projects CqrsFramework
+pub fn init(
+    client: aws_sdk_dynamodb::Client,
+    config: &Config,
+    repo: Arc<Box<dyn ViewRepository<View, Project>>>,
+) -> Arc<CqrsFramework<Project, EventStore>> {
+    init_with_queries(client, config, repo, vec![])
+}
+
+/// Initialize the Projects CqrsFramework with extra queries, which run after the View has been
+/// updated
+pub fn init_with_queries(
+    client: aws_sdk_dynamodb::Client,
+    config: &Config,
+    repo: Arc<Box<dyn ViewRepository<View, Project>>>,
+    queries: Vec<Box<dyn cqrs_es::Query<Project>>>,
+) -> Arc<CqrsFramework<Project, EventStore>> {
+    let store = PersistedEventStore::new_snapshot_store(
+        ShreddingRepository::new(
+            DynamoEventRepository::new(client.clone())
+                .with_tables(&config.tables.event_log, &config.tables.event_snapshots),
+            Shredder::init(client, config),
+        ),
+        config.snapshot_size,
+    )
+    .with_upcasters(vec![Box::new(domains::upcasters())]);
+    let store: EventStore = MeasuredEventStore::new(store);
+
+    let query: Box<dyn cqrs_es::Query<Project>> = Box::new(Query::new(repo));
+
+    let mut all_queries = vec![query];
+    all_queries.extend(queries);
+
+    Arc::new(CqrsFramework::new(store, all_queries, ()))
+}
+
+/// Initialize the Projects View Repository
+pub fn init_repo(
+    client: aws_sdk_dynamodb::Client,
+    config: &Config,
+) -> Arc<Box<dyn ViewRepository<View, Project>>> {
+    Arc::new(Box::new(TracedViewRepository::new(
+        "projects",
+        DynamoViewRepository::new(&config.tables.projects_view, client),
+    )))
+}
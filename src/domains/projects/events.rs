@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use cqrs_es::DomainEvent;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::domains::{shredding::SensitiveFields, upcasters::Upcasters};
+
+use super::{inputs, Project, AGGREGATE_TYPE};
+
+use Event::{Archived, Created, Deleted, Unarchived, Updated};
+
+/// The current version of the Project event schema
+pub const EVENT_VERSION: &str = "1.0";
+
+/// Project events
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(tag = "type")]
+pub enum Event {
+    /// A Project was successfully created
+    Created {
+        /// The ID of the Project that was created
+        id: String,
+
+        /// The date this instance was created
+        created_at: DateTime<Utc>,
+
+        /// The created Project
+        project: Project,
+    },
+
+    /// A Project was successfully updated
+    Updated {
+        /// The ID of the Project that was updated
+        id: String,
+
+        /// The update to the Project
+        update: inputs::Update,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Project was closed to new Tasks
+    Archived {
+        /// The ID of the Project that was archived
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// An archived Project was opened to new Tasks again
+    Unarchived {
+        /// The ID of the Project that was unarchived
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Project was successfully deleted
+    Deleted {
+        /// The ID of the Project that was deleted
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+}
+
+impl Event {
+    /// Return the Aggregate ID
+    #[allow(dead_code)]
+    pub fn id(&self) -> String {
+        match self {
+            Created { id, .. }
+            | Updated { id, .. }
+            | Archived { id, .. }
+            | Unarchived { id, .. }
+            | Deleted { id, .. } => id.to_string(),
+        }
+    }
+}
+
+impl DomainEvent for Event {
+    fn event_type(&self) -> String {
+        match self {
+            Created { .. } => "Project:Created".to_string(),
+            Updated { .. } => "Project:Updated".to_string(),
+            Archived { .. } => "Project:Archived".to_string(),
+            Unarchived { .. } => "Project:Unarchived".to_string(),
+            Deleted { .. } => "Project:Deleted".to_string(),
+        }
+    }
+
+    #[allow(clippy::unused_self)]
+    fn event_version(&self) -> String {
+        EVENT_VERSION.to_string()
+    }
+}
+
+/// Register the upcasters that migrate stored Project events to the current `EVENT_VERSION`
+///
+/// Add an entry here whenever a Project event changes shape, keyed by the version being migrated
+/// from.
+///
+/// Version history:
+///   - 1.0: The initial Project events.
+pub fn register_upcasters(upcasters: Upcasters) -> Upcasters {
+    upcasters
+}
+
+/// Register the Project fields that hold personal data, so they can be crypto-shredded
+pub fn register_sensitive_fields(fields: SensitiveFields) -> SensitiveFields {
+    fields
+        .event(
+            "Project:Created",
+            &["/project/name", "/project/description"],
+        )
+        .event("Project:Updated", &["/update/name", "/update/description"])
+        .snapshot(AGGREGATE_TYPE, &["/name", "/description"])
+}
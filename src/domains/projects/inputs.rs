@@ -0,0 +1,25 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+/// An input type for Project creation
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Create {
+    /// A name
+    pub name: String,
+
+    /// An optional description
+    pub description: Option<String>,
+}
+
+/// An input type that supports partial Project updates
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Update {
+    /// A name
+    pub name: Option<String>,
+
+    /// An optional description. Omit to leave it unchanged, or set it to `null` to clear it.
+    #[serde(default, skip_serializing_if = "utils::Update::is_unchanged")]
+    pub description: utils::Update<String>,
+}
@@ -0,0 +1,22 @@
+/// The Projects Aggregate
+pub mod aggregate;
+
+/// Project Commands
+pub mod commands;
+
+/// Project Events
+pub mod events;
+
+/// Project input types
+pub mod inputs;
+
+/// The default Project View
+pub mod view;
+
+/// The default Project CqrsFramework
+pub mod cqrs;
+
+pub use aggregate::{Project, AGGREGATE_TYPE};
+pub use commands::Command;
+pub use events::Event;
+pub use view::{Query, View};
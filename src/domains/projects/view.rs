@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cqrs_es::{
+    persist::{PersistenceError, ViewContext, ViewRepository},
+    Aggregate, EventEnvelope, View as CqrsView,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Project, AGGREGATE_TYPE};
+
+/// The default View for a Project
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct View {
+    /// The Aggregage type, to differentiate the many types stored in the default view
+    pub aggregate_type: String,
+
+    /// The last Command ID processed
+    pub command_id: String,
+
+    /// The tenant the Project belongs to
+    pub tenant: String,
+
+    /// The Project id
+    pub id: String,
+
+    /// The primary entity, a Project
+    pub project: Project,
+}
+
+impl cqrs_es::View<Project> for View {
+    fn update(&mut self, event: &EventEnvelope<Project>) {
+        self.id.clone_from(&event.aggregate_id);
+        self.aggregate_type = AGGREGATE_TYPE.to_string();
+
+        self.command_id = event
+            .metadata
+            .get("command_id")
+            .unwrap_or(&"".to_string())
+            .to_string();
+
+        if let Some(tenant) = event.metadata.get("tenant") {
+            self.tenant.clone_from(tenant);
+        }
+
+        self.project.apply(event.payload.clone());
+    }
+}
+
+/// A Query to update Project views in response to Project events
+///
+/// Like the Task `Query`, this is custom rather than a `GenericQuery` so the ViewRepository can be
+/// dynamic.
+pub struct Query {
+    projects: Arc<Box<dyn ViewRepository<View, Project>>>,
+}
+
+impl Query {
+    /// Create a new instance
+    pub fn new(projects: Arc<Box<dyn ViewRepository<View, Project>>>) -> Self {
+        Self { projects }
+    }
+
+    async fn update(
+        &self,
+        project_id: &str,
+        events: &[EventEnvelope<Project>],
+    ) -> Result<(), PersistenceError> {
+        let (mut view, view_context) = match self.projects.load_with_context(project_id).await? {
+            None => {
+                let view_context = ViewContext::new(project_id.to_string(), 0);
+                (Default::default(), view_context)
+            }
+            Some((view, context)) => (view, context),
+        };
+
+        for event in events {
+            view.update(event);
+        }
+
+        self.projects.update_view(view, view_context).await
+    }
+}
+
+#[async_trait]
+impl cqrs_es::Query<Project> for Query {
+    async fn dispatch(&self, project_id: &str, events: &[EventEnvelope<Project>]) {
+        if let Err(err) = self.update(project_id, events).await {
+            error!(
+                err:err = err,
+                project_id = project_id;
+                "ProjectQuery: {}",
+                err,
+            );
+        }
+    }
+}
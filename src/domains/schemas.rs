@@ -0,0 +1,547 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::{projects, tasks, DomainEvent};
+
+/// The name the `DomainEvent` envelope schema is published under
+pub const ENVELOPE: &str = "DomainEvent";
+
+/// The key that records the event version a schema describes
+const VERSION_KEY: &str = "x-event-version";
+
+/// The schemas checked in under `schemas/` when this was built, by path relative to it. Each
+/// version only has files for the event types that changed in it, and the rest are carried
+/// forward from the version before, so every version is listed in full.
+const PUBLISHED: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/published_schemas.rs"));
+
+/// A JSON Schema published for a single event type at a specific event version
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct EventSchema {
+    /// The event type, such as "Task:Created"
+    pub event_type: String,
+
+    /// The event version the schema describes
+    pub event_version: String,
+
+    /// The JSON Schema document for the event payload
+    pub schema: Value,
+}
+
+impl EventSchema {
+    /// The schema document as it's written under `schemas/`, without the `x-event-version` that
+    /// its path already records
+    pub fn unversioned(&self) -> Value {
+        let mut schema = self.schema.clone();
+
+        if let Value::Object(fields) = &mut schema {
+            fields.remove(VERSION_KEY);
+        }
+
+        schema
+    }
+
+    /// The relative path the schema is published to, such as "Task/1.0/Created.json"
+    pub fn path(&self) -> PathBuf {
+        let (entity, name) = self
+            .event_type
+            .split_once(':')
+            .unwrap_or(("", &self.event_type));
+
+        Path::new(entity)
+            .join(&self.event_version)
+            .join(format!("{name}.json"))
+    }
+
+    /// Identify a published schema by its relative path, the reverse of `path()`
+    pub fn from_path(path: &Path, schema: Value) -> Option<Self> {
+        let mut parts = path.iter().map(|part| part.to_str());
+
+        let (Some(Some(entity)), Some(Some(version)), Some(Some(file)), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+
+        Some(Self {
+            event_type: format!("{entity}:{}", file.strip_suffix(".json")?),
+            event_version: version.to_string(),
+            schema,
+        })
+    }
+}
+
+/// Generate a schema for each variant of an internally tagged event enum
+///
+/// Variants are identified by the `type` tag, which is combined with the aggregate type to form
+/// the event type. The shared definitions each variant refers to are included with it, so each
+/// document stands alone and only changes when its own event does.
+pub fn event_schemas<E: JsonSchema>(aggregate_type: &str, event_version: &str) -> Vec<EventSchema> {
+    let root = serde_json::to_value(schema_for!(E)).expect("Unable to serialize event schema");
+
+    let meta_schema = root.get("$schema").cloned().unwrap_or(Value::Null);
+    let definitions = root
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+
+    let variants = root
+        .get("oneOf")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    variants
+        .into_iter()
+        .filter_map(|variant| {
+            let tag = variant
+                .pointer("/properties/type/enum/0")
+                .and_then(Value::as_str)?
+                .to_string();
+
+            let event_type = format!("{aggregate_type}:{tag}");
+
+            let mut schema = Map::new();
+            schema.insert("$schema".to_string(), meta_schema.clone());
+            schema.insert("title".to_string(), json!(event_type));
+            schema.insert(VERSION_KEY.to_string(), json!(event_version));
+
+            let referenced = referenced(&definitions, &variant);
+
+            if let Value::Object(fields) = variant {
+                schema.extend(fields);
+            }
+
+            if !referenced.is_empty() {
+                schema.insert("definitions".to_string(), Value::Object(referenced));
+            }
+
+            Some(EventSchema {
+                event_type,
+                event_version: event_version.to_string(),
+                schema: Value::Object(schema),
+            })
+        })
+        .collect()
+}
+
+/// The definitions a schema refers to, directly or through other definitions
+fn referenced(definitions: &Map<String, Value>, schema: &Value) -> Map<String, Value> {
+    let mut names = BTreeSet::new();
+    let mut pending = vec![schema];
+
+    while let Some(node) = pending.pop() {
+        match node {
+            Value::Object(fields) => {
+                let name = fields
+                    .get("$ref")
+                    .and_then(Value::as_str)
+                    .and_then(|r| r.strip_prefix("#/definitions/"));
+
+                if let Some((name, definition)) = name.and_then(|n| definitions.get_key_value(n)) {
+                    if names.insert(name) {
+                        pending.push(definition);
+                    }
+                }
+
+                pending.extend(fields.values());
+            }
+            Value::Array(items) => pending.extend(items),
+            _ => {}
+        }
+    }
+
+    definitions
+        .iter()
+        .filter(|(name, _)| names.contains(name))
+        .map(|(name, definition)| (name.clone(), definition.clone()))
+        .collect()
+}
+
+/// The schemas for every event type in the domain, at their current versions
+pub fn registry() -> Vec<EventSchema> {
+    let mut schemas =
+        event_schemas::<tasks::Event>(tasks::AGGREGATE_TYPE, tasks::events::EVENT_VERSION);
+
+    schemas.extend(event_schemas::<projects::Event>(
+        projects::AGGREGATE_TYPE,
+        projects::events::EVENT_VERSION,
+    ));
+
+    schemas
+}
+
+/// The schemas published for every event type, at every version they were published at
+///
+/// These are the files checked in under `schemas/`, so older versions stay available to consumers
+/// that still read them after the current version moves on. An event type that was removed in a
+/// version is published there as the `false` schema, which no event matches, and isn't listed
+/// from then on.
+pub fn published() -> Vec<EventSchema> {
+    PUBLISHED
+        .iter()
+        .filter_map(|(path, contents)| {
+            let mut schema: Value =
+                serde_json::from_str(contents).expect("Unable to parse published schema");
+
+            let Value::Object(fields) = &mut schema else {
+                return None;
+            };
+
+            let mut published = EventSchema::from_path(Path::new(path), Value::Null)?;
+            fields.insert(VERSION_KEY.to_string(), json!(published.event_version));
+            published.schema = schema;
+
+            Some(published)
+        })
+        .collect()
+}
+
+/// The latest schema published for an event type at a version before `event_version`
+pub fn previous<'a>(
+    published: &'a [EventSchema],
+    event_type: &str,
+    event_version: &str,
+) -> Option<&'a EventSchema> {
+    published
+        .iter()
+        .filter(|schema| {
+            schema.event_type == event_type
+                && compare_versions(&schema.event_version, event_version) == Ordering::Less
+        })
+        .max_by(|a, b| compare_versions(&a.event_version, &b.event_version))
+}
+
+/// Compare event versions numerically, so "1.10" comes after "1.9"
+///
+/// ```rust
+/// use std::cmp::Ordering;
+///
+/// use event_driven_architecture::domains::schemas::compare_versions;
+///
+/// assert_eq!(compare_versions("1.9", "1.10"), Ordering::Less);
+/// assert_eq!(compare_versions("2.0", "1.12"), Ordering::Greater);
+/// assert_eq!(compare_versions("2.1", "2.1"), Ordering::Equal);
+/// ```
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map(|part| part.parse().unwrap_or_default())
+            .collect()
+    };
+
+    parts(a).cmp(&parts(b))
+}
+
+/// The schema for the `DomainEvent` envelope shared by every event on the stream
+pub fn envelope() -> Value {
+    serde_json::to_value(schema_for!(DomainEvent)).expect("Unable to serialize envelope schema")
+}
+
+/// Check that a schema is backward-compatible with a previously published one
+///
+/// A change is compatible when every event produced under the `current` schema is still valid
+/// under the `previous` one, so existing consumers keep working. That means no required
+/// properties removed or made optional, no types widened, and no enum values added. Adding new
+/// optional properties or narrowing a type is allowed. Returns a description of each
+/// incompatibility found, so an empty result means the change is safe.
+///
+/// ```rust
+/// use event_driven_architecture::domains::schemas::check_compatibility;
+/// use serde_json::json;
+///
+/// let previous = json!({
+///     "type": "object",
+///     "required": ["id", "name"],
+///     "properties": {
+///         "id": { "type": "string" },
+///         "name": { "type": "string" }
+///     }
+/// });
+///
+/// let added_optional = json!({
+///     "type": "object",
+///     "required": ["id", "name"],
+///     "properties": {
+///         "id": { "type": "string" },
+///         "name": { "type": "string" },
+///         "summary": { "type": ["string", "null"] }
+///     }
+/// });
+///
+/// assert!(check_compatibility(&previous, &added_optional).is_empty());
+///
+/// let removed_name = json!({
+///     "type": "object",
+///     "required": ["id"],
+///     "properties": {
+///         "id": { "type": "string" }
+///     }
+/// });
+///
+/// assert_eq!(
+///     check_compatibility(&previous, &removed_name),
+///     vec!["$.name: required property was removed".to_string()]
+/// );
+/// ```
+pub fn check_compatibility(previous: &Value, current: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    compare(
+        &Side::new(previous, previous),
+        &Side::new(current, current),
+        "$",
+        &mut errors,
+    );
+
+    errors
+}
+
+/// A schema node paired with the root document its `$ref`s resolve against
+struct Side<'a> {
+    root: &'a Value,
+    node: &'a Value,
+}
+
+impl<'a> Side<'a> {
+    fn new(root: &'a Value, node: &'a Value) -> Self {
+        let mut node = node;
+
+        // Follow local references like "#/definitions/Task"
+        while let Some(pointer) = node
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|r| r.strip_prefix('#'))
+        {
+            match root.pointer(pointer) {
+                Some(target) => node = target,
+                None => break,
+            }
+        }
+
+        Self { root, node }
+    }
+
+    fn child(&self, node: &'a Value) -> Self {
+        Self::new(self.root, node)
+    }
+
+    fn types(&self) -> Option<BTreeSet<String>> {
+        match self.node.get("type")? {
+            Value::String(t) => Some(BTreeSet::from([t.clone()])),
+            Value::Array(ts) => Some(
+                ts.iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn required(&self) -> BTreeSet<String> {
+        self.node
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| {
+                r.iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn list(&self, key: &str) -> Option<&'a Vec<Value>> {
+        self.node.get(key).and_then(Value::as_array)
+    }
+}
+
+fn compare(previous: &Side<'_>, current: &Side<'_>, path: &str, errors: &mut Vec<String>) {
+    if let (Some(prev_types), Some(cur_types)) = (previous.types(), current.types()) {
+        if !cur_types.is_subset(&prev_types) {
+            errors.push(format!(
+                "{path}: type changed from {prev_types:?} to {cur_types:?}"
+            ));
+            return;
+        }
+    }
+
+    if let (Some(prev_values), Some(cur_values)) = (previous.list("enum"), current.list("enum")) {
+        for value in cur_values {
+            if !prev_values.contains(value) {
+                errors.push(format!("{path}: enum value {value} was added"));
+            }
+        }
+    }
+
+    let prev_required = previous.required();
+    let cur_required = current.required();
+
+    let empty = Map::new();
+    let prev_props = previous
+        .node
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let cur_props = current
+        .node
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    for (name, prev_prop) in prev_props {
+        let prop_path = format!("{path}.{name}");
+
+        match cur_props.get(name) {
+            None if prev_required.contains(name) => {
+                errors.push(format!("{prop_path}: required property was removed"));
+            }
+            None => {}
+            Some(cur_prop) => {
+                if prev_required.contains(name) && !cur_required.contains(name) {
+                    errors.push(format!("{prop_path}: property is no longer required"));
+                }
+
+                compare(
+                    &previous.child(prev_prop),
+                    &current.child(cur_prop),
+                    &prop_path,
+                    errors,
+                );
+            }
+        }
+    }
+
+    if let (Some(prev_items), Some(cur_items)) =
+        (previous.node.get("items"), current.node.get("items"))
+    {
+        compare(
+            &previous.child(prev_items),
+            &current.child(cur_items),
+            &format!("{path}[]"),
+            errors,
+        );
+    }
+
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let (Some(prev_variants), Some(cur_variants)) = (previous.list(key), current.list(key)) {
+            if cur_variants.len() > prev_variants.len() && key != "allOf" {
+                errors.push(format!("{path}: {key} alternatives were added"));
+            }
+
+            for (i, (prev_variant, cur_variant)) in
+                prev_variants.iter().zip(cur_variants.iter()).enumerate()
+            {
+                compare(
+                    &previous.child(prev_variant),
+                    &current.child(cur_variant),
+                    &format!("{path}<{key}:{i}>"),
+                    errors,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_includes_every_version() {
+        let published = published();
+
+        let find = |event_type: &str, event_version: &str| {
+            published.iter().find(|schema| {
+                schema.event_type == event_type && schema.event_version == event_version
+            })
+        };
+
+        let created = find("Task:Created", "1.0").expect("Task:Created 1.0 is published");
+        assert_eq!(created.schema["x-event-version"], "1.0");
+
+        for schema in registry() {
+            assert!(
+                find(&schema.event_type, &schema.event_version).is_some(),
+                "{} {} hasn't been generated into schemas/",
+                schema.event_type,
+                schema.event_version,
+            );
+        }
+    }
+
+    #[test]
+    fn unchanged_event_types_are_carried_forward() {
+        let published = published();
+
+        let find = |event_version: &str| {
+            published
+                .iter()
+                .find(|schema| {
+                    schema.event_type == "Task:Deleted" && schema.event_version == event_version
+                })
+                .unwrap_or_else(|| panic!("Task:Deleted {event_version} is published"))
+        };
+
+        let first = find("1.0");
+        let current = find(tasks::events::EVENT_VERSION);
+
+        assert_eq!(first.unversioned(), current.unversioned());
+        assert_eq!(
+            current.schema[VERSION_KEY],
+            json!(tasks::events::EVENT_VERSION)
+        );
+    }
+
+    #[test]
+    fn schemas_only_include_the_definitions_they_refer_to() {
+        let registry = registry();
+
+        let definitions = |event_type: &str| -> Vec<String> {
+            let schema = registry
+                .iter()
+                .find(|schema| schema.event_type == event_type)
+                .unwrap();
+
+            schema
+                .schema
+                .get("definitions")
+                .and_then(Value::as_object)
+                .map(|definitions| definitions.keys().cloned().collect())
+                .unwrap_or_default()
+        };
+
+        assert!(definitions("Task:Deleted").is_empty());
+        assert_eq!(definitions("Task:PriorityChanged"), ["Priority"]);
+
+        // Definitions that are only referred to by other definitions are included too
+        let created = definitions("Task:Created");
+        assert!(["Task", "Status", "Recurrence", "Series"]
+            .iter()
+            .all(|name| created.iter().any(|included| included == name)));
+    }
+
+    #[test]
+    fn previous_finds_the_latest_earlier_version() {
+        let published = published();
+
+        let updated = previous(&published, "Task:Updated", "2.0").unwrap();
+        assert_eq!(updated.event_version, "1.5");
+
+        let created = previous(&published, "Task:Created", tasks::events::EVENT_VERSION);
+        assert!(created.is_some_and(|schema| {
+            compare_versions(&schema.event_version, tasks::events::EVENT_VERSION) == Ordering::Less
+        }));
+
+        assert!(previous(&published, "Task:Created", "1.0").is_none());
+    }
+}
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, fmt};
+
+use aes_gcm::{
+    aead::{KeyInit, OsRng},
+    Aes256Gcm,
+};
+use async_trait::async_trait;
+use aws_sdk_dynamodb::{primitives::Blob, types::AttributeValue};
+use chrono::Utc;
+use tokio::sync::RwLock;
+
+use super::Error;
+
+/// A per-aggregate data encryption key
+#[derive(Clone)]
+pub struct Key(pub(super) aes_gcm::Key<Aes256Gcm>);
+
+impl Key {
+    /// Generate a new random key
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 32 {
+            return Err(Error::KeyStore("Stored key has an invalid length".into()));
+        }
+
+        Ok(Self(*aes_gcm::Key::<Aes256Gcm>::from_slice(bytes)))
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// Storage for per-aggregate encryption keys
+///
+/// Destroying a key leaves a tombstone behind, so a key is never silently recreated for an
+/// aggregate that has been erased.
+#[async_trait]
+pub trait KeyStore: Send + Sync {
+    /// Get the key for an aggregate, or `None` if it was never created or has been destroyed
+    async fn get(&self, aggregate_id: &str) -> Result<Option<Key>, Error>;
+
+    /// Get the key for an aggregate, creating one if needed
+    async fn get_or_create(&self, aggregate_id: &str) -> Result<Key, Error>;
+
+    /// Permanently destroy the key for an aggregate
+    async fn destroy(&self, aggregate_id: &str) -> Result<(), Error>;
+}
+
+/// A KeyStore backed by a DynamoDB table keyed by `AggregateId`
+pub struct DynamoKeyStore {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl DynamoKeyStore {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Load the stored entry, returning `Some(None)` for a destroyed key
+    async fn load(&self, aggregate_id: &str) -> Result<Option<Option<Key>>, Error> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("AggregateId", AttributeValue::S(aggregate_id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|err| Error::KeyStore(Box::new(err)))?;
+
+        let Some(item) = output.item else {
+            return Ok(None);
+        };
+
+        match item.get("Key") {
+            Some(AttributeValue::B(bytes)) => Ok(Some(Some(Key::from_bytes(bytes.as_ref())?))),
+            _ => Ok(Some(None)),
+        }
+    }
+}
+
+#[async_trait]
+impl KeyStore for DynamoKeyStore {
+    async fn get(&self, aggregate_id: &str) -> Result<Option<Key>, Error> {
+        Ok(self.load(aggregate_id).await?.flatten())
+    }
+
+    async fn get_or_create(&self, aggregate_id: &str) -> Result<Key, Error> {
+        match self.load(aggregate_id).await? {
+            Some(Some(key)) => return Ok(key),
+            Some(None) => {
+                return Err(Error::Erased {
+                    aggregate_id: aggregate_id.to_string(),
+                })
+            }
+            None => {}
+        }
+
+        let key = Key::generate();
+
+        let result = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("AggregateId", AttributeValue::S(aggregate_id.to_string()))
+            .item("Key", AttributeValue::B(Blob::new(key.0.to_vec())))
+            .item("CreatedAt", AttributeValue::S(Utc::now().to_rfc3339()))
+            .condition_expression("attribute_not_exists(AggregateId)")
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(key),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+            {
+                // Another writer created the key first, so use theirs
+                self.get(aggregate_id).await?.ok_or_else(|| Error::Erased {
+                    aggregate_id: aggregate_id.to_string(),
+                })
+            }
+            Err(err) => Err(Error::KeyStore(Box::new(err))),
+        }
+    }
+
+    async fn destroy(&self, aggregate_id: &str) -> Result<(), Error> {
+        // Overwrite the whole item, so the key material is gone and only the tombstone remains
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("AggregateId", AttributeValue::S(aggregate_id.to_string()))
+            .item("DestroyedAt", AttributeValue::S(Utc::now().to_rfc3339()))
+            .send()
+            .await
+            .map_err(|err| Error::KeyStore(Box::new(err)))?;
+
+        Ok(())
+    }
+}
+
+/// A KeyStore held in memory, for local development and examples
+#[derive(Default)]
+pub struct MemoryKeyStore {
+    keys: RwLock<HashMap<String, Option<Key>>>,
+}
+
+#[async_trait]
+impl KeyStore for MemoryKeyStore {
+    async fn get(&self, aggregate_id: &str) -> Result<Option<Key>, Error> {
+        Ok(self.keys.read().await.get(aggregate_id).cloned().flatten())
+    }
+
+    async fn get_or_create(&self, aggregate_id: &str) -> Result<Key, Error> {
+        let mut keys = self.keys.write().await;
+
+        match keys
+            .entry(aggregate_id.to_string())
+            .or_insert_with(|| Some(Key::generate()))
+        {
+            Some(key) => Ok(key.clone()),
+            None => Err(Error::Erased {
+                aggregate_id: aggregate_id.to_string(),
+            }),
+        }
+    }
+
+    async fn destroy(&self, aggregate_id: &str) -> Result<(), Error> {
+        self.keys
+            .write()
+            .await
+            .insert(aggregate_id.to_string(), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+
+use crate::config::Config;
+
+use super::{projects, tasks, DomainEvent};
+
+mod keys;
+mod repository;
+
+pub use keys::{DynamoKeyStore, Key, KeyStore, MemoryKeyStore};
+pub use repository::ShreddingRepository;
+
+/// The placeholder rendered in place of sensitive data once its key has been destroyed
+pub const REDACTED: &str = "[redacted]";
+
+/// The prefix that marks a string field as sealed
+const SEALED_PREFIX: &str = "sealed:v1:";
+
+/// The length of an AES-GCM nonce, which is stored ahead of the ciphertext
+const NONCE_LENGTH: usize = 12;
+
+/// Crypto-shredding errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The key store couldn't be reached, or returned something unexpected
+    #[error("Key store error: {0}")]
+    KeyStore(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The aggregate's key has been destroyed, so nothing new can be sealed for it
+    #[error("The key for {aggregate_id} has been destroyed")]
+    Erased {
+        /// The erased aggregate
+        aggregate_id: String,
+    },
+
+    /// A sealed value couldn't be encrypted or decrypted with the aggregate's key
+    #[error("Unable to {action} a sensitive field for {aggregate_id}")]
+    Cipher {
+        /// Whether sealing or opening failed
+        action: &'static str,
+
+        /// The aggregate the field belongs to
+        aggregate_id: String,
+    },
+}
+
+/// A registry of the fields that hold personal data, as JSON pointers into event payloads and
+/// aggregate snapshots. A pointer can name a string, or an array whose strings are each sealed.
+#[derive(Debug, Default)]
+pub struct SensitiveFields {
+    events: HashMap<String, Vec<String>>,
+    snapshots: HashMap<String, Vec<String>>,
+    erasures: HashSet<String>,
+}
+
+impl SensitiveFields {
+    /// Register the sensitive fields within an event type's payload
+    pub fn event(mut self, event_type: &str, pointers: &[&str]) -> Self {
+        self.events.insert(
+            event_type.to_string(),
+            pointers.iter().map(|p| p.to_string()).collect(),
+        );
+        self
+    }
+
+    /// Register the sensitive fields within an aggregate type's snapshot
+    pub fn snapshot(mut self, aggregate_type: &str, pointers: &[&str]) -> Self {
+        self.snapshots.insert(
+            aggregate_type.to_string(),
+            pointers.iter().map(|p| p.to_string()).collect(),
+        );
+        self
+    }
+
+    /// Register an event type that erases its aggregate, destroying the key when it's committed
+    pub fn erasure(mut self, event_type: &str) -> Self {
+        self.erasures.insert(event_type.to_string());
+        self
+    }
+}
+
+/// The sensitive fields for every event type in the domain
+pub fn sensitive_fields() -> SensitiveFields {
+    let fields = tasks::events::register_sensitive_fields(SensitiveFields::default());
+
+    projects::events::register_sensitive_fields(fields)
+}
+
+/// Seals and opens sensitive fields with per-aggregate keys
+///
+/// Sensitive string fields are encrypted with a key unique to their aggregate, so destroying
+/// that key makes them permanently unreadable while the events around them stay intact. Sealed
+/// fields that can no longer be opened are rendered as [`REDACTED`]. Fields recorded before
+/// sealing was introduced are plaintext, and are passed through as-is.
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use event_driven_architecture::domains::shredding::{
+///     MemoryKeyStore, SensitiveFields, Shredder, REDACTED,
+/// };
+/// use serde_json::json;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let shredder = Shredder::new(
+///     Arc::new(MemoryKeyStore::default()),
+///     SensitiveFields::default().event("Task:Created", &["/task/name"]),
+/// );
+///
+/// let mut payload = json!({ "task": { "name": "My Task" } });
+///
+/// shredder.seal_event("acme#1", "Task:Created", &mut payload).await.unwrap();
+/// assert_ne!(payload["task"]["name"], "My Task");
+///
+/// let mut opened = payload.clone();
+/// shredder.open_event("acme#1", "Task:Created", &mut opened).await.unwrap();
+/// assert_eq!(opened["task"]["name"], "My Task");
+///
+/// shredder.erase("acme#1").await.unwrap();
+///
+/// shredder.open_event("acme#1", "Task:Created", &mut payload).await.unwrap();
+/// assert_eq!(payload["task"]["name"], REDACTED);
+/// # }
+/// ```
+pub struct Shredder {
+    keys: Arc<dyn KeyStore>,
+    fields: SensitiveFields,
+}
+
+impl Shredder {
+    /// Create a new instance
+    pub fn new(keys: Arc<dyn KeyStore>, fields: SensitiveFields) -> Self {
+        Self { keys, fields }
+    }
+
+    /// Initialize a Shredder for the domain, with keys stored in DynamoDB
+    pub fn init(client: aws_sdk_dynamodb::Client, config: &Config) -> Arc<Self> {
+        Arc::new(Self::new(
+            Arc::new(DynamoKeyStore::new(client, &config.tables.encryption_keys)),
+            sensitive_fields(),
+        ))
+    }
+
+    /// Whether committing this event type erases its aggregate
+    pub fn is_erasure(&self, event_type: &str) -> bool {
+        self.fields.erasures.contains(event_type)
+    }
+
+    /// Permanently destroy an aggregate's key, so its sealed fields can never be opened again
+    pub async fn erase(&self, aggregate_id: &str) -> Result<(), Error> {
+        self.keys.destroy(aggregate_id).await
+    }
+
+    /// Seal the sensitive fields in an event payload
+    pub async fn seal_event(
+        &self,
+        aggregate_id: &str,
+        event_type: &str,
+        payload: &mut Value,
+    ) -> Result<(), Error> {
+        self.seal(aggregate_id, self.fields.events.get(event_type), payload)
+            .await
+    }
+
+    /// Open the sealed fields in an event payload, redacting any that belong to an erased
+    /// aggregate
+    pub async fn open_event(
+        &self,
+        aggregate_id: &str,
+        event_type: &str,
+        payload: &mut Value,
+    ) -> Result<(), Error> {
+        self.open(aggregate_id, self.fields.events.get(event_type), payload)
+            .await
+    }
+
+    /// Redact the sealed fields in an event payload if the aggregate has been erased, leaving
+    /// them sealed otherwise
+    pub async fn redact_event(
+        &self,
+        aggregate_id: &str,
+        event_type: &str,
+        payload: &mut Value,
+    ) -> Result<(), Error> {
+        let Some(pointers) = self.fields.events.get(event_type) else {
+            return Ok(());
+        };
+
+        if !has_sealed(pointers, payload) || self.keys.get(aggregate_id).await?.is_some() {
+            return Ok(());
+        }
+
+        redact(pointers, payload)
+    }
+
+    /// Seal the sensitive fields in an aggregate snapshot
+    pub async fn seal_snapshot(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        snapshot: &mut Value,
+    ) -> Result<(), Error> {
+        self.seal(
+            aggregate_id,
+            self.fields.snapshots.get(aggregate_type),
+            snapshot,
+        )
+        .await
+    }
+
+    /// Open the sealed fields in an aggregate snapshot
+    pub async fn open_snapshot(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        snapshot: &mut Value,
+    ) -> Result<(), Error> {
+        self.open(
+            aggregate_id,
+            self.fields.snapshots.get(aggregate_type),
+            snapshot,
+        )
+        .await
+    }
+
+    /// Open the sealed fields in a published Domain Event's payload
+    pub async fn open_domain_event(&self, mut event: DomainEvent) -> Result<DomainEvent, Error> {
+        if let Some(mut payload) = self.domain_event_payload(&event) {
+            self.open_event(&event.id, &event.event_type, &mut payload)
+                .await?;
+
+            event.payload = payload.to_string();
+        }
+
+        Ok(event)
+    }
+
+    /// Redact the sealed fields in a published Domain Event's payload if the aggregate has been
+    /// erased
+    pub async fn redact_domain_event(&self, mut event: DomainEvent) -> Result<DomainEvent, Error> {
+        if let Some(mut payload) = self.domain_event_payload(&event) {
+            self.redact_event(&event.id, &event.event_type, &mut payload)
+                .await?;
+
+            event.payload = payload.to_string();
+        }
+
+        Ok(event)
+    }
+
+    /// Decode a Domain Event's payload if it has sensitive fields registered
+    fn domain_event_payload(&self, event: &DomainEvent) -> Option<Value> {
+        if !self.fields.events.contains_key(&event.event_type) {
+            return None;
+        }
+
+        serde_json::from_str(&event.payload).ok()
+    }
+
+    async fn seal(
+        &self,
+        aggregate_id: &str,
+        pointers: Option<&Vec<String>>,
+        value: &mut Value,
+    ) -> Result<(), Error> {
+        let Some(pointers) = pointers else {
+            return Ok(());
+        };
+
+        let mut plaintext = false;
+        for_each_field(pointers, value, |field| {
+            plaintext |= !is_sealed(field) && field != REDACTED;
+            Ok(())
+        })?;
+
+        if !plaintext {
+            return Ok(());
+        }
+
+        let key = self.keys.get_or_create(aggregate_id).await?;
+        let cipher = Aes256Gcm::new(&key.0);
+
+        for_each_field(pointers, value, |field| {
+            if is_sealed(field) || field == REDACTED {
+                return Ok(());
+            }
+
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: field.as_bytes(),
+                        aad: aggregate_id.as_bytes(),
+                    },
+                )
+                .map_err(|_| Error::Cipher {
+                    action: "seal",
+                    aggregate_id: aggregate_id.to_string(),
+                })?;
+
+            let mut sealed = nonce.to_vec();
+            sealed.extend(ciphertext);
+
+            *field = format!("{SEALED_PREFIX}{}", STANDARD.encode(sealed));
+
+            Ok(())
+        })
+    }
+
+    async fn open(
+        &self,
+        aggregate_id: &str,
+        pointers: Option<&Vec<String>>,
+        value: &mut Value,
+    ) -> Result<(), Error> {
+        let Some(pointers) = pointers else {
+            return Ok(());
+        };
+
+        if !has_sealed(pointers, value) {
+            return Ok(());
+        }
+
+        let Some(key) = self.keys.get(aggregate_id).await? else {
+            // The key has been destroyed, so the data is gone for good
+            return redact(pointers, value);
+        };
+
+        let cipher = Aes256Gcm::new(&key.0);
+        let failed = || Error::Cipher {
+            action: "open",
+            aggregate_id: aggregate_id.to_string(),
+        };
+
+        for_each_field(pointers, value, |field| {
+            let Some(encoded) = field.strip_prefix(SEALED_PREFIX) else {
+                return Ok(());
+            };
+
+            let sealed = STANDARD.decode(encoded).map_err(|_| failed())?;
+            if sealed.len() < NONCE_LENGTH {
+                return Err(failed());
+            }
+
+            let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+            let plaintext = cipher
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: aggregate_id.as_bytes(),
+                    },
+                )
+                .map_err(|_| failed())?;
+
+            *field = String::from_utf8(plaintext).map_err(|_| failed())?;
+
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Debug for Shredder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shredder")
+            .field("fields", &self.fields)
+            .finish_non_exhaustive()
+    }
+}
+
+fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+fn has_sealed(pointers: &[String], value: &Value) -> bool {
+    pointers
+        .iter()
+        .filter_map(|pointer| value.pointer(pointer))
+        .any(|field| match field {
+            Value::String(field) => is_sealed(field),
+            Value::Array(items) => items.iter().filter_map(Value::as_str).any(is_sealed),
+            _ => false,
+        })
+}
+
+/// Replace every sealed field with the redacted placeholder
+fn redact(pointers: &[String], value: &mut Value) -> Result<(), Error> {
+    for_each_field(pointers, value, |field| {
+        if is_sealed(field) {
+            *field = REDACTED.to_string();
+        }
+
+        Ok(())
+    })
+}
+
+/// Visit each sensitive string field that is present, and each string in a sensitive array,
+/// skipping missing or null fields
+fn for_each_field(
+    pointers: &[String],
+    value: &mut Value,
+    mut f: impl FnMut(&mut String) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for pointer in pointers {
+        match value.pointer_mut(pointer) {
+            Some(Value::String(field)) => f(field)?,
+            Some(Value::Array(items)) => {
+                for item in items {
+                    if let Value::String(field) = item {
+                        f(field)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ID: &str = "acme#1";
+
+    fn shredder() -> Shredder {
+        Shredder::new(Arc::new(MemoryKeyStore::default()), sensitive_fields())
+    }
+
+    async fn sealed(shredder: &Shredder, event_type: &str, payload: &Value) -> Value {
+        let mut sealed = payload.clone();
+
+        shredder
+            .seal_event(ID, event_type, &mut sealed)
+            .await
+            .unwrap();
+
+        sealed
+    }
+
+    #[tokio::test]
+    async fn personal_data_is_sealed() {
+        let shredder = shredder();
+
+        let cases = [
+            (
+                "Task:Created",
+                json!({ "task": { "name": "Call Alice", "labels": ["alice", "health"] } }),
+                vec!["/task/name", "/task/labels/0", "/task/labels/1"],
+            ),
+            (
+                "Task:LabelAdded",
+                json!({ "label": "alice" }),
+                vec!["/label"],
+            ),
+            (
+                "Task:LabelRemoved",
+                json!({ "label": "alice" }),
+                vec!["/label"],
+            ),
+            (
+                "Project:Created",
+                json!({ "project": { "name": "Alice's move", "description": "Boxes" } }),
+                vec!["/project/name", "/project/description"],
+            ),
+            (
+                "Project:Updated",
+                json!({ "update": { "name": "Alice's move", "description": "Boxes" } }),
+                vec!["/update/name", "/update/description"],
+            ),
+        ];
+
+        for (event_type, payload, pointers) in cases {
+            let sealed = sealed(&shredder, event_type, &payload).await;
+
+            for pointer in pointers {
+                let field = sealed.pointer(pointer).and_then(Value::as_str).unwrap();
+
+                assert!(is_sealed(field), "{event_type} {pointer} is sealed");
+            }
+
+            let mut opened = sealed.clone();
+            shredder
+                .open_event(ID, event_type, &mut opened)
+                .await
+                .unwrap();
+
+            assert_eq!(opened, payload, "{event_type} opens again");
+        }
+    }
+
+    #[tokio::test]
+    async fn subject_ids_are_left_in_the_clear() {
+        let shredder = shredder();
+
+        for event_type in ["Task:ReminderDue", "Task:BecameOverdue", "Task:Assigned"] {
+            let payload = json!({ "owner": "user-1", "assignee": "user-2" });
+
+            assert_eq!(sealed(&shredder, event_type, &payload).await, payload);
+        }
+
+        let payload = json!({ "task": { "name": "Call Alice", "owner": "user-1" } });
+        let sealed = sealed(&shredder, "Task:Created", &payload).await;
+
+        assert_eq!(sealed["task"]["owner"], "user-1");
+    }
+
+    #[tokio::test]
+    async fn erased_arrays_are_redacted_item_by_item() {
+        let shredder = shredder();
+        let payload = json!({ "task": { "name": "Call Alice", "labels": ["alice", "health"] } });
+
+        let mut sealed = sealed(&shredder, "Task:Created", &payload).await;
+        shredder.erase(ID).await.unwrap();
+
+        shredder
+            .redact_event(ID, "Task:Created", &mut sealed)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sealed,
+            json!({ "task": { "name": REDACTED, "labels": [REDACTED, REDACTED] } })
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cqrs_es::{
+    persist::{
+        PersistedEventRepository, PersistenceError, ReplayStream, SerializedEvent,
+        SerializedSnapshot,
+    },
+    Aggregate,
+};
+use serde_json::Value;
+
+use super::Shredder;
+
+/// An event repository that seals sensitive fields before they are stored, and opens them again
+/// when they are loaded
+///
+/// Aggregates and upcasters only ever see plaintext, or the redacted placeholder once an
+/// aggregate has been erased. Keys are destroyed once an erasure event has been committed, so a
+/// commit that fails, such as on a concurrent write, never destroys data that is still live. If
+/// destroying the key fails after the commit, the publisher finishes the erasure when the event
+/// reaches it.
+pub struct ShreddingRepository<R> {
+    inner: R,
+    shredder: Arc<Shredder>,
+}
+
+impl<R: PersistedEventRepository> ShreddingRepository<R> {
+    /// Wrap an existing repository
+    pub fn new(inner: R, shredder: Arc<Shredder>) -> Self {
+        Self { inner, shredder }
+    }
+
+    async fn open_events(
+        &self,
+        mut events: Vec<SerializedEvent>,
+    ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+        for event in events.iter_mut() {
+            self.shredder
+                .open_event(&event.aggregate_id, &event.event_type, &mut event.payload)
+                .await
+                .map_err(|err| PersistenceError::UnknownError(Box::new(err)))?;
+        }
+
+        Ok(events)
+    }
+}
+
+#[async_trait]
+impl<R: PersistedEventRepository> PersistedEventRepository for ShreddingRepository<R> {
+    async fn get_events<A: Aggregate>(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+        let events = self.inner.get_events::<A>(aggregate_id).await?;
+
+        self.open_events(events).await
+    }
+
+    async fn get_last_events<A: Aggregate>(
+        &self,
+        aggregate_id: &str,
+        last_sequence: usize,
+    ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+        let events = self
+            .inner
+            .get_last_events::<A>(aggregate_id, last_sequence)
+            .await?;
+
+        self.open_events(events).await
+    }
+
+    async fn get_snapshot<A: Aggregate>(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<SerializedSnapshot>, PersistenceError> {
+        let Some(mut snapshot) = self.inner.get_snapshot::<A>(aggregate_id).await? else {
+            return Ok(None);
+        };
+
+        self.shredder
+            .open_snapshot(&A::aggregate_type(), aggregate_id, &mut snapshot.aggregate)
+            .await
+            .map_err(|err| PersistenceError::UnknownError(Box::new(err)))?;
+
+        Ok(Some(snapshot))
+    }
+
+    async fn persist<A: Aggregate>(
+        &self,
+        events: &[SerializedEvent],
+        snapshot_update: Option<(String, Value, usize)>,
+    ) -> Result<(), PersistenceError> {
+        let mut sealed = events.to_vec();
+
+        for event in sealed.iter_mut() {
+            self.shredder
+                .seal_event(&event.aggregate_id, &event.event_type, &mut event.payload)
+                .await
+                .map_err(|err| PersistenceError::UnknownError(Box::new(err)))?;
+        }
+
+        let snapshot_update = match snapshot_update {
+            Some((aggregate_id, mut aggregate, version)) => {
+                self.shredder
+                    .seal_snapshot(&A::aggregate_type(), &aggregate_id, &mut aggregate)
+                    .await
+                    .map_err(|err| PersistenceError::UnknownError(Box::new(err)))?;
+
+                Some((aggregate_id, aggregate, version))
+            }
+            None => None,
+        };
+
+        self.inner.persist::<A>(&sealed, snapshot_update).await?;
+
+        for event in events
+            .iter()
+            .filter(|event| self.shredder.is_erasure(&event.event_type))
+        {
+            // The erasure is committed, so the publisher retries this until the key is gone
+            if let Err(err) = self.shredder.erase(&event.aggregate_id).await {
+                warn!(
+                    "Unable to destroy the key for {} yet, leaving it to the publisher: {}",
+                    event.aggregate_id, err
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn stream_events<A: Aggregate>(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<ReplayStream, PersistenceError> {
+        let events = self.get_events::<A>(aggregate_id).await?;
+
+        let (mut feed, stream) = ReplayStream::new(events.len().max(1));
+
+        tokio::spawn(async move {
+            for event in events {
+                if feed.push(Ok(event)).await.is_err() {
+                    // The stream was dropped
+                    return;
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+
+    /// Streams all events for an aggregate type
+    ///
+    /// Sensitive fields are left sealed here, since the replay stream can't be decoded without
+    /// the aggregate's event type. Queries replayed from it should open them with the Shredder.
+    async fn stream_all_events<A: Aggregate>(&self) -> Result<ReplayStream, PersistenceError> {
+        self.inner.stream_all_events::<A>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::domains::{
+        shredding::{sensitive_fields, MemoryKeyStore, REDACTED},
+        tasks::Task,
+    };
+
+    use super::*;
+
+    const ID: &str = "acme#1";
+
+    /// An event repository that stores nothing, and fails every commit when told to
+    struct FakeRepository {
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl PersistedEventRepository for FakeRepository {
+        async fn get_events<A: Aggregate>(
+            &self,
+            _aggregate_id: &str,
+        ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+            Ok(vec![])
+        }
+
+        async fn get_last_events<A: Aggregate>(
+            &self,
+            _aggregate_id: &str,
+            _last_sequence: usize,
+        ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+            Ok(vec![])
+        }
+
+        async fn get_snapshot<A: Aggregate>(
+            &self,
+            _aggregate_id: &str,
+        ) -> Result<Option<SerializedSnapshot>, PersistenceError> {
+            Ok(None)
+        }
+
+        async fn persist<A: Aggregate>(
+            &self,
+            _events: &[SerializedEvent],
+            _snapshot_update: Option<(String, Value, usize)>,
+        ) -> Result<(), PersistenceError> {
+            if self.fail {
+                return Err(PersistenceError::OptimisticLockError);
+            }
+
+            Ok(())
+        }
+
+        async fn stream_events<A: Aggregate>(
+            &self,
+            _aggregate_id: &str,
+        ) -> Result<ReplayStream, PersistenceError> {
+            Ok(ReplayStream::new(1).1)
+        }
+
+        async fn stream_all_events<A: Aggregate>(&self) -> Result<ReplayStream, PersistenceError> {
+            Ok(ReplayStream::new(1).1)
+        }
+    }
+
+    fn erased() -> SerializedEvent {
+        SerializedEvent::new(
+            ID.to_string(),
+            2,
+            "Task".to_string(),
+            "Task:Erased".to_string(),
+            "1.4".to_string(),
+            json!({ "type": "Erased", "id": ID, "updated_at": "2024-01-02T03:04:05Z" }),
+            json!({}),
+        )
+    }
+
+    /// Seal a Task name, returning the sealed payload
+    async fn sealed(shredder: &Shredder) -> Value {
+        let mut payload = json!({ "task": { "name": "My Task", "summary": null } });
+
+        shredder
+            .seal_event(ID, "Task:Created", &mut payload)
+            .await
+            .unwrap();
+
+        payload
+    }
+
+    async fn opened_name(shredder: &Shredder, mut payload: Value) -> Value {
+        shredder
+            .open_event(ID, "Task:Created", &mut payload)
+            .await
+            .unwrap();
+
+        payload["task"]["name"].take()
+    }
+
+    #[tokio::test]
+    async fn failed_erasure_commit_keeps_the_key() {
+        let shredder = Arc::new(Shredder::new(
+            Arc::new(MemoryKeyStore::default()),
+            sensitive_fields(),
+        ));
+        let payload = sealed(&shredder).await;
+
+        let repo = ShreddingRepository::new(FakeRepository { fail: true }, shredder.clone());
+
+        assert!(repo.persist::<Task>(&[erased()], None).await.is_err());
+        assert_eq!(opened_name(&shredder, payload).await, "My Task");
+    }
+
+    #[tokio::test]
+    async fn committed_erasure_destroys_the_key() {
+        let shredder = Arc::new(Shredder::new(
+            Arc::new(MemoryKeyStore::default()),
+            sensitive_fields(),
+        ));
+        let payload = sealed(&shredder).await;
+
+        let repo = ShreddingRepository::new(FakeRepository { fail: false }, shredder.clone());
+
+        repo.persist::<Task>(&[erased()], None).await.unwrap();
+        assert_eq!(opened_name(&shredder, payload).await, REDACTED);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{DateTime, Utc};
+use cqrs_es::{persist::PersistenceError, DomainEvent, EventEnvelope};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+use super::{Event, Task};
+
+/// The most entries returned in a single page of the feed
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+/// A single entry in a Task's activity feed
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct Activity {
+    /// The sequence number of the event this entry was recorded from, which orders the feed
+    pub sequence: usize,
+
+    /// The event type, such as "Task:Started" or "Task:CommentAdded"
+    pub event_type: String,
+
+    /// The subject whose command caused the event
+    pub actor: String,
+
+    /// When the event occurred
+    pub occurred_at: DateTime<Utc>,
+
+    /// The comment this entry is about, for comment events
+    pub comment_id: Option<String>,
+
+    /// The comment text, for comments that are added or edited. It's removed once the comment is
+    /// deleted or the Task is erased.
+    pub body: Option<String>,
+}
+
+/// A page of a Task's activity feed, newest first
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct ActivityPage {
+    /// The entries on this page
+    pub activity: Vec<Activity>,
+
+    /// Pass this as `before` to fetch the next page, if there is one
+    pub next: Option<usize>,
+}
+
+/// The activity feed for every Task, in a DynamoDB table keyed by `AggregateId` and `Sequence`
+pub struct Feed {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl Feed {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Initialize the Task activity Feed from the config
+    pub fn init(client: aws_sdk_dynamodb::Client, config: &Config) -> Arc<Self> {
+        Arc::new(Self::new(client, &config.tables.tasks_activity))
+    }
+
+    /// Read up to `limit` entries for a Task, starting just before the `before` sequence
+    pub async fn page(
+        &self,
+        aggregate_id: &str,
+        before: Option<usize>,
+        limit: i32,
+    ) -> Result<ActivityPage, PersistenceError> {
+        let mut request = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .expression_attribute_values(":id", AttributeValue::S(aggregate_id.to_string()))
+            .scan_index_forward(false)
+            .limit(limit.clamp(1, MAX_PAGE_SIZE));
+
+        request = match before {
+            Some(before) => request
+                .key_condition_expression("AggregateId = :id AND #sequence < :before")
+                .expression_attribute_names("#sequence", "Sequence")
+                .expression_attribute_values(":before", AttributeValue::N(before.to_string())),
+            None => request.key_condition_expression("AggregateId = :id"),
+        };
+
+        let output = request
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        let activity = output
+            .items()
+            .iter()
+            .map(decode)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let next = output
+            .last_evaluated_key()
+            .and(activity.last())
+            .map(|last| last.sequence);
+
+        Ok(ActivityPage { activity, next })
+    }
+
+    /// Add an entry to a Task's feed
+    pub async fn record(
+        &self,
+        aggregate_id: &str,
+        activity: &Activity,
+    ) -> Result<(), PersistenceError> {
+        let mut request = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("AggregateId", AttributeValue::S(aggregate_id.to_string()))
+            .item("Sequence", AttributeValue::N(activity.sequence.to_string()))
+            .item("EventType", AttributeValue::S(activity.event_type.clone()))
+            .item("Actor", AttributeValue::S(activity.actor.clone()))
+            .item(
+                "OccurredAt",
+                AttributeValue::S(activity.occurred_at.to_rfc3339()),
+            );
+
+        if let Some(comment_id) = &activity.comment_id {
+            request = request.item("CommentId", AttributeValue::S(comment_id.clone()));
+        }
+
+        if let Some(body) = &activity.body {
+            request = request.item("Body", AttributeValue::S(body.clone()));
+        }
+
+        request
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        Ok(())
+    }
+
+    /// Remove the comment text from a Task's feed, for a single comment or for every comment
+    pub async fn remove_bodies(
+        &self,
+        aggregate_id: &str,
+        comment_id: Option<&str>,
+    ) -> Result<(), PersistenceError> {
+        let mut start_key = None;
+
+        loop {
+            let mut request = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("AggregateId = :id")
+                .expression_attribute_values(":id", AttributeValue::S(aggregate_id.to_string()))
+                .projection_expression("#sequence")
+                .expression_attribute_names("#sequence", "Sequence")
+                .set_exclusive_start_key(start_key);
+
+            request = match comment_id {
+                Some(comment_id) => request
+                    .filter_expression("attribute_exists(Body) AND CommentId = :comment_id")
+                    .expression_attribute_values(
+                        ":comment_id",
+                        AttributeValue::S(comment_id.to_string()),
+                    ),
+                None => request.filter_expression("attribute_exists(Body)"),
+            };
+
+            let output = request
+                .send()
+                .await
+                .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+            for item in output.items() {
+                let Some(sequence) = item.get("Sequence") else {
+                    continue;
+                };
+
+                self.client
+                    .update_item()
+                    .table_name(&self.table_name)
+                    .key("AggregateId", AttributeValue::S(aggregate_id.to_string()))
+                    .key("Sequence", sequence.clone())
+                    .update_expression("REMOVE Body")
+                    .send()
+                    .await
+                    .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+            }
+
+            start_key = output.last_evaluated_key().cloned();
+
+            if start_key.is_none() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn decode(item: &HashMap<String, AttributeValue>) -> Result<Activity, PersistenceError> {
+    let missing =
+        |field: &str| PersistenceError::DeserializationError(format!("Missing {field}").into());
+
+    let string = |field: &str| item.get(field).and_then(|v| v.as_s().ok()).cloned();
+
+    let sequence = item
+        .get("Sequence")
+        .and_then(|v| v.as_n().ok())
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| missing("Sequence"))?;
+
+    let occurred_at = string("OccurredAt")
+        .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| missing("OccurredAt"))?;
+
+    Ok(Activity {
+        sequence,
+        event_type: string("EventType").ok_or_else(|| missing("EventType"))?,
+        actor: string("Actor").unwrap_or_default(),
+        occurred_at,
+        comment_id: string("CommentId"),
+        body: string("Body"),
+    })
+}
+
+/// A Query that records every Task event in the Task's activity feed
+pub struct ActivityQuery {
+    feed: Arc<Feed>,
+}
+
+impl ActivityQuery {
+    /// Create a new instance
+    pub fn new(feed: Arc<Feed>) -> Self {
+        Self { feed }
+    }
+
+    async fn update(
+        &self,
+        task_id: &str,
+        events: &[EventEnvelope<Task>],
+    ) -> Result<(), PersistenceError> {
+        for event in events {
+            let (comment_id, body) = match &event.payload {
+                Event::CommentAdded {
+                    comment_id, body, ..
+                }
+                | Event::CommentEdited {
+                    comment_id, body, ..
+                } => (Some(comment_id.clone()), Some(body.clone())),
+                Event::CommentDeleted { comment_id, .. } => (Some(comment_id.clone()), None),
+                _ => (None, None),
+            };
+
+            let activity = Activity {
+                sequence: event.sequence,
+                event_type: event.payload.event_type(),
+                actor: event.metadata.get("subject").cloned().unwrap_or_default(),
+                occurred_at: event.payload.occurred_at(),
+                comment_id,
+                body,
+            };
+
+            self.feed.record(task_id, &activity).await?;
+
+            match &event.payload {
+                Event::CommentDeleted { comment_id, .. } => {
+                    self.feed.remove_bodies(task_id, Some(comment_id)).await?
+                }
+                Event::Erased { .. } => self.feed.remove_bodies(task_id, None).await?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl cqrs_es::Query<Task> for ActivityQuery {
+    async fn dispatch(&self, task_id: &str, events: &[EventEnvelope<Task>]) {
+        if let Err(err) = self.update(task_id, events).await {
+            error!(
+                err:err = err,
+                task_id = task_id;
+                "ActivityQuery: {}",
+                err,
+            );
+        }
+    }
+}
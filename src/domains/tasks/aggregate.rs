@@ -1,20 +1,36 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use cqrs_es::Aggregate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    domains,
+    domains::{self, projects::Project, shredding::REDACTED, tenants},
     utils::Update::{Empty, Unchanged, Value},
 };
 
-use super::{Command, Event};
+use super::{
+    inputs, labels, relations, Command, Event, Priority, Recurrence, Relation, Series, Status,
+};
 
-use Command::{Create, Delete, Update};
-use Event::{Created, Deleted, Updated};
+use Command::{
+    AddComment, Archive, Assign, Block, Complete, Create, CreateOccurrence, Delete, DeleteComment,
+    EditComment, Erase, Link, MarkOverdue, Move, Remind, Reopen, Restore, Start, Unassign, Unlink,
+    Update,
+};
+use Event::{
+    Archived, Assigned, BecameOverdue, Blocked, CommentAdded, CommentDeleted, CommentEdited,
+    Completed, Created, Deleted, Erased, LabelAdded, LabelRemoved, Linked, Moved, PriorityChanged,
+    ReminderDue, Reopened, Restored, Started, Unassigned, Unlinked, Updated,
+};
 
 /// A Task as aggregated within the Event Store
-#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
 pub struct Task {
     /// A unique ID
     pub id: String,
@@ -25,25 +41,171 @@ pub struct Task {
     /// The last updated date
     pub updated_at: DateTime<Utc>,
 
+    /// The subject that created this Task, and is allowed to change it
+    #[serde(default)]
+    pub owner: String,
+
     /// A name
     pub name: String,
 
     /// An optional summary
     pub summary: Option<String>,
 
-    /// Whether this Task is completed or not
-    pub done: bool,
+    /// Where this Task is in its lifecycle. Snapshots and views recorded before the lifecycle was
+    /// introduced have a `done` flag instead, which is read as `Done` or `Todo`.
+    #[serde(
+        default,
+        alias = "done",
+        deserialize_with = "Status::deserialize_legacy"
+    )]
+    pub status: Status,
 
     /// Whether this Task is is active or has been removed
     pub deleted: bool,
+
+    /// When this Task was removed, if it has been
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// Whether this Task's personal data has been permanently erased
+    #[serde(default)]
+    pub erased: bool,
+
+    /// When this Task is due, if it has a deadline
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+
+    /// When to remind the owner about this Task
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Utc>>,
+
+    /// Whether the reminder for the current `remind_at` has been sent
+    #[serde(default)]
+    pub reminded: bool,
+
+    /// Whether this Task passed its current `due_at` without being completed
+    #[serde(default)]
+    pub overdue: bool,
+
+    /// The Project this Task belongs to, if any
+    #[serde(default)]
+    pub project_id: Option<String>,
+
+    /// The Task this is a subtask of, if any
+    #[serde(default)]
+    pub parent_id: Option<String>,
+
+    /// The Tasks that must be done before this one can be completed
+    #[serde(default)]
+    pub blocked_by: BTreeSet<String>,
+
+    /// The author of each comment on this Task that hasn't been deleted, by comment ID. The
+    /// comments themselves are read from the activity feed.
+    #[serde(default)]
+    pub comment_authors: BTreeMap<String, String>,
+
+    /// Labels the Task is filed under, normalized to lowercase
+    #[serde(default)]
+    pub labels: BTreeSet<String>,
+
+    /// How urgent the Task is
+    #[serde(default)]
+    pub priority: Priority,
+
+    /// The subjects responsible for this Task
+    #[serde(default)]
+    pub assignees: BTreeSet<String>,
+
+    /// How often the Task repeats once it's completed
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+
+    /// The series this Task is an occurrence of, if it was created by an earlier one
+    #[serde(default)]
+    pub series: Option<Series>,
 }
 
 /// The Aggregate Type constant
 pub const AGGREGATE_TYPE: &str = "Task";
 
-/// Services needed by the Task Aggregate (currently none)
-#[derive(Clone, Default)]
-pub struct Services {}
+/// Decides whether a subject may change an existing Task
+pub trait Authorizer: Send + Sync {
+    /// Return `Forbidden` if the subject may not change the Task
+    fn authorize(&self, subject: &str, task: &Task) -> Result<(), domains::Error>;
+
+    /// Return `Forbidden` if the subject may not record progress on the Task, by starting,
+    /// blocking, or completing it, or commenting on it. The Task's assignees can, along with
+    /// anyone allowed to change it.
+    fn authorize_progress(&self, subject: &str, task: &Task) -> Result<(), domains::Error> {
+        if task.assignees.contains(subject) {
+            return Ok(());
+        }
+
+        self.authorize(subject, task)
+    }
+}
+
+/// Only allow a Task's owner to change it
+///
+/// Tasks created before ownership was tracked have no owner, and remain open to any
+/// authenticated subject.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OwnerOnly;
+
+impl Authorizer for OwnerOnly {
+    fn authorize(&self, subject: &str, task: &Task) -> Result<(), domains::Error> {
+        if task.owner.is_empty() || task.owner == subject {
+            return Ok(());
+        }
+
+        Err(domains::Error::Forbidden)
+    }
+}
+
+/// Looks up the Projects that Tasks are moved into
+#[async_trait]
+pub trait Projects: Send + Sync {
+    /// Load a Project by its tenant-scoped aggregate ID
+    async fn find(&self, aggregate_id: &str) -> Result<Option<Project>, domains::Error>;
+}
+
+/// Looks up other Tasks, to check links between them
+#[async_trait]
+pub trait Tasks: Send + Sync {
+    /// Load a Task by its tenant-scoped aggregate ID
+    async fn find(&self, aggregate_id: &str) -> Result<Option<Task>, domains::Error>;
+}
+
+/// The default amount of time a deleted Task can still be restored
+pub const DEFAULT_RESTORE_GRACE_PERIOD_DAYS: i64 = 30;
+
+/// Services needed by the Task Aggregate
+#[derive(Clone)]
+pub struct Services {
+    /// The authorization policy for changes to existing Tasks
+    pub authorizer: Arc<dyn Authorizer>,
+
+    /// How long after deletion a Task can still be restored
+    pub restore_grace_period: Duration,
+
+    /// Where Projects are looked up when a Task is moved
+    pub projects: Arc<dyn Projects>,
+
+    /// Where other Tasks are looked up when Tasks are linked or completed
+    pub tasks: Arc<dyn Tasks>,
+}
+
+impl Services {
+    /// Create a new instance with the default policies
+    pub fn new(projects: Arc<dyn Projects>, tasks: Arc<dyn Tasks>) -> Self {
+        Self {
+            authorizer: Arc::new(OwnerOnly),
+            restore_grace_period: Duration::days(DEFAULT_RESTORE_GRACE_PERIOD_DAYS),
+            projects,
+            tasks,
+        }
+    }
+}
 
 #[async_trait]
 impl Aggregate for Task {
@@ -59,10 +221,10 @@ impl Aggregate for Task {
     async fn handle(
         &self,
         command: Self::Command,
-        _services: &Self::Services,
+        services: &Self::Services,
     ) -> Result<Vec<Self::Event>, Self::Error> {
         match command {
-            Create { id, input } => {
+            Create { id, owner, input } => {
                 self.validate_new()?;
 
                 let created_at = Utc::now();
@@ -70,36 +232,539 @@ impl Aggregate for Task {
                 Ok(vec![Created {
                     id: id.clone(),
                     created_at,
-                    task: Task {
+                    task: Box::new(Task {
                         id,
                         created_at,
                         updated_at: created_at,
+                        owner,
                         name: input.name,
                         summary: input.summary,
-                        done: false,
+                        status: Status::Todo,
                         deleted: false,
-                    },
+                        deleted_at: None,
+                        erased: false,
+                        due_at: input.due_at,
+                        remind_at: input.remind_at,
+                        reminded: false,
+                        overdue: false,
+                        project_id: None,
+                        parent_id: None,
+                        blocked_by: BTreeSet::new(),
+                        comment_authors: BTreeMap::new(),
+                        labels: labels::normalize(&input.labels),
+                        priority: input.priority,
+                        assignees: BTreeSet::new(),
+                        recurrence: input.recurrence,
+                        series: None,
+                    }),
+                }])
+            }
+
+            CreateOccurrence { previous } => {
+                self.validate_new()?;
+
+                if previous.status != Status::Done || previous.deleted {
+                    return Err(domains::Error::InvalidState {
+                        reason: "Only completed Tasks are followed by another occurrence"
+                            .to_string(),
+                    });
+                }
+
+                let (series, due_at) =
+                    previous
+                        .next_occurrence()
+                        .ok_or_else(|| domains::Error::InvalidState {
+                            reason: "The series has no more occurrences".to_string(),
+                        })?;
+
+                let id = series
+                    .task_id()
+                    .ok_or_else(|| domains::Error::InvalidState {
+                        reason: "The series ID isn't a ULID".to_string(),
+                    })?;
+
+                // Keep the reminder the same distance ahead of the due date
+                let remind_at = previous
+                    .remind_at
+                    .zip(previous.due_at)
+                    .map(|(remind_at, previous_due_at)| remind_at + (due_at - previous_due_at));
+
+                let created_at = Utc::now();
+
+                let task = Task {
+                    id: id.clone(),
+                    created_at,
+                    updated_at: created_at,
+                    owner: previous.owner.clone(),
+                    name: previous.name.clone(),
+                    summary: previous.summary.clone(),
+                    status: Status::Todo,
+                    due_at: Some(due_at),
+                    remind_at,
+                    project_id: previous.project_id.clone(),
+                    labels: previous.labels.clone(),
+                    priority: previous.priority,
+                    recurrence: previous.recurrence.clone(),
+                    series: Some(series),
+                    ..Task::default()
+                };
+
+                // Assignments are recorded as their own events, so the assignees' inboxes and
+                // notifications pick the new occurrence up
+                let assignments = previous.assignees.iter().map(|assignee| Assigned {
+                    id: id.clone(),
+                    assignee: assignee.clone(),
+                    assigned_by: previous.owner.clone(),
+                    updated_at: created_at,
+                });
+
+                Ok(std::iter::once(Created {
+                    id: id.clone(),
+                    created_at,
+                    task: Box::new(task),
+                })
+                .chain(assignments)
+                .collect())
+            }
+
+            Update { subject, input } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                if self.status == Status::Archived {
+                    return Err(domains::Error::InvalidState {
+                        reason: "Archived Tasks can't be changed".to_string(),
+                    });
+                }
+
+                let updated_at = Utc::now();
+
+                // Labels and priority have their own events, so the Update only records the rest
+                let added = labels::normalize(&input.add_labels);
+                let removed = labels::normalize(&input.remove_labels);
+                let priority = input.priority.filter(|priority| *priority != self.priority);
+
+                let mut events: Vec<Event> = added
+                    .into_iter()
+                    .filter(|label| !self.labels.contains(label))
+                    .map(|label| LabelAdded {
+                        id: self.id.clone(),
+                        label,
+                        updated_at,
+                    })
+                    .chain(
+                        removed
+                            .into_iter()
+                            .filter(|label| self.labels.contains(label))
+                            .map(|label| LabelRemoved {
+                                id: self.id.clone(),
+                                label,
+                                updated_at,
+                            }),
+                    )
+                    .chain(priority.map(|priority| PriorityChanged {
+                        id: self.id.clone(),
+                        priority,
+                        updated_at,
+                    }))
+                    .collect();
+
+                if input.changes_details() || events.is_empty() {
+                    events.insert(
+                        0,
+                        Updated {
+                            id: self.id.clone(),
+                            updated_at,
+                            update: inputs::Update {
+                                add_labels: BTreeSet::new(),
+                                remove_labels: BTreeSet::new(),
+                                priority: None,
+                                ..input
+                            },
+                            status: None,
+                        },
+                    );
+                }
+
+                Ok(events)
+            }
+
+            Delete { subject } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                Ok(vec![Deleted {
+                    id: self.id.clone(),
+                    updated_at: Utc::now(),
                 }])
             }
 
-            Update(update) => {
+            Restore { subject } => {
+                self.validate_deleted()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                let updated_at = Utc::now();
+
+                // Tasks deleted before deletion times were tracked can no longer be restored
+                let expired = self.deleted_at.map_or(true, |deleted_at| {
+                    updated_at - deleted_at > services.restore_grace_period
+                });
+
+                if expired {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The restore grace period has expired".to_string(),
+                    });
+                }
+
+                Ok(vec![Restored {
+                    id: self.id.clone(),
+                    updated_at,
+                }])
+            }
+
+            Erase { subject } => {
+                if self.id.is_empty() {
+                    return Err(domains::Error::NotFound {
+                        entity: AGGREGATE_TYPE.to_string(),
+                    });
+                }
+
+                if self.erased {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The Task has already been erased".to_string(),
+                    });
+                }
+
+                services.authorizer.authorize(&subject, self)?;
+
+                Ok(vec![Erased {
+                    id: self.id.clone(),
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Start { subject } => {
                 self.validate_existing()?;
+                services.authorizer.authorize_progress(&subject, self)?;
+                self.validate_transition(Status::InProgress)?;
 
-                Ok(vec![Updated {
+                Ok(vec![Started {
                     id: self.id.clone(),
                     updated_at: Utc::now(),
-                    update,
                 }])
             }
 
-            Delete => {
+            Block { subject, input } => {
                 self.validate_existing()?;
+                services.authorizer.authorize_progress(&subject, self)?;
+                self.validate_transition(Status::Blocked)?;
 
-                Ok(vec![Deleted {
+                Ok(vec![Blocked {
+                    id: self.id.clone(),
+                    reason: input.reason,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Complete { subject, tenant } => {
+                self.validate_existing()?;
+                services.authorizer.authorize_progress(&subject, self)?;
+                self.validate_transition(Status::Done)?;
+
+                let mut open_blockers = Vec::new();
+                for blocker_id in &self.blocked_by {
+                    let blocker = services
+                        .tasks
+                        .find(&tenants::scoped_id(&tenant, blocker_id))
+                        .await?;
+
+                    // Blockers that were deleted no longer hold anything up
+                    if blocker.is_some_and(|blocker| !blocker.deleted && blocker.status.is_open()) {
+                        open_blockers.push(blocker_id.as_str());
+                    }
+                }
+
+                if !open_blockers.is_empty() {
+                    return Err(domains::Error::InvalidState {
+                        reason: format!(
+                            "The Task is blocked by open Tasks: {}",
+                            open_blockers.join(", ")
+                        ),
+                    });
+                }
+
+                Ok(vec![Completed {
                     id: self.id.clone(),
                     updated_at: Utc::now(),
                 }])
             }
+
+            Reopen { subject } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+                self.validate_transition(Status::Todo)?;
+
+                Ok(vec![Reopened {
+                    id: self.id.clone(),
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Archive { subject } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+                self.validate_transition(Status::Archived)?;
+
+                Ok(vec![Archived {
+                    id: self.id.clone(),
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Move {
+                subject,
+                tenant,
+                input,
+            } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                if self.status == Status::Archived {
+                    return Err(domains::Error::InvalidState {
+                        reason: "Archived Tasks can't be changed".to_string(),
+                    });
+                }
+
+                if input.project_id == self.project_id {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The Task is already there".to_string(),
+                    });
+                }
+
+                // Taking a Task out of a Project needs no checks against the Project, so Tasks
+                // can still leave an archived or deleted one
+                if let Some(project_id) = &input.project_id {
+                    let project = services
+                        .projects
+                        .find(&tenants::scoped_id(&tenant, project_id))
+                        .await?
+                        .unwrap_or_default();
+
+                    project.validate_accepts_tasks()?;
+                    project.authorize(&subject)?;
+                }
+
+                Ok(vec![Moved {
+                    id: self.id.clone(),
+                    from_project_id: self.project_id.clone(),
+                    project_id: input.project_id,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Link {
+                subject,
+                tenant,
+                input,
+            } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                if input.relation.targets(self).contains(&input.task_id) {
+                    return Err(domains::Error::InvalidState {
+                        reason: format!("The Task is already linked as {}", input.relation),
+                    });
+                }
+
+                if input.relation == Relation::Parent && self.parent_id.is_some() {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The Task already has a parent".to_string(),
+                    });
+                }
+
+                let target = services
+                    .tasks
+                    .find(&tenants::scoped_id(&tenant, &input.task_id))
+                    .await?
+                    .filter(|target| !target.deleted)
+                    .ok_or(domains::Error::NotFound {
+                        entity: AGGREGATE_TYPE.to_string(),
+                    })?;
+
+                services.authorizer.authorize(&subject, &target)?;
+
+                let cycle = relations::creates_cycle(
+                    services.tasks.as_ref(),
+                    &tenant,
+                    input.relation,
+                    &self.id,
+                    &input.task_id,
+                )
+                .await?;
+
+                if cycle {
+                    return Err(domains::Error::InvalidState {
+                        reason: format!(
+                            "Linking the Task as {} would create a cycle",
+                            input.relation
+                        ),
+                    });
+                }
+
+                Ok(vec![Linked {
+                    id: self.id.clone(),
+                    relation: input.relation,
+                    task_id: input.task_id,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Unlink { subject, input } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                if !input.relation.targets(self).contains(&input.task_id) {
+                    return Err(domains::Error::InvalidState {
+                        reason: format!("The Task isn't linked as {}", input.relation),
+                    });
+                }
+
+                Ok(vec![Unlinked {
+                    id: self.id.clone(),
+                    relation: input.relation,
+                    task_id: input.task_id,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Assign { subject, input } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                if self.assignees.contains(&input.assignee) {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The Task is already assigned to them".to_string(),
+                    });
+                }
+
+                Ok(vec![Assigned {
+                    id: self.id.clone(),
+                    assignee: input.assignee,
+                    assigned_by: subject,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Unassign { subject, input } => {
+                self.validate_existing()?;
+                services.authorizer.authorize(&subject, self)?;
+
+                if !self.assignees.contains(&input.assignee) {
+                    return Err(domains::Error::InvalidState {
+                        reason: "The Task isn't assigned to them".to_string(),
+                    });
+                }
+
+                Ok(vec![Unassigned {
+                    id: self.id.clone(),
+                    assignee: input.assignee,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            AddComment {
+                subject,
+                comment_id,
+                input,
+            } => {
+                self.validate_existing()?;
+                services.authorizer.authorize_progress(&subject, self)?;
+
+                if self.comment_authors.contains_key(&comment_id) {
+                    return Err(domains::Error::Uniqueness {
+                        field: "comment_id".to_string(),
+                    });
+                }
+
+                Ok(vec![CommentAdded {
+                    id: self.id.clone(),
+                    comment_id,
+                    author: subject,
+                    body: input.body,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            EditComment {
+                subject,
+                comment_id,
+                input,
+            } => {
+                self.validate_comment_author(&comment_id, &subject)?;
+
+                Ok(vec![CommentEdited {
+                    id: self.id.clone(),
+                    comment_id,
+                    body: input.body,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            DeleteComment {
+                subject,
+                comment_id,
+            } => {
+                self.validate_comment_author(&comment_id, &subject)?;
+
+                Ok(vec![CommentDeleted {
+                    id: self.id.clone(),
+                    comment_id,
+                    updated_at: Utc::now(),
+                }])
+            }
+
+            Remind => {
+                self.validate_existing()?;
+
+                let updated_at = Utc::now();
+
+                match self.remind_at {
+                    Some(remind_at) if remind_at <= updated_at && !self.reminded => {
+                        Ok(vec![ReminderDue {
+                            id: self.id.clone(),
+                            owner: self.owner.clone(),
+                            remind_at,
+                            due_at: self.due_at,
+                            updated_at,
+                        }])
+                    }
+                    _ => Err(domains::Error::InvalidState {
+                        reason: "No reminder is due for the Task".to_string(),
+                    }),
+                }
+            }
+
+            MarkOverdue => {
+                self.validate_existing()?;
+
+                let updated_at = Utc::now();
+
+                match self.due_at {
+                    Some(due_at)
+                        if due_at <= updated_at && self.status.is_open() && !self.overdue =>
+                    {
+                        Ok(vec![BecameOverdue {
+                            id: self.id.clone(),
+                            owner: self.owner.clone(),
+                            due_at,
+                            updated_at,
+                        }])
+                    }
+                    _ => Err(domains::Error::InvalidState {
+                        reason: "The Task isn't overdue".to_string(),
+                    }),
+                }
+            }
         }
     }
 
@@ -113,14 +778,29 @@ impl Aggregate for Task {
                 self.id = id;
                 self.created_at = created_at;
                 self.updated_at = created_at;
+                self.owner = task.owner;
                 self.name = task.name;
                 self.summary = task.summary;
-                self.done = task.done;
+                self.status = task.status;
                 self.deleted = task.deleted;
+                self.due_at = task.due_at;
+                self.remind_at = task.remind_at;
+                self.project_id = task.project_id;
+                self.parent_id = task.parent_id;
+                self.blocked_by = task.blocked_by;
+                self.comment_authors = task.comment_authors;
+                self.labels = task.labels;
+                self.priority = task.priority;
+                self.assignees = task.assignees;
+                self.recurrence = task.recurrence;
+                self.series = task.series;
             }
 
             Updated {
-                update, updated_at, ..
+                update,
+                status,
+                updated_at,
+                ..
             } => {
                 if let Some(name) = update.name {
                     self.name = name;
@@ -138,8 +818,23 @@ impl Aggregate for Task {
                     }
                 }
 
-                if let Some(done) = update.done {
-                    self.done = done;
+                if let Some(status) = status {
+                    self.status = status;
+                }
+
+                // A new deadline or reminder time starts over, even if the last one passed
+                if update.due_at.is_changed() {
+                    self.due_at = update.due_at.take();
+                    self.overdue = false;
+                }
+
+                if update.remind_at.is_changed() {
+                    self.remind_at = update.remind_at.take();
+                    self.reminded = false;
+                }
+
+                if update.recurrence.is_changed() {
+                    self.recurrence = update.recurrence.take();
                 }
 
                 self.updated_at = updated_at;
@@ -147,6 +842,155 @@ impl Aggregate for Task {
 
             Deleted { updated_at, .. } => {
                 self.deleted = true;
+                self.deleted_at = Some(updated_at);
+                self.updated_at = updated_at;
+            }
+
+            Restored { updated_at, .. } => {
+                self.deleted = false;
+                self.deleted_at = None;
+                self.updated_at = updated_at;
+            }
+
+            Erased { updated_at, .. } => {
+                // The key protecting this data is destroyed along with the event, so render it
+                // the same way earlier events now load
+                self.name = REDACTED.to_string();
+                self.summary = self.summary.as_ref().map(|_| REDACTED.to_string());
+                self.labels.clear();
+                self.erased = true;
+                self.deleted = true;
+                self.deleted_at = self.deleted_at.or(Some(updated_at));
+                self.updated_at = updated_at;
+            }
+
+            Started { updated_at, .. } => {
+                self.status = Status::InProgress;
+                self.updated_at = updated_at;
+            }
+
+            Blocked { updated_at, .. } => {
+                self.status = Status::Blocked;
+                self.updated_at = updated_at;
+            }
+
+            Completed { updated_at, .. } => {
+                self.status = Status::Done;
+                self.updated_at = updated_at;
+            }
+
+            Reopened { updated_at, .. } => {
+                self.status = Status::Todo;
+                self.updated_at = updated_at;
+            }
+
+            Archived { updated_at, .. } => {
+                self.status = Status::Archived;
+                self.updated_at = updated_at;
+            }
+
+            Moved {
+                project_id,
+                updated_at,
+                ..
+            } => {
+                self.project_id = project_id;
+                self.updated_at = updated_at;
+            }
+
+            Linked {
+                relation,
+                task_id,
+                updated_at,
+                ..
+            } => {
+                match relation {
+                    Relation::Parent => self.parent_id = Some(task_id),
+                    Relation::BlockedBy => {
+                        self.blocked_by.insert(task_id);
+                    }
+                }
+
+                self.updated_at = updated_at;
+            }
+
+            Unlinked {
+                relation,
+                task_id,
+                updated_at,
+                ..
+            } => {
+                match relation {
+                    Relation::Parent => self.parent_id = None,
+                    Relation::BlockedBy => {
+                        self.blocked_by.remove(&task_id);
+                    }
+                }
+
+                self.updated_at = updated_at;
+            }
+
+            Assigned {
+                assignee,
+                updated_at,
+                ..
+            } => {
+                self.assignees.insert(assignee);
+                self.updated_at = updated_at;
+            }
+
+            Unassigned {
+                assignee,
+                updated_at,
+                ..
+            } => {
+                self.assignees.remove(&assignee);
+                self.updated_at = updated_at;
+            }
+
+            LabelAdded {
+                label, updated_at, ..
+            } => {
+                self.labels.insert(label);
+                self.updated_at = updated_at;
+            }
+
+            LabelRemoved {
+                label, updated_at, ..
+            } => {
+                self.labels.remove(&label);
+                self.updated_at = updated_at;
+            }
+
+            PriorityChanged {
+                priority,
+                updated_at,
+                ..
+            } => {
+                self.priority = priority;
+                self.updated_at = updated_at;
+            }
+
+            // Comments are kept apart from the Task itself, so they don't change `updated_at`
+            CommentAdded {
+                comment_id, author, ..
+            } => {
+                self.comment_authors.insert(comment_id, author);
+            }
+
+            CommentEdited { .. } => {}
+
+            CommentDeleted { comment_id, .. } => {
+                self.comment_authors.remove(&comment_id);
+            }
+
+            ReminderDue { updated_at, .. } => {
+                self.reminded = true;
+                self.updated_at = updated_at;
+            }
+
+            BecameOverdue { updated_at, .. } => {
+                self.overdue = true;
                 self.updated_at = updated_at;
             }
         }
@@ -154,6 +998,22 @@ impl Aggregate for Task {
 }
 
 impl Task {
+    /// The series position and due date of the occurrence after this one, or `None` if the
+    /// Task doesn't repeat or its series has ended. Tasks without a due date repeat from when
+    /// they were last updated, which for a completed Task is when it was completed.
+    pub fn next_occurrence(&self) -> Option<(Series, DateTime<Utc>)> {
+        let recurrence = self.recurrence.as_ref()?;
+        let from = self.due_at.unwrap_or(self.updated_at);
+        let series = self
+            .series
+            .clone()
+            .unwrap_or_else(|| Series::start(&self.id, from));
+
+        let due_at = recurrence.next(series.occurrence, series.starts_at, from)?;
+
+        Some((series.next(), due_at))
+    }
+
     fn validate_new(&self) -> Result<(), domains::Error> {
         if !self.id.is_empty() {
             // A Universe with this ID already exists, so there is a uniqueness conflict
@@ -179,4 +1039,705 @@ impl Task {
 
         Ok(())
     }
+
+    fn validate_transition(&self, next: Status) -> Result<(), domains::Error> {
+        if !self.status.can_become(next) {
+            return Err(domains::Error::InvalidState {
+                reason: format!("A {} Task can't become {next}", self.status),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_comment_author(
+        &self,
+        comment_id: &str,
+        subject: &str,
+    ) -> Result<(), domains::Error> {
+        self.validate_existing()?;
+
+        match self.comment_authors.get(comment_id) {
+            None => Err(domains::Error::NotFound {
+                entity: "Comment".to_string(),
+            }),
+            Some(author) if author != subject => Err(domains::Error::Forbidden),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn validate_deleted(&self) -> Result<(), domains::Error> {
+        if self.id.is_empty() {
+            return Err(domains::Error::NotFound {
+                entity: AGGREGATE_TYPE.to_string(),
+            });
+        }
+
+        if !self.deleted {
+            return Err(domains::Error::InvalidState {
+                reason: "Only deleted Tasks can be restored".to_string(),
+            });
+        }
+
+        if self.erased {
+            return Err(domains::Error::InvalidState {
+                reason: "Erased Tasks can't be restored".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cqrs_es::{test::TestFramework, DomainEvent};
+
+    use super::*;
+
+    const TASK_ID: &str = "task-1";
+    const OWNER: &str = "user-1";
+    const TENANT: &str = "acme";
+
+    /// Other Tasks in the tenant, by unscoped ID
+    #[derive(Clone, Default)]
+    struct FakeTasks(HashMap<String, Task>);
+
+    #[async_trait]
+    impl Tasks for FakeTasks {
+        async fn find(&self, aggregate_id: &str) -> Result<Option<Task>, domains::Error> {
+            let id = tenants::split_id(aggregate_id).map_or(aggregate_id, |(_, id)| id);
+
+            Ok(self.0.get(id).cloned())
+        }
+    }
+
+    struct NoProjects;
+
+    #[async_trait]
+    impl Projects for NoProjects {
+        async fn find(&self, _aggregate_id: &str) -> Result<Option<Project>, domains::Error> {
+            Ok(None)
+        }
+    }
+
+    fn services(tasks: FakeTasks) -> Services {
+        Services::new(Arc::new(NoProjects), Arc::new(tasks))
+    }
+
+    fn framework() -> TestFramework<Task> {
+        TestFramework::with(services(FakeTasks::default()))
+    }
+
+    fn created(owner: &str) -> Event {
+        Created {
+            id: TASK_ID.to_string(),
+            created_at: Utc::now(),
+            task: Box::new(Task {
+                id: TASK_ID.to_string(),
+                owner: owner.to_string(),
+                name: "Write the report".to_string(),
+                ..Task::default()
+            }),
+        }
+    }
+
+    fn rename(subject: &str) -> Command {
+        Update {
+            subject: subject.to_string(),
+            input: inputs::Update {
+                name: Some("Write the summary".to_string()),
+                ..inputs::Update::default()
+            },
+        }
+    }
+
+    /// The types of the events a command recorded
+    fn event_types(result: Result<Vec<Event>, domains::Error>) -> Vec<String> {
+        result
+            .expect("The command was accepted")
+            .iter()
+            .map(Event::event_type)
+            .collect()
+    }
+
+    #[test]
+    fn created_tasks_are_owned_by_their_creator() {
+        let events = framework()
+            .given_no_previous_events()
+            .when(Create {
+                id: TASK_ID.to_string(),
+                owner: OWNER.to_string(),
+                input: inputs::Create {
+                    name: "Write the report".to_string(),
+                    ..inputs::Create::default()
+                },
+            })
+            .inspect_result()
+            .unwrap();
+
+        assert!(matches!(&events[..], [Created { task, .. }] if task.owner == OWNER));
+    }
+
+    #[test]
+    fn owners_can_change_their_tasks() {
+        let result = framework()
+            .given(vec![created(OWNER)])
+            .when(rename(OWNER))
+            .inspect_result();
+
+        assert_eq!(event_types(result), ["Task:Updated"]);
+    }
+
+    #[test]
+    fn other_subjects_are_forbidden() {
+        framework()
+            .given(vec![created(OWNER)])
+            .when(rename("user-2"))
+            .then_expect_error_message("Forbidden");
+
+        framework()
+            .given(vec![created(OWNER)])
+            .when(Delete {
+                subject: "user-2".to_string(),
+            })
+            .then_expect_error_message("Forbidden");
+    }
+
+    #[test]
+    fn tasks_without_an_owner_are_open_to_any_subject() {
+        let result = framework()
+            .given(vec![created("")])
+            .when(rename("user-2"))
+            .inspect_result();
+
+        assert_eq!(event_types(result), ["Task:Updated"]);
+    }
+
+    fn deleted(days_ago: i64) -> Event {
+        Deleted {
+            id: TASK_ID.to_string(),
+            updated_at: Utc::now() - Duration::days(days_ago),
+        }
+    }
+
+    fn restore() -> Command {
+        Restore {
+            subject: OWNER.to_string(),
+        }
+    }
+
+    #[test]
+    fn deleted_tasks_can_be_restored_within_the_grace_period() {
+        let result = framework()
+            .given(vec![
+                created(OWNER),
+                deleted(DEFAULT_RESTORE_GRACE_PERIOD_DAYS - 1),
+            ])
+            .when(restore())
+            .inspect_result();
+
+        assert_eq!(event_types(result), ["Task:Restored"]);
+    }
+
+    #[test]
+    fn deleted_tasks_cant_be_restored_after_the_grace_period() {
+        framework()
+            .given(vec![
+                created(OWNER),
+                deleted(DEFAULT_RESTORE_GRACE_PERIOD_DAYS + 1),
+            ])
+            .when(restore())
+            .then_expect_error_message("Invalid state: The restore grace period has expired");
+
+        let short = Services {
+            restore_grace_period: Duration::days(1),
+            ..services(FakeTasks::default())
+        };
+
+        TestFramework::<Task>::with(short)
+            .given(vec![created(OWNER), deleted(2)])
+            .when(restore())
+            .then_expect_error_message("Invalid state: The restore grace period has expired");
+    }
+
+    #[test]
+    fn only_deleted_tasks_can_be_restored() {
+        framework()
+            .given(vec![created(OWNER)])
+            .when(restore())
+            .then_expect_error_message("Invalid state: Only deleted Tasks can be restored");
+
+        framework()
+            .given(vec![created(OWNER), deleted(1)])
+            .when(Restore {
+                subject: "user-2".to_string(),
+            })
+            .then_expect_error_message("Forbidden");
+    }
+
+    #[test]
+    fn tasks_deleted_before_deletion_times_were_tracked_cant_be_restored() {
+        let legacy = Created {
+            id: TASK_ID.to_string(),
+            created_at: Utc::now(),
+            task: Box::new(Task {
+                id: TASK_ID.to_string(),
+                owner: OWNER.to_string(),
+                deleted: true,
+                ..Task::default()
+            }),
+        };
+
+        framework()
+            .given(vec![legacy])
+            .when(restore())
+            .then_expect_error_message("Invalid state: The restore grace period has expired");
+    }
+
+    #[test]
+    fn tasks_can_be_erased_once() {
+        let erase = || Erase {
+            subject: OWNER.to_string(),
+        };
+
+        let result = framework()
+            .given(vec![created(OWNER), deleted(1)])
+            .when(erase())
+            .inspect_result();
+
+        assert_eq!(event_types(result), ["Task:Erased"]);
+
+        framework()
+            .given(vec![
+                created(OWNER),
+                Erased {
+                    id: TASK_ID.to_string(),
+                    updated_at: Utc::now(),
+                },
+            ])
+            .when(erase())
+            .then_expect_error_message("Invalid state: The Task has already been erased");
+
+        framework()
+            .given(vec![created(OWNER)])
+            .when(Erase {
+                subject: "user-2".to_string(),
+            })
+            .then_expect_error_message("Forbidden");
+    }
+
+    #[test]
+    fn erasing_a_task_drops_its_personal_data() {
+        let mut task = Task {
+            id: TASK_ID.to_string(),
+            owner: OWNER.to_string(),
+            name: "Call Alice".to_string(),
+            summary: Some("About the move".to_string()),
+            labels: BTreeSet::from(["alice".to_string()]),
+            ..Task::default()
+        };
+
+        task.apply(Erased {
+            id: TASK_ID.to_string(),
+            updated_at: Utc::now(),
+        });
+
+        assert_eq!(task.name, REDACTED);
+        assert_eq!(task.summary.as_deref(), Some(REDACTED));
+        assert!(task.labels.is_empty());
+        assert!(task.erased && task.deleted);
+    }
+
+    #[test]
+    fn erased_tasks_cant_be_restored() {
+        framework()
+            .given(vec![
+                created(OWNER),
+                Erased {
+                    id: TASK_ID.to_string(),
+                    updated_at: Utc::now(),
+                },
+            ])
+            .when(restore())
+            .then_expect_error_message("Invalid state: Erased Tasks can't be restored");
+    }
+
+    fn with_status(status: Status) -> Event {
+        let Created {
+            id,
+            created_at,
+            task,
+        } = created(OWNER)
+        else {
+            unreachable!()
+        };
+
+        Created {
+            id,
+            created_at,
+            task: Box::new(Task { status, ..*task }),
+        }
+    }
+
+    /// The command that moves a Task to `status`, and the event it records
+    fn transition(status: Status) -> (Command, &'static str) {
+        let subject = OWNER.to_string();
+
+        match status {
+            Status::Todo => (Reopen { subject }, "Task:Reopened"),
+            Status::InProgress => (Start { subject }, "Task:Started"),
+            Status::Blocked => (
+                Block {
+                    subject,
+                    input: inputs::Block::default(),
+                },
+                "Task:Blocked",
+            ),
+            Status::Done => (
+                Complete {
+                    subject,
+                    tenant: TENANT.to_string(),
+                },
+                "Task:Completed",
+            ),
+            Status::Archived => (Archive { subject }, "Task:Archived"),
+        }
+    }
+
+    #[test]
+    fn tasks_only_move_through_their_lifecycle() {
+        use Status::{Archived, Blocked, Done, InProgress, Todo};
+
+        let allowed = [
+            (Todo, InProgress),
+            (Todo, Blocked),
+            (Todo, Done),
+            (Todo, Archived),
+            (InProgress, Blocked),
+            (InProgress, Done),
+            (InProgress, Archived),
+            (Blocked, InProgress),
+            (Blocked, Archived),
+            (Done, Todo),
+            (Done, Archived),
+            (Archived, Todo),
+        ];
+
+        for from in [Todo, InProgress, Blocked, Done, Archived] {
+            for to in [Todo, InProgress, Blocked, Done, Archived] {
+                let (command, event_type) = transition(to);
+                let result = framework()
+                    .given(vec![with_status(from)])
+                    .when(command)
+                    .inspect_result();
+
+                if allowed.contains(&(from, to)) {
+                    assert_eq!(event_types(result), [event_type], "{from} -> {to}");
+                } else {
+                    assert_eq!(
+                        result.unwrap_err().to_string(),
+                        format!("Invalid state: A {from} Task can't become {to}"),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn transitions_are_restricted_to_the_owner() {
+        framework()
+            .given(vec![created(OWNER)])
+            .when(Start {
+                subject: "user-2".to_string(),
+            })
+            .then_expect_error_message("Forbidden");
+    }
+
+    #[test]
+    fn archived_tasks_cant_be_changed() {
+        framework()
+            .given(vec![with_status(Status::Archived)])
+            .when(rename(OWNER))
+            .then_expect_error_message("Invalid state: Archived Tasks can't be changed");
+    }
+
+    /// Another Task in the tenant, owned by the same subject
+    fn other(id: &str, status: Status) -> (String, Task) {
+        (
+            id.to_string(),
+            Task {
+                id: id.to_string(),
+                owner: OWNER.to_string(),
+                status,
+                ..Task::default()
+            },
+        )
+    }
+
+    fn blocked_by(task_id: &str) -> Event {
+        Linked {
+            id: TASK_ID.to_string(),
+            relation: Relation::BlockedBy,
+            task_id: task_id.to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn complete() -> Command {
+        Complete {
+            subject: OWNER.to_string(),
+            tenant: TENANT.to_string(),
+        }
+    }
+
+    #[test]
+    fn tasks_with_open_blockers_cant_be_completed() {
+        let tasks = FakeTasks(HashMap::from([
+            other("task-2", Status::InProgress),
+            other("task-3", Status::Done),
+        ]));
+
+        TestFramework::<Task>::with(services(tasks))
+            .given(vec![
+                created(OWNER),
+                blocked_by("task-2"),
+                blocked_by("task-3"),
+            ])
+            .when(complete())
+            .then_expect_error_message("Invalid state: The Task is blocked by open Tasks: task-2");
+    }
+
+    #[test]
+    fn tasks_blocked_by_finished_or_deleted_tasks_can_be_completed() {
+        let (id, deleted) = other("task-4", Status::Todo);
+        let tasks = FakeTasks(HashMap::from([
+            other("task-2", Status::Done),
+            other("task-3", Status::Archived),
+            (
+                id,
+                Task {
+                    deleted: true,
+                    ..deleted
+                },
+            ),
+        ]));
+
+        let result = TestFramework::<Task>::with(services(tasks))
+            .given(vec![
+                created(OWNER),
+                blocked_by("task-2"),
+                blocked_by("task-3"),
+                blocked_by("task-4"),
+                blocked_by("task-5"),
+            ])
+            .when(complete())
+            .inspect_result();
+
+        assert_eq!(event_types(result), ["Task:Completed"]);
+    }
+
+    #[test]
+    fn links_that_would_create_a_cycle_are_rejected() {
+        // task-2 is already waiting on task-3, which is waiting on this Task
+        let waiting_on = |id: &str, blocker: &str| {
+            let (id, task) = other(id, Status::Todo);
+
+            (
+                id,
+                Task {
+                    blocked_by: BTreeSet::from([blocker.to_string()]),
+                    ..task
+                },
+            )
+        };
+        let tasks = FakeTasks(HashMap::from([
+            waiting_on("task-2", "task-3"),
+            waiting_on("task-3", TASK_ID),
+        ]));
+
+        let link = |relation| Link {
+            subject: OWNER.to_string(),
+            tenant: TENANT.to_string(),
+            input: inputs::Link {
+                relation,
+                task_id: "task-2".to_string(),
+            },
+        };
+
+        TestFramework::<Task>::with(services(tasks.clone()))
+            .given(vec![created(OWNER)])
+            .when(link(Relation::BlockedBy))
+            .then_expect_error_message(
+                "Invalid state: Linking the Task as BlockedBy would create a cycle",
+            );
+
+        // Parent links are followed separately, so the same Tasks don't form a cycle there
+        let result = TestFramework::<Task>::with(services(tasks))
+            .given(vec![created(OWNER)])
+            .when(link(Relation::Parent))
+            .inspect_result();
+
+        assert_eq!(event_types(result), ["Task:Linked"]);
+    }
+
+    const ASSIGNEE: &str = "user-2";
+
+    fn assigned() -> Event {
+        Assigned {
+            id: TASK_ID.to_string(),
+            assignee: ASSIGNEE.to_string(),
+            assigned_by: OWNER.to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn assign(subject: &str) -> Command {
+        Assign {
+            subject: subject.to_string(),
+            input: inputs::Assignment {
+                assignee: ASSIGNEE.to_string(),
+            },
+        }
+    }
+
+    fn unassign(subject: &str) -> Command {
+        Unassign {
+            subject: subject.to_string(),
+            input: inputs::Assignment {
+                assignee: ASSIGNEE.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn owners_assign_and_unassign_tasks() {
+        let events = framework()
+            .given(vec![created(OWNER)])
+            .when(assign(OWNER))
+            .inspect_result()
+            .unwrap();
+
+        assert!(matches!(
+            &events[..],
+            [Assigned { assignee, assigned_by, .. }] if assignee == ASSIGNEE && assigned_by == OWNER
+        ));
+
+        let result = framework()
+            .given(vec![created(OWNER), assigned()])
+            .when(unassign(OWNER))
+            .inspect_result();
+
+        assert_eq!(event_types(result), ["Task:Unassigned"]);
+    }
+
+    #[test]
+    fn assignments_must_change_the_assignees() {
+        framework()
+            .given(vec![created(OWNER), assigned()])
+            .when(assign(OWNER))
+            .then_expect_error_message("Invalid state: The Task is already assigned to them");
+
+        framework()
+            .given(vec![created(OWNER)])
+            .when(unassign(OWNER))
+            .then_expect_error_message("Invalid state: The Task isn't assigned to them");
+    }
+
+    #[test]
+    fn only_owners_change_assignments() {
+        framework()
+            .given(vec![created(OWNER), assigned()])
+            .when(unassign(ASSIGNEE))
+            .then_expect_error_message("Forbidden");
+
+        framework()
+            .given(vec![created(OWNER)])
+            .when(assign("user-3"))
+            .then_expect_error_message("Forbidden");
+    }
+
+    #[test]
+    fn assignees_record_progress() {
+        let subject = ASSIGNEE.to_string();
+        let commands = [
+            (
+                Start {
+                    subject: subject.clone(),
+                },
+                "Task:Started",
+            ),
+            (
+                Block {
+                    subject: subject.clone(),
+                    input: inputs::Block::default(),
+                },
+                "Task:Blocked",
+            ),
+            (
+                Complete {
+                    subject: subject.clone(),
+                    tenant: TENANT.to_string(),
+                },
+                "Task:Completed",
+            ),
+            (
+                AddComment {
+                    subject: subject.clone(),
+                    comment_id: "comment-1".to_string(),
+                    input: inputs::Comment {
+                        body: "On it".to_string(),
+                    },
+                },
+                "Task:CommentAdded",
+            ),
+        ];
+
+        for (command, event_type) in commands {
+            let result = framework()
+                .given(vec![created(OWNER), assigned()])
+                .when(command)
+                .inspect_result();
+
+            assert_eq!(event_types(result), [event_type]);
+        }
+    }
+
+    #[test]
+    fn assignees_cant_change_the_task_itself() {
+        let subject = ASSIGNEE.to_string();
+        let commands = [
+            rename(ASSIGNEE),
+            Delete {
+                subject: subject.clone(),
+            },
+            Archive {
+                subject: subject.clone(),
+            },
+            assign(ASSIGNEE),
+        ];
+
+        for command in commands {
+            framework()
+                .given(vec![created(OWNER), assigned()])
+                .when(command)
+                .then_expect_error_message("Forbidden");
+        }
+
+        // Once they're unassigned, they can't record progress either
+        framework()
+            .given(vec![
+                created(OWNER),
+                assigned(),
+                Unassigned {
+                    id: TASK_ID.to_string(),
+                    assignee: ASSIGNEE.to_string(),
+                    updated_at: Utc::now(),
+                },
+            ])
+            .when(Start { subject })
+            .then_expect_error_message("Forbidden");
+    }
 }
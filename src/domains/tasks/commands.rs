@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-use super::inputs;
+use crate::utils::metrics::CommandType;
+
+use super::{inputs, Task};
 
 /// Task Aggregate Commands
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -10,13 +12,199 @@ pub enum Command {
         /// The Task ID to create (auto-generated in the http Create handler)
         id: String,
 
+        /// The authenticated subject creating the Task, who becomes its owner
+        owner: String,
+
         /// The Create input
         input: inputs::Create,
     },
 
+    /// Create the next occurrence of a recurring Task, once the previous one is completed
+    CreateOccurrence {
+        /// The occurrence this one follows
+        previous: Box<Task>,
+    },
+
     /// Update an existing Task
-    Update(inputs::Update),
+    Update {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The Update input
+        input: inputs::Update,
+    },
 
     /// Remove an existing Task
-    Delete,
+    Delete {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Restore a removed Task within the grace period
+    Restore {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Permanently erase a Task's personal data
+    Erase {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Start work on a Task
+    Start {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Mark a Task as waiting on something
+    Block {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The Block input
+        input: inputs::Block,
+    },
+
+    /// Mark a Task as completed, once every Task blocking it is done
+    Complete {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The tenant the Task and its blockers belong to
+        tenant: String,
+    },
+
+    /// Move a completed or archived Task back to Todo
+    Reopen {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Put a Task away
+    Archive {
+        /// The authenticated subject making the change
+        subject: String,
+    },
+
+    /// Move a Task into a Project, or out of its current one
+    Move {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The tenant the Task and Project belong to
+        tenant: String,
+
+        /// The Move input
+        input: inputs::Move,
+    },
+
+    /// Link a Task to its parent, or to a Task that blocks it
+    Link {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The tenant both Tasks belong to
+        tenant: String,
+
+        /// The Link input
+        input: inputs::Link,
+    },
+
+    /// Remove a link to a parent or blocking Task
+    Unlink {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The Link input
+        input: inputs::Link,
+    },
+
+    /// Give a subject responsibility for a Task
+    Assign {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The Assignment input
+        input: inputs::Assignment,
+    },
+
+    /// Take responsibility for a Task away from a subject
+    Unassign {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The Assignment input
+        input: inputs::Assignment,
+    },
+
+    /// Comment on a Task
+    AddComment {
+        /// The authenticated subject making the change, who becomes the comment's author
+        subject: String,
+
+        /// The comment ID to create (auto-generated in the http handler)
+        comment_id: String,
+
+        /// The Comment input
+        input: inputs::Comment,
+    },
+
+    /// Change the text of a comment
+    EditComment {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The comment to edit
+        comment_id: String,
+
+        /// The Comment input
+        input: inputs::Comment,
+    },
+
+    /// Remove a comment
+    DeleteComment {
+        /// The authenticated subject making the change
+        subject: String,
+
+        /// The comment to remove
+        comment_id: String,
+    },
+
+    /// Send the reminder for a Task once its `remind_at` time has passed (issued by the
+    /// scheduler)
+    Remind,
+
+    /// Flag a Task as overdue once its `due_at` time has passed without it being completed
+    /// (issued by the scheduler)
+    MarkOverdue,
+}
+
+impl CommandType for Command {
+    fn command_type(&self) -> &'static str {
+        match self {
+            Command::Create { .. } => "Create",
+            Command::CreateOccurrence { .. } => "CreateOccurrence",
+            Command::Update { .. } => "Update",
+            Command::Delete { .. } => "Delete",
+            Command::Restore { .. } => "Restore",
+            Command::Erase { .. } => "Erase",
+            Command::Start { .. } => "Start",
+            Command::Block { .. } => "Block",
+            Command::Complete { .. } => "Complete",
+            Command::Reopen { .. } => "Reopen",
+            Command::Archive { .. } => "Archive",
+            Command::Move { .. } => "Move",
+            Command::Link { .. } => "Link",
+            Command::Unlink { .. } => "Unlink",
+            Command::Assign { .. } => "Assign",
+            Command::Unassign { .. } => "Unassign",
+            Command::AddComment { .. } => "AddComment",
+            Command::EditComment { .. } => "EditComment",
+            Command::DeleteComment { .. } => "DeleteComment",
+            Command::Remind => "Remind",
+            Command::MarkOverdue => "MarkOverdue",
+        }
+    }
 }
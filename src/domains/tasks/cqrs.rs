@@ -1,43 +1,149 @@
-use std::{env, sync::Arc};
+use std::sync::Arc;
 
+use async_trait::async_trait;
+use chrono::Duration;
 use cqrs_es::{
     persist::{PersistedEventStore, ViewRepository},
     CqrsFramework,
 };
 use dynamo_es::{DynamoEventRepository, DynamoViewRepository};
 
-use super::{Query, Services, Task, View};
+use crate::{
+    config::Config,
+    domains::{
+        self,
+        projects::{self, Project},
+        shredding::{Shredder, ShreddingRepository},
+    },
+    utils::{metrics::MeasuredEventStore, telemetry::TracedViewRepository},
+};
+
+use super::{
+    activity::{ActivityQuery, Feed},
+    index::{IndexQuery, TaskIndex},
+    relations::ChildrenQuery,
+    schedule::{DynamoSchedule, ScheduleQuery},
+    Projects, Query, Services, Task, Tasks, View,
+};
+
+/// The Tasks Event Store, which seals sensitive fields before they reach DynamoDB
+pub type EventStore =
+    MeasuredEventStore<PersistedEventStore<ShreddingRepository<DynamoEventRepository>, Task>>;
 
 /// Initialize the Tasks CqrsFramework
 pub fn init(
     client: aws_sdk_dynamodb::Client,
+    config: &Config,
     repo: Arc<Box<dyn ViewRepository<View, Task>>>,
-) -> Arc<CqrsFramework<Task, PersistedEventStore<DynamoEventRepository, Task>>> {
-    let event_log_table =
-        env::var("EVENT_LOG_TABLE_NAME").unwrap_or("event-driven-dev-event-log".to_string());
+) -> Arc<CqrsFramework<Task, EventStore>> {
+    let services = init_services(client.clone(), config, repo.clone());
 
-    let event_snapshots_table = env::var("EVENT_SNAPSHOTS_TABLE_NAME")
-        .unwrap_or("event-driven-dev-event-snapshots".to_string());
+    init_with_queries(client, config, repo, services, vec![])
+}
 
-    let store: PersistedEventStore<DynamoEventRepository, Task> =
-        PersistedEventStore::new_snapshot_store(
+/// Initialize the Tasks CqrsFramework with the given services and extra queries, which run after
+/// the read models have been updated
+pub fn init_with_queries(
+    client: aws_sdk_dynamodb::Client,
+    config: &Config,
+    repo: Arc<Box<dyn ViewRepository<View, Task>>>,
+    services: Services,
+    queries: Vec<Box<dyn cqrs_es::Query<Task>>>,
+) -> Arc<CqrsFramework<Task, EventStore>> {
+    let store = PersistedEventStore::new_snapshot_store(
+        ShreddingRepository::new(
             DynamoEventRepository::new(client.clone())
-                .with_tables(&event_log_table, &event_snapshots_table),
-            5,
-        );
+                .with_tables(&config.tables.event_log, &config.tables.event_snapshots),
+            Shredder::init(client.clone(), config),
+        ),
+        config.snapshot_size,
+    )
+    .with_upcasters(vec![Box::new(domains::upcasters())]);
+    let store: EventStore = MeasuredEventStore::new(store);
+
+    // The schedule reads Task state from the view, so it must be updated after the view
+    let query = Box::new(Query::new(repo.clone()));
+    let schedule_query = Box::new(ScheduleQuery::new(
+        repo.clone(),
+        DynamoSchedule::init(client.clone(), config),
+    ));
+    let children_query = Box::new(ChildrenQuery::new(repo.clone()));
+    let index_query = Box::new(IndexQuery::new(
+        repo.clone(),
+        TaskIndex::init(client.clone(), config),
+    ));
+    let activity_query = Box::new(ActivityQuery::new(Feed::init(client.clone(), config)));
 
-    let query = Box::new(Query::new(repo));
+    let mut all_queries: Vec<Box<dyn cqrs_es::Query<Task>>> = vec![
+        query,
+        schedule_query,
+        children_query,
+        index_query,
+        activity_query,
+    ];
+    all_queries.extend(queries);
 
-    Arc::new(CqrsFramework::new(store, vec![query], Services::default()))
+    Arc::new(CqrsFramework::new(store, all_queries, services))
 }
 
-/// Initialize the Tasks View Repository
-pub fn init_repo(client: aws_sdk_dynamodb::Client) -> Arc<Box<dyn ViewRepository<View, Task>>> {
-    let tasks_view_table =
-        env::var("TASKS_VIEW_TABLE_NAME").unwrap_or("event-driven-dev-tasks-view".to_string());
+/// Initialize the services the Task Aggregate uses, which the API shares to authorize reads the
+/// same way as changes
+pub fn init_services(
+    client: aws_sdk_dynamodb::Client,
+    config: &Config,
+    repo: Arc<Box<dyn ViewRepository<View, Task>>>,
+) -> Services {
+    let projects = ProjectViews(projects::cqrs::init_repo(client, config));
+
+    let mut services = Services::new(Arc::new(projects), Arc::new(TaskViews(repo)));
+    if let Some(days) = config.task_restore_grace_period_days {
+        services.restore_grace_period = Duration::days(days);
+    }
 
-    Arc::new(Box::new(DynamoViewRepository::new(
-        &tasks_view_table,
-        client,
+    services
+}
+
+/// Initialize the Tasks View Repository
+pub fn init_repo(
+    client: aws_sdk_dynamodb::Client,
+    config: &Config,
+) -> Arc<Box<dyn ViewRepository<View, Task>>> {
+    Arc::new(Box::new(TracedViewRepository::new(
+        "tasks",
+        DynamoViewRepository::new(&config.tables.tasks_view, client),
     )))
 }
+
+/// Looks up Projects from their default View, so moves are checked against the latest Project
+/// state that has been projected
+struct ProjectViews(Arc<Box<dyn ViewRepository<projects::View, Project>>>);
+
+#[async_trait]
+impl Projects for ProjectViews {
+    async fn find(&self, aggregate_id: &str) -> Result<Option<Project>, domains::Error> {
+        let view = self
+            .0
+            .load(aggregate_id)
+            .await
+            .map_err(|err| domains::Error::Unavailable(err.into()))?;
+
+        Ok(view.map(|view| view.project))
+    }
+}
+
+/// Looks up other Tasks from their default View, so links are checked against the latest Task
+/// state that has been projected
+struct TaskViews(Arc<Box<dyn ViewRepository<View, Task>>>);
+
+#[async_trait]
+impl Tasks for TaskViews {
+    async fn find(&self, aggregate_id: &str) -> Result<Option<Task>, domains::Error> {
+        let view = self
+            .0
+            .load(aggregate_id)
+            .await
+            .map_err(|err| domains::Error::Unavailable(err.into()))?;
+
+        Ok(view.map(|view| view.task))
+    }
+}
@@ -1,13 +1,27 @@
 use chrono::{DateTime, Utc};
 use cqrs_es::DomainEvent;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use super::{inputs, Task};
+use crate::domains::{shredding::SensitiveFields, upcasters::Upcasters};
 
-use Event::{Created, Deleted, Updated};
+use super::{inputs, Priority, Relation, Status, Task, AGGREGATE_TYPE};
+
+use Event::{
+    Archived, Assigned, BecameOverdue, Blocked, CommentAdded, CommentDeleted, CommentEdited,
+    Completed, Created, Deleted, Erased, LabelAdded, LabelRemoved, Linked, Moved, PriorityChanged,
+    ReminderDue, Reopened, Restored, Started, Unassigned, Unlinked, Updated,
+};
+
+/// The current version of the Task event schema
+pub const EVENT_VERSION: &str = "2.6";
+
+/// The versions that recorded completion with a `done` flag, before the lifecycle was introduced
+const DONE_FLAG_VERSIONS: [&str; 6] = ["1.0", "1.1", "1.2", "1.3", "1.4", "1.5"];
 
 /// Task events
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
 #[serde(tag = "type")]
 pub enum Event {
     /// A Task was successfully created
@@ -19,7 +33,7 @@ pub enum Event {
         created_at: DateTime<Utc>,
 
         /// The created Task
-        task: Task,
+        task: Box<Task>,
     },
 
     /// A Task was successfully updated
@@ -32,6 +46,11 @@ pub enum Event {
 
         /// The update to the Task
         update: inputs::Update,
+
+        /// A status change carried over from a 1.x update that also changed other fields. New
+        /// updates never set it, since status changes have their own events.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<Status>,
     },
 
     /// A Task was successfully deleted
@@ -42,6 +61,258 @@ pub enum Event {
         /// When the change occurred
         updated_at: DateTime<Utc>,
     },
+
+    /// A deleted Task was successfully restored
+    Restored {
+        /// The ID of the Task that was restored
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task's personal data was permanently erased
+    Erased {
+        /// The ID of the Task that was erased
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// Work on a Task was started
+    Started {
+        /// The ID of the Task that was started
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task was marked as waiting on something
+    Blocked {
+        /// The ID of the Task that was blocked
+        id: String,
+
+        /// What the Task is waiting on
+        reason: Option<String>,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task was completed
+    Completed {
+        /// The ID of the Task that was completed
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A completed or archived Task was moved back to Todo
+    Reopened {
+        /// The ID of the Task that was reopened
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task was put away
+    Archived {
+        /// The ID of the Task that was archived
+        id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task was moved into a Project, or out of one
+    Moved {
+        /// The ID of the Task that was moved
+        id: String,
+
+        /// The Project the Task was in before, if any
+        from_project_id: Option<String>,
+
+        /// The Project the Task is in now, if any
+        project_id: Option<String>,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task was linked to its parent, or to a Task that blocks it
+    Linked {
+        /// The ID of the Task that was linked
+        id: String,
+
+        /// How the Task relates to the other one
+        relation: Relation,
+
+        /// The ID of the other Task
+        task_id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A link to a parent or blocking Task was removed
+    Unlinked {
+        /// The ID of the Task that was unlinked
+        id: String,
+
+        /// How the Task related to the other one
+        relation: Relation,
+
+        /// The ID of the other Task
+        task_id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A label was added to a Task
+    LabelAdded {
+        /// The ID of the Task that was labelled
+        id: String,
+
+        /// The normalized label
+        label: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A label was removed from a Task
+    LabelRemoved {
+        /// The ID of the Task the label was removed from
+        id: String,
+
+        /// The normalized label
+        label: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task's priority was changed
+    PriorityChanged {
+        /// The ID of the Task that changed
+        id: String,
+
+        /// The new priority
+        priority: Priority,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A subject was given responsibility for a Task
+    Assigned {
+        /// The ID of the Task that was assigned
+        id: String,
+
+        /// The subject the Task was assigned to
+        assignee: String,
+
+        /// The subject that made the assignment
+        assigned_by: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A subject was no longer responsible for a Task
+    Unassigned {
+        /// The ID of the Task that was unassigned
+        id: String,
+
+        /// The subject the Task was unassigned from
+        assignee: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A comment was added to a Task
+    CommentAdded {
+        /// The ID of the Task that was commented on
+        id: String,
+
+        /// The ID of the new comment
+        comment_id: String,
+
+        /// The subject that wrote the comment
+        author: String,
+
+        /// The comment text
+        body: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A comment on a Task was edited by its author
+    CommentEdited {
+        /// The ID of the Task the comment is on
+        id: String,
+
+        /// The ID of the comment that was edited
+        comment_id: String,
+
+        /// The new comment text
+        body: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A comment on a Task was removed by its author
+    CommentDeleted {
+        /// The ID of the Task the comment was on
+        id: String,
+
+        /// The ID of the comment that was removed
+        comment_id: String,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task's reminder time has arrived, and the owner should be notified
+    ReminderDue {
+        /// The ID of the Task to remind about
+        id: String,
+
+        /// The owner to remind
+        owner: String,
+
+        /// The reminder time that was reached
+        remind_at: DateTime<Utc>,
+
+        /// When the Task is due, if it has a deadline
+        due_at: Option<DateTime<Utc>>,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
+
+    /// A Task passed its due date without being completed
+    BecameOverdue {
+        /// The ID of the Task that is overdue
+        id: String,
+
+        /// The owner of the overdue Task
+        owner: String,
+
+        /// The due date that was missed
+        due_at: DateTime<Utc>,
+
+        /// When the change occurred
+        updated_at: DateTime<Utc>,
+    },
 }
 
 impl Event {
@@ -49,7 +320,58 @@ impl Event {
     #[allow(dead_code)]
     pub fn id(&self) -> String {
         match self {
-            Created { id, .. } | Updated { id, .. } | Deleted { id, .. } => id.to_string(),
+            Created { id, .. }
+            | Updated { id, .. }
+            | Deleted { id, .. }
+            | Restored { id, .. }
+            | Erased { id, .. }
+            | Started { id, .. }
+            | Blocked { id, .. }
+            | Completed { id, .. }
+            | Reopened { id, .. }
+            | Archived { id, .. }
+            | Moved { id, .. }
+            | Linked { id, .. }
+            | Unlinked { id, .. }
+            | Assigned { id, .. }
+            | Unassigned { id, .. }
+            | LabelAdded { id, .. }
+            | LabelRemoved { id, .. }
+            | PriorityChanged { id, .. }
+            | CommentAdded { id, .. }
+            | CommentEdited { id, .. }
+            | CommentDeleted { id, .. }
+            | ReminderDue { id, .. }
+            | BecameOverdue { id, .. } => id.to_string(),
+        }
+    }
+
+    /// Return when the event occurred
+    pub fn occurred_at(&self) -> DateTime<Utc> {
+        match self {
+            Created { created_at, .. } => *created_at,
+            Updated { updated_at, .. }
+            | Deleted { updated_at, .. }
+            | Restored { updated_at, .. }
+            | Erased { updated_at, .. }
+            | Started { updated_at, .. }
+            | Blocked { updated_at, .. }
+            | Completed { updated_at, .. }
+            | Reopened { updated_at, .. }
+            | Archived { updated_at, .. }
+            | Moved { updated_at, .. }
+            | Linked { updated_at, .. }
+            | Unlinked { updated_at, .. }
+            | Assigned { updated_at, .. }
+            | Unassigned { updated_at, .. }
+            | LabelAdded { updated_at, .. }
+            | LabelRemoved { updated_at, .. }
+            | PriorityChanged { updated_at, .. }
+            | CommentAdded { updated_at, .. }
+            | CommentEdited { updated_at, .. }
+            | CommentDeleted { updated_at, .. }
+            | ReminderDue { updated_at, .. }
+            | BecameOverdue { updated_at, .. } => *updated_at,
         }
     }
 }
@@ -60,11 +382,274 @@ impl DomainEvent for Event {
             Created { .. } => "Task:Created".to_string(),
             Updated { .. } => "Task:Updated".to_string(),
             Deleted { .. } => "Task:Deleted".to_string(),
+            Restored { .. } => "Task:Restored".to_string(),
+            Erased { .. } => "Task:Erased".to_string(),
+            Started { .. } => "Task:Started".to_string(),
+            Blocked { .. } => "Task:Blocked".to_string(),
+            Completed { .. } => "Task:Completed".to_string(),
+            Reopened { .. } => "Task:Reopened".to_string(),
+            Archived { .. } => "Task:Archived".to_string(),
+            Moved { .. } => "Task:Moved".to_string(),
+            Linked { .. } => "Task:Linked".to_string(),
+            Unlinked { .. } => "Task:Unlinked".to_string(),
+            Assigned { .. } => "Task:Assigned".to_string(),
+            Unassigned { .. } => "Task:Unassigned".to_string(),
+            LabelAdded { .. } => "Task:LabelAdded".to_string(),
+            LabelRemoved { .. } => "Task:LabelRemoved".to_string(),
+            PriorityChanged { .. } => "Task:PriorityChanged".to_string(),
+            CommentAdded { .. } => "Task:CommentAdded".to_string(),
+            CommentEdited { .. } => "Task:CommentEdited".to_string(),
+            CommentDeleted { .. } => "Task:CommentDeleted".to_string(),
+            ReminderDue { .. } => "Task:ReminderDue".to_string(),
+            BecameOverdue { .. } => "Task:BecameOverdue".to_string(),
         }
     }
 
     #[allow(clippy::unused_self)]
     fn event_version(&self) -> String {
-        "1.0".to_string()
+        EVENT_VERSION.to_string()
+    }
+}
+
+/// Register the upcasters that migrate stored Task events to the current `EVENT_VERSION`
+///
+/// Add an entry here whenever a Task event changes shape, keyed by the version being migrated
+/// from.
+///
+/// Version history:
+///   - 1.1: `utils::Update` fields in `Task:Updated` are omitted when unchanged. Version 1.0
+///     payloads always include the `summary`, as null when it was unchanged, which is dropped
+///     when they're upcast to 2.0.
+///   - 1.2: `Task` gains the `owner` that is allowed to change it. Earlier events are read as-is,
+///     with no owner.
+///   - 1.3: Deleted Tasks can be restored with `Task:Restored`, and `Task` gains `deleted_at`.
+///     Earlier events are read as-is.
+///   - 1.4: The `name` and `summary` fields are sealed with the Task's encryption key, and
+///     `Task:Erased` destroys that key. Earlier payloads are plaintext, which is still read
+///     as-is, so no upcast is needed.
+///   - 1.5: Tasks gain `due_at` and `remind_at`, with `Task:ReminderDue` and
+///     `Task:BecameOverdue` recorded when they pass. Earlier events are read as-is, as Tasks
+///     without a deadline.
+///   - 2.0: The `done` flag is replaced by the `status` lifecycle. `Task:Created` payloads gain a
+///     `status` in place of `done`. `Task:Updated` payloads that only changed `done` become
+///     `Task:Completed` or `Task:Reopened`, and ones that changed other fields too keep the
+///     status change in the event's `status` field.
+///   - 2.1: Tasks can be moved between Projects with `Task:Moved`, and `Task` gains an optional
+///     `project_id`. Earlier events are read as-is.
+///   - 2.2: Tasks can be linked to a parent or to blocking Tasks with `Task:Linked` and
+///     `Task:Unlinked`, and `Task` gains `parent_id` and `blocked_by`. Earlier events are read
+///     as-is.
+///   - 2.3: Tasks can be commented on with `Task:CommentAdded`, `Task:CommentEdited`, and
+///     `Task:CommentDeleted`, and `Task` gains `comment_authors`. Earlier events are read as-is.
+///   - 2.4: Tasks gain `labels` and a `priority`, which are changed with `Task:LabelAdded`,
+///     `Task:LabelRemoved`, and `Task:PriorityChanged` rather than `Task:Updated`. Earlier events
+///     are read as-is, with no labels and `Normal` priority.
+///   - 2.5: Tasks can be assigned to subjects with `Task:Assigned` and `Task:Unassigned`, and
+///     `Task` gains `assignees`. Earlier events are read as-is.
+///   - 2.6: `Task` gains a `recurrence` rule and the `series` it belongs to, and updates can
+///     change the `recurrence`. Earlier events are read as-is, as Tasks that don't repeat.
+pub fn register_upcasters(upcasters: Upcasters) -> Upcasters {
+    DONE_FLAG_VERSIONS
+        .iter()
+        .fold(upcasters, |upcasters, version| {
+            upcasters
+                .register("Task:Created", version, "2.0", upcast_created_done)
+                .register_retyped("Task:Updated", version, "2.0", |payload| {
+                    upcast_updated_done(version, payload)
+                })
+        })
+}
+
+/// Replace the created Task's `done` flag with a `status`
+fn upcast_created_done(mut payload: Value) -> Value {
+    if let Some(task) = payload.get_mut("task").and_then(Value::as_object_mut) {
+        let done = task.remove("done").and_then(|done| done.as_bool());
+
+        task.insert("status".to_string(), json!(status_for(done)));
+    }
+
+    payload
+}
+
+/// Turn a change to the `done` flag into the lifecycle event it stands for
+///
+/// Version 1.0 always recorded the `summary`, as null when it was unchanged, so a null summary
+/// from then is dropped rather than read as clearing it.
+fn upcast_updated_done(version: &str, mut payload: Value) -> (String, Value) {
+    let done = payload
+        .get_mut("update")
+        .and_then(Value::as_object_mut)
+        .and_then(|update| {
+            if version == "1.0" && update.get("summary").is_some_and(Value::is_null) {
+                update.remove("summary");
+            }
+
+            update.remove("done")
+        })
+        .and_then(|done| done.as_bool());
+
+    let Some(done) = done else {
+        return ("Task:Updated".to_string(), payload);
+    };
+
+    // Any other field that is present is a change, except a `name` of null
+    let only_done = payload
+        .get("update")
+        .and_then(Value::as_object)
+        .is_none_or(|update| {
+            update
+                .iter()
+                .all(|(field, value)| field == "name" && value.is_null())
+        });
+
+    if !only_done {
+        payload["status"] = json!(status_for(Some(done)));
+
+        return ("Task:Updated".to_string(), payload);
+    }
+
+    let kind = if done { "Completed" } else { "Reopened" };
+
+    (
+        format!("{AGGREGATE_TYPE}:{kind}"),
+        json!({
+            "type": kind,
+            "id": payload["id"],
+            "updated_at": payload["updated_at"],
+        }),
+    )
+}
+
+fn status_for(done: Option<bool>) -> Status {
+    if done == Some(true) {
+        Status::Done
+    } else {
+        Status::Todo
+    }
+}
+
+/// Register the Task fields that hold personal data, so they can be crypto-shredded
+///
+/// Owners, assignees and comment authors are left in the clear. They're the opaque subject IDs
+/// issued by the identity provider rather than anything that identifies a person on its own, and
+/// Tasks are authorized, listed and delivered to inboxes by them, so they have to stay readable.
+pub fn register_sensitive_fields(fields: SensitiveFields) -> SensitiveFields {
+    fields
+        .event(
+            "Task:Created",
+            &["/task/name", "/task/summary", "/task/labels"],
+        )
+        .event("Task:Updated", &["/update/name", "/update/summary"])
+        .event("Task:Blocked", &["/reason"])
+        .event("Task:CommentAdded", &["/body"])
+        .event("Task:CommentEdited", &["/body"])
+        .event("Task:LabelAdded", &["/label"])
+        .event("Task:LabelRemoved", &["/label"])
+        .snapshot(AGGREGATE_TYPE, &["/name", "/summary", "/labels"])
+        .erasure("Task:Erased")
+}
+
+#[cfg(test)]
+mod tests {
+    use cqrs_es::persist::{EventUpcaster, SerializedEvent};
+
+    use crate::domains::upcasters;
+
+    use super::*;
+
+    /// Replay a stored `Task:Updated` payload through the domain's upcasters
+    fn replay(event_version: &str, update: Value) -> (String, Event) {
+        let stored = SerializedEvent::new(
+            "acme#1".to_string(),
+            2,
+            AGGREGATE_TYPE.to_string(),
+            "Task:Updated".to_string(),
+            event_version.to_string(),
+            json!({
+                "type": "Updated",
+                "id": "acme#1",
+                "updated_at": "2024-01-02T03:04:05Z",
+                "update": update,
+            }),
+            json!({}),
+        );
+
+        let event = upcasters().upcast(stored);
+        let decoded = serde_json::from_value(event.payload).unwrap();
+
+        (event.event_type, decoded)
+    }
+
+    #[test]
+    fn done_only_1_0_updates_become_lifecycle_events() {
+        let (event_type, event) = replay(
+            "1.0",
+            json!({ "name": null, "summary": null, "done": true }),
+        );
+        assert_eq!(event_type, "Task:Completed");
+        assert!(matches!(event, Completed { .. }));
+
+        let (event_type, event) = replay(
+            "1.0",
+            json!({ "name": null, "summary": null, "done": false }),
+        );
+        assert_eq!(event_type, "Task:Reopened");
+        assert!(matches!(event, Reopened { .. }));
+    }
+
+    #[test]
+    fn null_1_0_summaries_are_unchanged() {
+        let (event_type, event) = replay(
+            "1.0",
+            json!({ "name": "Renamed", "summary": null, "done": true }),
+        );
+        assert_eq!(event_type, "Task:Updated");
+
+        let Updated { update, status, .. } = event else {
+            panic!("Expected an update, got {event:?}");
+        };
+        assert_eq!(update.name.as_deref(), Some("Renamed"));
+        assert!(update.summary.is_unchanged());
+        assert_eq!(status, Some(Status::Done));
+
+        let (_, event) = replay(
+            "1.0",
+            json!({ "name": "Renamed", "summary": null, "done": null }),
+        );
+
+        let Updated { update, status, .. } = event else {
+            panic!("Expected an update, got {event:?}");
+        };
+        assert!(update.summary.is_unchanged());
+        assert_eq!(status, None);
+    }
+
+    #[test]
+    fn done_only_1_x_updates_become_lifecycle_events() {
+        for version in ["1.1", "1.2", "1.3", "1.4", "1.5"] {
+            let (event_type, _) = replay(version, json!({ "name": null, "done": true }));
+            assert_eq!(event_type, "Task:Completed", "{version}");
+
+            let (event_type, _) = replay(version, json!({ "name": null, "done": false }));
+            assert_eq!(event_type, "Task:Reopened", "{version}");
+        }
+    }
+
+    #[test]
+    fn null_1_x_summaries_are_cleared() {
+        // Unchanged summaries were omitted from 1.1, so a null one clears it
+        for version in ["1.1", "1.2", "1.3", "1.4", "1.5"] {
+            let (event_type, event) = replay(
+                version,
+                json!({ "name": null, "summary": null, "done": true }),
+            );
+            assert_eq!(event_type, "Task:Updated", "{version}");
+
+            let Updated { update, status, .. } = event else {
+                panic!("Expected an update, got {event:?}");
+            };
+            assert!(update.summary.is_empty(), "{version}");
+            assert_eq!(status, Some(Status::Done), "{version}");
+        }
     }
 }
@@ -0,0 +1,262 @@
+use std::{collections::HashMap, sync::Arc};
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{DateTime, Utc};
+use cqrs_es::persist::PersistenceError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, domains::tenants};
+
+/// The most entries returned in a single page of an inbox
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+/// The index used to find every inbox entry for a Task
+const TASK_ID_INDEX: &str = "TaskIdIndex";
+
+/// A Task assigned to a subject
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct InboxEntry {
+    /// The ID of the assigned Task
+    pub task_id: String,
+
+    /// The subject that made the assignment
+    pub assigned_by: String,
+
+    /// When the Task was assigned
+    pub assigned_at: DateTime<Utc>,
+}
+
+/// A page of the Tasks assigned to a subject
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct InboxPage {
+    /// The entries on this page
+    pub tasks: Vec<InboxEntry>,
+
+    /// Pass this as `after` to fetch the next page, if there is one
+    pub next: Option<String>,
+}
+
+/// The "my tasks" read model, in a DynamoDB table keyed by `UserId` and `AggregateId`. Both keys
+/// are tenant-scoped, so a subject's inbox in one tenant never lists Tasks from another.
+pub struct Inbox {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl Inbox {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Initialize the Task Inbox from the config
+    pub fn init(client: aws_sdk_dynamodb::Client, config: &Config) -> Arc<Self> {
+        Arc::new(Self::new(client, &config.tables.tasks_inbox))
+    }
+
+    /// Read up to `limit` Tasks assigned to a subject, starting just after the `after` Task ID.
+    /// Deleted Tasks are left out until they're restored.
+    pub async fn page(
+        &self,
+        tenant: &str,
+        user_id: &str,
+        after: Option<String>,
+        limit: i32,
+    ) -> Result<InboxPage, PersistenceError> {
+        let user_key = tenants::scoped_id(tenant, user_id);
+
+        let mut request = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("UserId = :user_id")
+            .expression_attribute_values(":user_id", AttributeValue::S(user_key.clone()))
+            .filter_expression("attribute_not_exists(Hidden)")
+            .limit(limit.clamp(1, MAX_PAGE_SIZE));
+
+        if let Some(after) = after {
+            request = request
+                .exclusive_start_key("UserId", AttributeValue::S(user_key))
+                .exclusive_start_key(
+                    "AggregateId",
+                    AttributeValue::S(tenants::scoped_id(tenant, &after)),
+                );
+        }
+
+        let output = request
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        let tasks = output
+            .items()
+            .iter()
+            .map(decode)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Filtering can leave a page empty while there are more to read, so continue from the
+        // last key DynamoDB evaluated rather than the last entry returned
+        let next = output
+            .last_evaluated_key()
+            .and_then(|key| key.get("AggregateId"))
+            .and_then(|id| id.as_s().ok())
+            .and_then(|id| tenants::split_id(id))
+            .map(|(_, task_id)| task_id.to_string());
+
+        Ok(InboxPage { tasks, next })
+    }
+
+    /// Add a Task to a subject's inbox
+    pub async fn assign(
+        &self,
+        tenant: &str,
+        aggregate_id: &str,
+        assignee: &str,
+        entry: &InboxEntry,
+    ) -> Result<(), PersistenceError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item(
+                "UserId",
+                AttributeValue::S(tenants::scoped_id(tenant, assignee)),
+            )
+            .item("AggregateId", AttributeValue::S(aggregate_id.to_string()))
+            .item("TaskId", AttributeValue::S(entry.task_id.clone()))
+            .item("AssignedBy", AttributeValue::S(entry.assigned_by.clone()))
+            .item(
+                "AssignedAt",
+                AttributeValue::S(entry.assigned_at.to_rfc3339()),
+            )
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        Ok(())
+    }
+
+    /// Remove a Task from a subject's inbox
+    pub async fn unassign(
+        &self,
+        tenant: &str,
+        aggregate_id: &str,
+        assignee: &str,
+    ) -> Result<(), PersistenceError> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key(
+                "UserId",
+                AttributeValue::S(tenants::scoped_id(tenant, assignee)),
+            )
+            .key("AggregateId", AttributeValue::S(aggregate_id.to_string()))
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        Ok(())
+    }
+
+    /// Hide a Task from every inbox it's in while it's deleted, or show it again once restored
+    pub async fn set_hidden(
+        &self,
+        aggregate_id: &str,
+        hidden: bool,
+    ) -> Result<(), PersistenceError> {
+        for key in self.entries(aggregate_id).await? {
+            let request = self
+                .client
+                .update_item()
+                .table_name(&self.table_name)
+                .set_key(Some(key));
+
+            let request = if hidden {
+                request
+                    .update_expression("SET Hidden = :hidden")
+                    .expression_attribute_values(":hidden", AttributeValue::Bool(true))
+            } else {
+                request.update_expression("REMOVE Hidden")
+            };
+
+            request
+                .send()
+                .await
+                .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove a Task from every inbox it's in
+    pub async fn remove_task(&self, aggregate_id: &str) -> Result<(), PersistenceError> {
+        for key in self.entries(aggregate_id).await? {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .set_key(Some(key))
+                .send()
+                .await
+                .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Find the keys of every inbox entry for a Task
+    async fn entries(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>, PersistenceError> {
+        let mut keys = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(TASK_ID_INDEX)
+                .key_condition_expression("AggregateId = :id")
+                .expression_attribute_values(":id", AttributeValue::S(aggregate_id.to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+            keys.extend(output.items().iter().filter_map(|item| {
+                Some(HashMap::from([
+                    ("UserId".to_string(), item.get("UserId")?.clone()),
+                    ("AggregateId".to_string(), item.get("AggregateId")?.clone()),
+                ]))
+            }));
+
+            start_key = output.last_evaluated_key().cloned();
+
+            if start_key.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+}
+
+fn decode(item: &HashMap<String, AttributeValue>) -> Result<InboxEntry, PersistenceError> {
+    let missing =
+        |field: &str| PersistenceError::DeserializationError(format!("Missing {field}").into());
+
+    let string = |field: &str| item.get(field).and_then(|v| v.as_s().ok()).cloned();
+
+    let assigned_at = string("AssignedAt")
+        .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| missing("AssignedAt"))?;
+
+    Ok(InboxEntry {
+        task_id: string("TaskId").ok_or_else(|| missing("TaskId"))?,
+        assigned_by: string("AssignedBy").unwrap_or_default(),
+        assigned_at,
+    })
+}
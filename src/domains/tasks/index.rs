@@ -0,0 +1,507 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use cqrs_es::{
+    persist::{PersistenceError, ViewRepository},
+    EventEnvelope,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, domains::tenants};
+
+use super::{Priority, Task, View};
+
+/// The sort key of the item that records which facets a Task is currently listed under
+const MANIFEST: &str = "-";
+
+/// The most Tasks returned in a single page
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+/// Criteria for listing Tasks
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Filter {
+    /// Only include Tasks with every one of these labels
+    pub labels: BTreeSet<String>,
+
+    /// Only include Tasks with this priority
+    pub priority: Option<Priority>,
+
+    /// Only include Tasks in this Project
+    pub project_id: Option<String>,
+}
+
+/// A page of Tasks matching a `Filter`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct TaskPage {
+    /// The Tasks on this page, oldest first
+    pub tasks: Vec<View>,
+
+    /// Pass this as `after` to fetch the next page, if there is one. Pages may hold fewer Tasks
+    /// than requested, even when there are more to come.
+    pub next: Option<String>,
+}
+
+/// A secondary read model that lists each owner's Tasks under every label and priority they have,
+/// and the Project they're in
+///
+/// Items live in a DynamoDB table keyed by `Facet` and `TaskId`, where the facet combines the
+/// tenant, the owner, and one label, priority, Project, or "all". Each item carries a copy of the Task's
+/// View, so a filter is answered with a single query against the most selective facet. Tasks in a
+/// Project are also listed under a facet for the whole tenant, so every Task in it can be found
+/// whoever owns it. Deleted Tasks aren't listed.
+pub struct TaskIndex {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl TaskIndex {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Initialize the Task Index from the config
+    pub fn init(client: aws_sdk_dynamodb::Client, config: &Config) -> Arc<Self> {
+        Arc::new(Self::new(client, &config.tables.tasks_index))
+    }
+
+    /// List an owner's Tasks that match the filter, starting after the `after` cursor
+    pub async fn search(
+        &self,
+        tenant: &str,
+        owner: &str,
+        filter: &Filter,
+        after: Option<String>,
+        limit: i32,
+    ) -> Result<TaskPage, PersistenceError> {
+        let search = Search::new(tenant, owner, filter);
+
+        let mut request = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("Facet = :facet")
+            .expression_attribute_values(":facet", AttributeValue::S(search.facet.clone()))
+            .expression_attribute_names("#owner", "Owner")
+            .filter_expression(search.conditions.join(" AND "))
+            .limit(limit.clamp(1, MAX_PAGE_SIZE));
+
+        for (name, value) in search.values {
+            request = request.expression_attribute_values(name, AttributeValue::S(value));
+        }
+
+        if let Some(after) = after {
+            request = request
+                .exclusive_start_key("Facet", AttributeValue::S(search.facet))
+                .exclusive_start_key("TaskId", AttributeValue::S(after));
+        }
+
+        let output = request
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        page(output.items(), output.last_evaluated_key())
+    }
+
+    /// List every Task in a Project, whoever owns it, starting after the `after` cursor
+    pub async fn in_project(
+        &self,
+        tenant: &str,
+        project_id: &str,
+        after: Option<String>,
+        limit: i32,
+    ) -> Result<TaskPage, PersistenceError> {
+        let facet = project_key(tenant, project_id);
+
+        let mut request = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("Facet = :facet")
+            .expression_attribute_values(":facet", AttributeValue::S(facet.clone()))
+            .limit(limit.clamp(1, MAX_PAGE_SIZE));
+
+        if let Some(after) = after {
+            request = request
+                .exclusive_start_key("Facet", AttributeValue::S(facet))
+                .exclusive_start_key("TaskId", AttributeValue::S(after));
+        }
+
+        let output = request
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        page(output.items(), output.last_evaluated_key())
+    }
+
+    /// Bring a Task's listings in line with its current View
+    pub async fn sync(&self, aggregate_id: &str, view: &View) -> Result<(), PersistenceError> {
+        let previous = self.manifest(aggregate_id).await?;
+        let current = facets(aggregate_id, view);
+
+        for facet in previous.difference(&current) {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key("Facet", AttributeValue::S(facet.clone()))
+                .key("TaskId", AttributeValue::S(aggregate_id.to_string()))
+                .send()
+                .await
+                .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+        }
+
+        let payload = serde_json::to_string(view)
+            .map_err(|err| PersistenceError::DeserializationError(Box::new(err)))?;
+
+        for facet in &current {
+            let mut request = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .item("Facet", AttributeValue::S(facet.clone()))
+                .item("TaskId", AttributeValue::S(aggregate_id.to_string()))
+                .item("Owner", AttributeValue::S(view.task.owner.clone()))
+                .item(
+                    "Priority",
+                    AttributeValue::S(view.task.priority.to_string()),
+                )
+                .item("View", AttributeValue::S(payload.clone()));
+
+            // String sets can't be empty
+            if !view.task.labels.is_empty() {
+                request = request.item(
+                    "Labels",
+                    AttributeValue::Ss(view.task.labels.iter().cloned().collect()),
+                );
+            }
+
+            request
+                .send()
+                .await
+                .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+        }
+
+        let manifest = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("Facet", AttributeValue::S(manifest_key(aggregate_id)))
+            .item("TaskId", AttributeValue::S(MANIFEST.to_string()));
+
+        let manifest = if current.is_empty() {
+            manifest
+        } else {
+            manifest.item("Facets", AttributeValue::Ss(current.into_iter().collect()))
+        };
+
+        manifest
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        Ok(())
+    }
+
+    /// The facets a Task was listed under when it was last synced
+    async fn manifest(&self, aggregate_id: &str) -> Result<BTreeSet<String>, PersistenceError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("Facet", AttributeValue::S(manifest_key(aggregate_id)))
+            .key("TaskId", AttributeValue::S(MANIFEST.to_string()))
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        Ok(output
+            .item()
+            .and_then(|item| item.get("Facets"))
+            .and_then(|v| v.as_ss().ok())
+            .map(|facets| facets.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+}
+
+/// How a `Filter` is answered: the facet that's queried, and the conditions the rest of the filter
+/// is checked with
+#[derive(Debug, PartialEq, Eq)]
+struct Search {
+    /// The facet key to query
+    facet: String,
+
+    /// Filter conditions, which must all hold
+    conditions: Vec<String>,
+
+    /// The values the conditions refer to, by placeholder
+    values: BTreeMap<String, String>,
+}
+
+impl Search {
+    /// Query the most selective facet, and filter on the rest. The labels that follow the one
+    /// queried, if any, are filtered on.
+    fn new(tenant: &str, owner: &str, filter: &Filter) -> Self {
+        let (facet, queried_labels, priority) =
+            match (&filter.project_id, filter.labels.first(), filter.priority) {
+                (Some(project_id), _, priority) => (format!("project:{project_id}"), 0, priority),
+                (None, Some(label), priority) => (format!("label:{label}"), 1, priority),
+                (None, None, Some(priority)) => (format!("priority:{priority}"), 0, None),
+                (None, None, None) => ("all".to_string(), 0, None),
+            };
+
+        let mut conditions = vec!["#owner = :owner".to_string()];
+        let mut values = BTreeMap::from([(":owner".to_string(), owner.to_string())]);
+
+        if let Some(priority) = priority {
+            conditions.push("Priority = :priority".to_string());
+            values.insert(":priority".to_string(), priority.to_string());
+        }
+
+        for (i, label) in filter.labels.iter().skip(queried_labels).enumerate() {
+            conditions.push(format!("contains(Labels, :label{i})"));
+            values.insert(format!(":label{i}"), label.clone());
+        }
+
+        Self {
+            facet: facet_key(tenant, owner, &facet),
+            conditions,
+            values,
+        }
+    }
+}
+
+/// Read a page of Tasks from the items a query returned
+fn page(
+    items: &[HashMap<String, AttributeValue>],
+    last_evaluated_key: Option<&HashMap<String, AttributeValue>>,
+) -> Result<TaskPage, PersistenceError> {
+    let tasks = items
+        .iter()
+        .map(|item| {
+            let view = item
+                .get("View")
+                .and_then(|v| v.as_s().ok())
+                .ok_or_else(|| PersistenceError::DeserializationError("Missing View".into()))?;
+
+            serde_json::from_str(view)
+                .map_err(|err| PersistenceError::DeserializationError(Box::new(err)))
+        })
+        .collect::<Result<Vec<View>, _>>()?;
+
+    let next = last_evaluated_key
+        .and_then(|key| key.get("TaskId"))
+        .and_then(|v| v.as_s().ok())
+        .cloned();
+
+    Ok(TaskPage { tasks, next })
+}
+
+/// The facet keys a Task should be listed under
+fn facets(aggregate_id: &str, view: &View) -> BTreeSet<String> {
+    let task = &view.task;
+
+    if task.deleted {
+        return BTreeSet::new();
+    }
+
+    let tenant = tenants::split_id(aggregate_id)
+        .map(|(tenant, _)| tenant)
+        .unwrap_or_default();
+
+    ["all".to_string(), format!("priority:{}", task.priority)]
+        .into_iter()
+        .chain(task.labels.iter().map(|label| format!("label:{label}")))
+        .chain(
+            task.project_id
+                .iter()
+                .map(|project_id| format!("project:{project_id}")),
+        )
+        .map(|facet| facet_key(tenant, &task.owner, &facet))
+        .chain(
+            task.project_id
+                .iter()
+                .map(|project_id| project_key(tenant, project_id)),
+        )
+        .collect()
+}
+
+fn facet_key(tenant: &str, owner: &str, facet: &str) -> String {
+    format!("{tenant}|{owner}|{facet}")
+}
+
+fn project_key(tenant: &str, project_id: &str) -> String {
+    format!("{tenant}|project:{project_id}")
+}
+
+fn manifest_key(aggregate_id: &str) -> String {
+    format!("task|{aggregate_id}")
+}
+
+/// A Query that keeps the Task Index in line with each Task's View
+///
+/// This reads the Task state from the default View, so it must be registered after the view
+/// `Query` that keeps it up to date.
+pub struct IndexQuery {
+    tasks: Arc<Box<dyn ViewRepository<View, Task>>>,
+    index: Arc<TaskIndex>,
+}
+
+impl IndexQuery {
+    /// Create a new instance
+    pub fn new(tasks: Arc<Box<dyn ViewRepository<View, Task>>>, index: Arc<TaskIndex>) -> Self {
+        Self { tasks, index }
+    }
+
+    async fn update(&self, task_id: &str) -> Result<(), PersistenceError> {
+        let Some(view) = self.tasks.load(task_id).await? else {
+            return Ok(());
+        };
+
+        self.index.sync(task_id, &view).await
+    }
+}
+
+#[async_trait]
+impl cqrs_es::Query<Task> for IndexQuery {
+    async fn dispatch(&self, task_id: &str, _events: &[EventEnvelope<Task>]) {
+        if let Err(err) = self.update(task_id).await {
+            error!(
+                err:err = err,
+                task_id = task_id;
+                "IndexQuery: {}",
+                err,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(labels: &[&str], priority: Option<Priority>, project_id: Option<&str>) -> Filter {
+        Filter {
+            labels: labels.iter().map(ToString::to_string).collect(),
+            priority,
+            project_id: project_id.map(str::to_string),
+        }
+    }
+
+    fn values(values: &[(&str, &str)]) -> BTreeMap<String, String> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn unfiltered_searches_query_every_task() {
+        let search = Search::new("acme", "user-1", &Filter::default());
+
+        assert_eq!(search.facet, "acme|user-1|all");
+        assert_eq!(search.conditions, ["#owner = :owner"]);
+        assert_eq!(search.values, values(&[(":owner", "user-1")]));
+    }
+
+    #[test]
+    fn priorities_are_queried_when_theyre_the_only_filter() {
+        let search = Search::new("acme", "user-1", &filter(&[], Some(Priority::High), None));
+
+        assert_eq!(search.facet, "acme|user-1|priority:High");
+        assert_eq!(search.conditions, ["#owner = :owner"]);
+    }
+
+    #[test]
+    fn the_first_label_is_queried_and_the_rest_are_filtered_on() {
+        let search = Search::new(
+            "acme",
+            "user-1",
+            &filter(&["frontend", "api", "backend"], Some(Priority::Low), None),
+        );
+
+        assert_eq!(search.facet, "acme|user-1|label:api");
+        assert_eq!(
+            search.conditions,
+            [
+                "#owner = :owner",
+                "Priority = :priority",
+                "contains(Labels, :label0)",
+                "contains(Labels, :label1)",
+            ]
+        );
+        assert_eq!(
+            search.values,
+            values(&[
+                (":owner", "user-1"),
+                (":priority", "Low"),
+                (":label0", "backend"),
+                (":label1", "frontend"),
+            ])
+        );
+    }
+
+    #[test]
+    fn projects_are_queried_and_every_label_is_filtered_on() {
+        let search = Search::new(
+            "acme",
+            "user-1",
+            &filter(&["api"], Some(Priority::High), Some("project-1")),
+        );
+
+        assert_eq!(search.facet, "acme|user-1|project:project-1");
+        assert_eq!(
+            search.conditions,
+            [
+                "#owner = :owner",
+                "Priority = :priority",
+                "contains(Labels, :label0)",
+            ]
+        );
+        assert_eq!(
+            search.values,
+            values(&[
+                (":owner", "user-1"),
+                (":priority", "High"),
+                (":label0", "api"),
+            ])
+        );
+    }
+
+    #[test]
+    fn tasks_are_listed_under_each_facet_they_can_be_searched_by() {
+        let mut view = View {
+            task: Task {
+                owner: "user-1".to_string(),
+                labels: BTreeSet::from(["api".to_string()]),
+                priority: Priority::High,
+                project_id: Some("project-1".to_string()),
+                ..Task::default()
+            },
+            ..View::default()
+        };
+
+        assert_eq!(
+            facets("acme#task-1", &view),
+            BTreeSet::from([
+                "acme|user-1|all".to_string(),
+                "acme|user-1|priority:High".to_string(),
+                "acme|user-1|label:api".to_string(),
+                "acme|user-1|project:project-1".to_string(),
+                "acme|project:project-1".to_string(),
+            ])
+        );
+
+        view.task.deleted = true;
+        assert!(facets("acme#task-1", &view).is_empty());
+    }
+}
@@ -1,17 +1,41 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::utils;
 
-use super::Task;
+use super::{Priority, Recurrence, Relation, Task};
 
 /// An input type for Task creation
-#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Create {
     /// A name
     pub name: String,
 
     /// An optional summary
     pub summary: Option<String>,
+
+    /// When the Task is due
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+
+    /// When to remind the owner about the Task
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Utc>>,
+
+    /// Labels to file the Task under
+    #[serde(default)]
+    pub labels: BTreeSet<String>,
+
+    /// How urgent the Task is
+    #[serde(default)]
+    pub priority: Priority,
+
+    /// How often the Task repeats once it's completed
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
 }
 
 impl From<Task> for Create {
@@ -19,19 +43,99 @@ impl From<Task> for Create {
         Create {
             name: task.name.clone(),
             summary: task.summary.clone(),
+            due_at: task.due_at,
+            remind_at: task.remind_at,
+            labels: task.labels.clone(),
+            priority: task.priority,
+            recurrence: task.recurrence.clone(),
         }
     }
 }
 
 /// An input type that supports partial Task updates
-#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Update {
     /// A name
     pub name: Option<String>,
 
-    /// An optional summary
+    /// An optional summary. Omit to leave it unchanged, or set it to `null` to clear it.
+    #[serde(default, skip_serializing_if = "utils::Update::is_unchanged")]
     pub summary: utils::Update<String>,
 
-    /// Whether this Task is completed or not
-    pub done: Option<bool>,
+    /// When the Task is due. Omit to leave it unchanged, or set it to `null` to clear it.
+    #[serde(default, skip_serializing_if = "utils::Update::is_unchanged")]
+    pub due_at: utils::Update<DateTime<Utc>>,
+
+    /// When to remind the owner about the Task. Omit to leave it unchanged, or set it to `null`
+    /// to clear it.
+    #[serde(default, skip_serializing_if = "utils::Update::is_unchanged")]
+    pub remind_at: utils::Update<DateTime<Utc>>,
+
+    /// Labels to add to the Task, leaving its other labels in place
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub add_labels: BTreeSet<String>,
+
+    /// Labels to remove from the Task
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub remove_labels: BTreeSet<String>,
+
+    /// A new priority for the Task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+
+    /// How often the Task repeats once it's completed. Omit to leave it unchanged, or set it to
+    /// `null` to stop it repeating.
+    #[serde(default, skip_serializing_if = "utils::Update::is_unchanged")]
+    pub recurrence: utils::Update<Recurrence>,
+}
+
+impl Update {
+    /// Whether this update changes any of the Task's details, other than its labels or priority
+    pub fn changes_details(&self) -> bool {
+        self.name.is_some()
+            || self.summary.is_changed()
+            || self.due_at.is_changed()
+            || self.remind_at.is_changed()
+            || self.recurrence.is_changed()
+    }
+}
+
+/// An input type for blocking a Task
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Block {
+    /// What the Task is waiting on
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// An input type for moving a Task into or out of a Project
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Move {
+    /// The Project to move the Task into, or `null` to take it out of its current Project
+    #[serde(default)]
+    pub project_id: Option<String>,
+}
+
+/// An input type for linking a Task to another one, or removing a link
+#[derive(Clone, Debug, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Link {
+    /// How the Task relates to the other one
+    pub relation: Relation,
+
+    /// The ID of the other Task
+    pub task_id: String,
+}
+
+/// An input type for adding or editing a comment on a Task
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Comment {
+    /// The comment text
+    pub body: String,
+}
+
+/// An input type for assigning a Task to a subject, or unassigning them
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct Assignment {
+    /// The subject to assign or unassign
+    pub assignee: String,
 }
@@ -0,0 +1,69 @@
+use std::{collections::BTreeSet, fmt};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The longest label accepted, in characters
+const MAX_LABEL_LENGTH: usize = 64;
+
+/// How urgent a Task is
+#[derive(
+    Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, JsonSchema,
+)]
+pub enum Priority {
+    /// Can wait
+    Low,
+
+    /// The default
+    #[default]
+    Normal,
+
+    /// Should be done soon
+    High,
+
+    /// Needs attention now
+    Urgent,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Priority::Low => f.write_str("Low"),
+            Priority::Normal => f.write_str("Normal"),
+            Priority::High => f.write_str("High"),
+            Priority::Urgent => f.write_str("Urgent"),
+        }
+    }
+}
+
+/// Normalize labels so they match regardless of case or surrounding whitespace, dropping any that
+/// are empty and truncating any that are too long
+///
+/// ```rust
+/// use std::collections::BTreeSet;
+///
+/// use event_driven_architecture::domains::tasks::labels::normalize;
+///
+/// let labels = normalize(["  Backend", "backend", "", "UI "]);
+///
+/// assert_eq!(labels, BTreeSet::from(["backend".to_string(), "ui".to_string()]));
+/// ```
+pub fn normalize<I, S>(labels: I) -> BTreeSet<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    labels
+        .into_iter()
+        .map(|label| {
+            label
+                .as_ref()
+                .trim()
+                .to_lowercase()
+                .chars()
+                .take(MAX_LABEL_LENGTH)
+                .collect::<String>()
+        })
+        .filter(|label| !label.is_empty())
+        .collect()
+}
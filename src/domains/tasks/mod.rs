@@ -10,13 +10,41 @@ pub mod events;
 /// Task input types
 pub mod inputs;
 
+/// The Task lifecycle
+pub mod status;
+
+/// Subtasks and dependencies between Tasks
+pub mod relations;
+
+/// The per-Task activity feed, including comments
+pub mod activity;
+
+/// Task labels and priority
+pub mod labels;
+
+/// Recurrence rules and series for repeating Tasks
+pub mod recurrence;
+
+/// The secondary read model used to filter Tasks by label and priority
+pub mod index;
+
+/// The per-subject inbox of assigned Tasks
+pub mod inbox;
+
 /// The default Task View
 pub mod view;
 
 /// The default Task CqrsFramework
 pub mod cqrs;
 
-pub use aggregate::{Services, Task, AGGREGATE_TYPE};
+/// Durable scheduling for Task reminders and due dates
+pub mod schedule;
+
+pub use aggregate::{Authorizer, OwnerOnly, Projects, Services, Task, Tasks, AGGREGATE_TYPE};
 pub use commands::Command;
 pub use events::Event;
+pub use labels::Priority;
+pub use recurrence::{Recurrence, Series};
+pub use relations::Relation;
+pub use status::Status;
 pub use view::{Query, View};
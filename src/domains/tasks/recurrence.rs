@@ -0,0 +1,475 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDateTime, Utc};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+/// The bits of a ULID that aren't its timestamp
+const RANDOM_MASK: u128 = (1 << 80) - 1;
+
+/// The RFC 5545 date-time format used by `UNTIL`
+const UNTIL_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// How often a recurring Task repeats
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub enum Frequency {
+    /// Every `interval` days
+    Daily,
+
+    /// Every `interval` weeks
+    Weekly,
+
+    /// Every `interval` months, on the same day of the month as the first occurrence. Months
+    /// without that day are skipped.
+    Monthly,
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frequency::Daily => write!(f, "DAILY"),
+            Frequency::Weekly => write!(f, "WEEKLY"),
+            Frequency::Monthly => write!(f, "MONTHLY"),
+        }
+    }
+}
+
+/// A recurrence rule, written as the `FREQ`, `INTERVAL`, `UNTIL`, and `COUNT` parts of an
+/// RFC 5545 RRULE
+///
+/// ```rust
+/// use event_driven_architecture::domains::tasks::recurrence::{Frequency, Recurrence};
+///
+/// let rule: Recurrence = "RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=4".parse().unwrap();
+///
+/// assert_eq!(rule.frequency, Frequency::Weekly);
+/// assert_eq!(rule.interval, 2);
+/// assert_eq!(rule.count, Some(4));
+/// assert_eq!(rule.to_string(), "FREQ=WEEKLY;INTERVAL=2;COUNT=4");
+///
+/// assert!("FREQ=WEEKLY;BYDAY=MO".parse::<Recurrence>().is_err());
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    /// How often the Task repeats
+    pub frequency: Frequency,
+
+    /// How many days, weeks, or months apart each occurrence is
+    pub interval: u32,
+
+    /// No occurrences are due after this time
+    pub until: Option<DateTime<Utc>>,
+
+    /// How many occurrences there are in total, including the first
+    pub count: Option<u32>,
+}
+
+impl Recurrence {
+    /// The first occurrence due after `from`, in a series whose first occurrence was due at
+    /// `start`
+    ///
+    /// Every occurrence is a whole number of intervals from `start`, rather than from the one
+    /// before it, so a monthly series due on the 31st stays on the 31st and skips the months that
+    /// don't have one, as RFC 5545 does.
+    ///
+    /// ```rust
+    /// use chrono::{TimeZone, Utc};
+    /// use event_driven_architecture::domains::tasks::recurrence::Recurrence;
+    ///
+    /// let rule: Recurrence = "FREQ=MONTHLY".parse().unwrap();
+    /// let start = Utc.with_ymd_and_hms(2025, 1, 31, 9, 0, 0).unwrap();
+    ///
+    /// assert_eq!(
+    ///     rule.after(start, start),
+    ///     Some(Utc.with_ymd_and_hms(2025, 3, 31, 9, 0, 0).unwrap())
+    /// );
+    /// ```
+    pub fn after(&self, start: DateTime<Utc>, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let step = match self.frequency {
+            Frequency::Daily => Duration::days(self.interval.into()),
+            Frequency::Weekly => Duration::weeks(self.interval.into()),
+            Frequency::Monthly => return self.month_after(start, from),
+        };
+
+        // The number of whole intervals from the start to the next occurrence
+        let periods = if from < start {
+            1
+        } else {
+            (from - start).num_seconds() / step.num_seconds() + 1
+        };
+
+        start.checked_add_signed(step.checked_mul(periods.try_into().ok()?)?)
+    }
+
+    fn month_after(&self, start: DateTime<Utc>, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let months_apart = |date: DateTime<Utc>| {
+            i64::from(date.year()) * 12 + i64::from(date.month0())
+                - (i64::from(start.year()) * 12 + i64::from(start.month0()))
+        };
+
+        // Start from the interval that `from` falls in, since no occurrence comes before it
+        let mut periods = u32::try_from(months_apart(from).max(0) / i64::from(self.interval))
+            .ok()?
+            .max(1);
+
+        loop {
+            let candidate =
+                start.checked_add_months(Months::new(periods.checked_mul(self.interval)?))?;
+
+            // Adding months clamps to the end of shorter months, which isn't an occurrence
+            if candidate.day() == start.day() && candidate > from {
+                return Some(candidate);
+            }
+
+            periods = periods.checked_add(1)?;
+        }
+    }
+
+    /// When the occurrence after `occurrence`, due at `from`, is due, in a series whose first
+    /// occurrence was due at `start`, or `None` once the rule's `COUNT` or `UNTIL` has been
+    /// reached
+    pub fn next(
+        &self,
+        occurrence: u32,
+        start: DateTime<Utc>,
+        from: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        if self.count.is_some_and(|count| occurrence >= count) {
+            return None;
+        }
+
+        self.after(start, from)
+            .filter(|next| self.until.is_none_or(|until| *next <= until))
+    }
+}
+
+/// A recurrence rule that couldn't be parsed
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid recurrence rule: {0}")]
+pub struct ParseError(String);
+
+impl FromStr for Recurrence {
+    type Err = ParseError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut until = None;
+        let mut count = None;
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| ParseError(format!("`{part}` isn't a NAME=VALUE pair")))?;
+
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return Err(ParseError(format!("FREQ={value} isn't supported"))),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| ParseError(format!("INTERVAL={value} isn't positive")))?
+                }
+                "UNTIL" => {
+                    until = Some(
+                        NaiveDateTime::parse_from_str(value, UNTIL_FORMAT)
+                            .map_err(|_| {
+                                ParseError(format!("UNTIL={value} isn't a UTC date-time"))
+                            })?
+                            .and_utc(),
+                    )
+                }
+                "COUNT" => {
+                    count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| ParseError(format!("COUNT={value} isn't positive")))?,
+                    )
+                }
+                _ => return Err(ParseError(format!("{name} isn't supported"))),
+            }
+        }
+
+        if until.is_some() && count.is_some() {
+            return Err(ParseError("UNTIL and COUNT can't be combined".to_string()));
+        }
+
+        Ok(Recurrence {
+            frequency: frequency.ok_or_else(|| ParseError("FREQ is required".to_string()))?,
+            interval,
+            until,
+            count,
+        })
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={};INTERVAL={}", self.frequency, self.interval)?;
+
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format(UNTIL_FORMAT))?;
+        }
+
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = ParseError;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(rule: Recurrence) -> Self {
+        rule.to_string()
+    }
+}
+
+impl JsonSchema for Recurrence {
+    fn schema_name() -> String {
+        "Recurrence".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = gen.subschema_for::<String>().into_object();
+        schema.metadata().description = Some(
+            "An RFC 5545 RRULE using FREQ (DAILY, WEEKLY, or MONTHLY), INTERVAL, and either \
+             UNTIL or COUNT, such as \"FREQ=WEEKLY;INTERVAL=2;COUNT=6\""
+                .to_string(),
+        );
+        schema.into()
+    }
+}
+
+/// The series a recurring Task belongs to
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub struct Series {
+    /// The ID of the first Task in the series
+    pub id: String,
+
+    /// Which occurrence in the series this is, starting from 1
+    pub occurrence: u32,
+
+    /// When the first occurrence was due, which every later occurrence is scheduled from
+    pub starts_at: DateTime<Utc>,
+}
+
+impl Series {
+    /// The series started by a Task that isn't part of one yet, due at `starts_at`
+    pub fn start(task_id: &str, starts_at: DateTime<Utc>) -> Self {
+        Series {
+            id: task_id.to_string(),
+            occurrence: 1,
+            starts_at,
+        }
+    }
+
+    /// The series position of the occurrence after this one
+    pub fn next(&self) -> Self {
+        Series {
+            id: self.id.clone(),
+            occurrence: self.occurrence + 1,
+            starts_at: self.starts_at,
+        }
+    }
+
+    /// The Task ID for this occurrence, derived from the series ID so that creating the same
+    /// occurrence twice addresses the same aggregate
+    ///
+    /// ```rust
+    /// use chrono::Utc;
+    /// use event_driven_architecture::domains::tasks::recurrence::Series;
+    ///
+    /// let first = Series::start("01J73SBWHE373VXWZTF7SJADD9", Utc::now());
+    ///
+    /// assert_eq!(first.task_id().as_deref(), Some("01J73SBWHE373VXWZTF7SJADD9"));
+    /// assert_eq!(first.next().task_id(), first.next().task_id());
+    /// assert_ne!(first.next().task_id(), first.next().next().task_id());
+    /// ```
+    pub fn task_id(&self) -> Option<String> {
+        let series = Ulid::from_string(&self.id).ok()?;
+        let offset = u128::from(self.occurrence.saturating_sub(1));
+
+        Some(
+            Ulid::from_parts(
+                series.timestamp_ms(),
+                series.random().wrapping_add(offset) & RANDOM_MASK,
+            )
+            .to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 9, 0, 0).unwrap()
+    }
+
+    fn rule(rule: &str) -> Recurrence {
+        rule.parse().unwrap()
+    }
+
+    /// The due dates of a series' occurrences, following it the way the Task aggregate does
+    fn occurrences(rule: &Recurrence, start: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let mut due = vec![start];
+
+        while let Some(next) = rule.next(due.len() as u32, start, *due.last().unwrap()) {
+            due.push(next);
+
+            if due.len() == 8 {
+                break;
+            }
+        }
+
+        due
+    }
+
+    #[test]
+    fn rules_are_parsed() {
+        assert_eq!(
+            rule("RRULE:FREQ=DAILY"),
+            Recurrence {
+                frequency: Frequency::Daily,
+                interval: 1,
+                until: None,
+                count: None,
+            }
+        );
+
+        let until = rule("freq=monthly;interval=3;until=20251231T235959Z");
+        assert_eq!(until.frequency, Frequency::Monthly);
+        assert_eq!(until.interval, 3);
+        assert_eq!(
+            until.until,
+            Some(Utc.with_ymd_and_hms(2025, 12, 31, 23, 59, 59).unwrap())
+        );
+
+        // Rules round-trip through the form they're stored in
+        assert_eq!(until.to_string().parse::<Recurrence>().unwrap(), until);
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        for invalid in [
+            "",
+            "INTERVAL=2",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=x",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;UNTIL=2025-12-31",
+            "FREQ=DAILY;COUNT=2;UNTIL=20251231T000000Z",
+            "FREQ=WEEKLY;BYDAY=MO",
+            "FREQ",
+        ] {
+            assert!(invalid.parse::<Recurrence>().is_err(), "{invalid:?} parsed");
+        }
+    }
+
+    #[test]
+    fn count_includes_the_first_occurrence() {
+        let due = occurrences(&rule("FREQ=WEEKLY;INTERVAL=2;COUNT=3"), at(2025, 1, 6));
+
+        assert_eq!(due, vec![at(2025, 1, 6), at(2025, 1, 20), at(2025, 2, 3)]);
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        let daily = rule("FREQ=DAILY;UNTIL=20250103T090000Z");
+
+        assert_eq!(
+            occurrences(&daily, at(2025, 1, 1)),
+            vec![at(2025, 1, 1), at(2025, 1, 2), at(2025, 1, 3)]
+        );
+    }
+
+    #[test]
+    fn monthly_series_skip_months_without_their_day() {
+        let due = occurrences(&rule("FREQ=MONTHLY;COUNT=4"), at(2025, 1, 31));
+
+        assert_eq!(
+            due,
+            vec![
+                at(2025, 1, 31),
+                at(2025, 3, 31),
+                at(2025, 5, 31),
+                at(2025, 7, 31)
+            ]
+        );
+
+        // February 29th only comes around in leap years
+        let leap_day = occurrences(&rule("FREQ=MONTHLY;INTERVAL=12;COUNT=3"), at(2024, 2, 29));
+
+        assert_eq!(
+            leap_day,
+            vec![at(2024, 2, 29), at(2028, 2, 29), at(2032, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn occurrences_are_scheduled_from_the_start_of_the_series() {
+        let monthly = rule("FREQ=MONTHLY");
+
+        // An occurrence that was moved earlier doesn't move the rest of the series
+        assert_eq!(
+            monthly.next(2, at(2025, 1, 31), at(2025, 3, 20)),
+            Some(at(2025, 3, 31))
+        );
+
+        let weekly = rule("FREQ=WEEKLY");
+
+        assert_eq!(
+            weekly.next(2, at(2025, 1, 6), at(2025, 1, 15)),
+            Some(at(2025, 1, 20))
+        );
+    }
+
+    #[test]
+    fn occurrence_ids_are_stable() {
+        let first = Series::start("01J73SBWHE373VXWZTF7SJADD9", at(2025, 1, 1));
+        let second = first.next();
+
+        assert_eq!(
+            first.task_id().as_deref(),
+            Some("01J73SBWHE373VXWZTF7SJADD9")
+        );
+        assert_eq!(second.occurrence, 2);
+        assert_eq!(second.starts_at, first.starts_at);
+
+        // The same occurrence always has the same ID, and no two occurrences share one
+        assert_eq!(second.task_id(), first.next().task_id());
+        assert_ne!(second.task_id(), first.task_id());
+        assert_ne!(second.task_id(), second.next().task_id());
+
+        let not_a_ulid = Series::start("task-1", at(2025, 1, 1));
+        assert_eq!(not_a_ulid.task_id(), None);
+    }
+}
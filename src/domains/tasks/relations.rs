@@ -0,0 +1,265 @@
+use std::{collections::HashSet, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use cqrs_es::{
+    persist::{PersistenceError, ViewRepository},
+    EventEnvelope,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::domains::{self, tenants};
+
+use super::{Event, Task, Tasks, View};
+
+/// The most links followed when checking for a cycle, so a corrupt graph can't stall a command
+const MAX_DEPTH: usize = 256;
+
+/// The ways one Task can be linked to another
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub enum Relation {
+    /// The linked Task is this Task's parent, making this Task one of its subtasks
+    Parent,
+
+    /// This Task can't be completed until the linked Task is
+    BlockedBy,
+}
+
+impl fmt::Display for Relation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Relation::Parent => f.write_str("Parent"),
+            Relation::BlockedBy => f.write_str("BlockedBy"),
+        }
+    }
+}
+
+impl Relation {
+    /// The Tasks that `task` is linked to by this relation
+    pub fn targets(self, task: &Task) -> Vec<String> {
+        match self {
+            Relation::Parent => task.parent_id.iter().cloned().collect(),
+            Relation::BlockedBy => task.blocked_by.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Check whether linking the Task `id` to `target_id` would create a cycle
+///
+/// This follows the same relation outward from the target, so a Task can't become its own
+/// ancestor, or end up waiting on itself. Tasks that can't be found end the search along that
+/// path.
+pub(super) async fn creates_cycle(
+    tasks: &dyn Tasks,
+    tenant: &str,
+    relation: Relation,
+    id: &str,
+    target_id: &str,
+) -> Result<bool, domains::Error> {
+    let mut pending = vec![(target_id.to_string(), 0)];
+    let mut seen = HashSet::new();
+
+    while let Some((current, depth)) = pending.pop() {
+        if current == id {
+            return Ok(true);
+        }
+
+        if depth >= MAX_DEPTH || !seen.insert(current.clone()) {
+            continue;
+        }
+
+        if let Some(task) = tasks.find(&tenants::scoped_id(tenant, &current)).await? {
+            pending.extend(
+                relation
+                    .targets(&task)
+                    .into_iter()
+                    .map(|next| (next, depth + 1)),
+            );
+        }
+    }
+
+    Ok(false)
+}
+
+/// A Query that lists each Task's subtasks on the parent's View
+///
+/// Subtasks record their parent on their own events, so this copies each change across to the
+/// parent's View. Parents without a View yet are skipped.
+pub struct ChildrenQuery {
+    tasks: Arc<Box<dyn ViewRepository<View, Task>>>,
+}
+
+impl ChildrenQuery {
+    /// Create a new instance
+    pub fn new(tasks: Arc<Box<dyn ViewRepository<View, Task>>>) -> Self {
+        Self { tasks }
+    }
+
+    async fn update(
+        &self,
+        task_id: &str,
+        events: &[EventEnvelope<Task>],
+    ) -> Result<(), PersistenceError> {
+        for event in events {
+            let (id, parent_id, linked) = match &event.payload {
+                Event::Linked {
+                    id,
+                    relation: Relation::Parent,
+                    task_id,
+                    ..
+                } => (id, task_id, true),
+                Event::Unlinked {
+                    id,
+                    relation: Relation::Parent,
+                    task_id,
+                    ..
+                } => (id, task_id, false),
+                _ => continue,
+            };
+
+            let Some((mut view, context)) = self
+                .tasks
+                .load_with_context(&related_id(task_id, parent_id))
+                .await?
+            else {
+                continue;
+            };
+
+            view.children.retain(|child| child != id);
+
+            if linked {
+                view.children.push(id.clone());
+            }
+
+            self.tasks.update_view(view, context).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The aggregate ID of a related Task, in the same tenant as `aggregate_id`
+fn related_id(aggregate_id: &str, id: &str) -> String {
+    match tenants::split_id(aggregate_id) {
+        Some((tenant, _)) => tenants::scoped_id(tenant, id),
+        None => id.to_string(),
+    }
+}
+
+#[async_trait]
+impl cqrs_es::Query<Task> for ChildrenQuery {
+    async fn dispatch(&self, task_id: &str, events: &[EventEnvelope<Task>]) {
+        if let Err(err) = self.update(task_id, events).await {
+            error!(
+                err:err = err,
+                task_id = task_id;
+                "ChildrenQuery: {}",
+                err,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const TENANT: &str = "acme";
+
+    /// Tasks linked as parents and blockers, by unscoped ID
+    struct Graph(HashMap<String, Task>);
+
+    impl Graph {
+        fn new(links: &[(&str, Relation, &str)]) -> Self {
+            let mut tasks = HashMap::<String, Task>::new();
+
+            for (id, relation, target) in links {
+                let task = tasks.entry(id.to_string()).or_insert_with(|| Task {
+                    id: id.to_string(),
+                    ..Task::default()
+                });
+
+                match relation {
+                    Relation::Parent => task.parent_id = Some(target.to_string()),
+                    Relation::BlockedBy => {
+                        task.blocked_by.insert(target.to_string());
+                    }
+                }
+            }
+
+            Self(tasks)
+        }
+
+        async fn cycle(&self, relation: Relation, id: &str, target_id: &str) -> bool {
+            creates_cycle(self, TENANT, relation, id, target_id)
+                .await
+                .unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl Tasks for Graph {
+        async fn find(&self, aggregate_id: &str) -> Result<Option<Task>, domains::Error> {
+            let (tenant, id) = tenants::split_id(aggregate_id).expect("The ID is scoped");
+            assert_eq!(tenant, TENANT);
+
+            Ok(self.0.get(id).cloned())
+        }
+    }
+
+    #[tokio::test]
+    async fn tasks_cant_be_linked_to_themselves() {
+        let graph = Graph::new(&[]);
+
+        assert!(graph.cycle(Relation::Parent, "a", "a").await);
+        assert!(graph.cycle(Relation::BlockedBy, "a", "a").await);
+    }
+
+    #[tokio::test]
+    async fn indirect_cycles_are_found() {
+        use Relation::{BlockedBy, Parent};
+
+        // c is a subtask of b, which is a subtask of a, and c is waiting on b, which is waiting on a
+        let graph = Graph::new(&[
+            ("b", Parent, "a"),
+            ("c", Parent, "b"),
+            ("b", BlockedBy, "a"),
+            ("c", BlockedBy, "b"),
+        ]);
+
+        assert!(graph.cycle(Parent, "a", "c").await);
+        assert!(graph.cycle(BlockedBy, "a", "c").await);
+
+        // Each relation is followed on its own
+        let graph = Graph::new(&[("b", Parent, "a"), ("c", BlockedBy, "b")]);
+
+        assert!(!graph.cycle(BlockedBy, "a", "c").await);
+        assert!(!graph.cycle(Parent, "a", "c").await);
+    }
+
+    #[tokio::test]
+    async fn diamonds_are_not_cycles() {
+        use Relation::BlockedBy;
+
+        // d waits on b and c, which both wait on a
+        let graph = Graph::new(&[
+            ("b", BlockedBy, "a"),
+            ("c", BlockedBy, "a"),
+            ("d", BlockedBy, "b"),
+            ("d", BlockedBy, "c"),
+        ]);
+
+        assert!(!graph.cycle(BlockedBy, "e", "d").await);
+        assert!(!graph.cycle(BlockedBy, "a", "e").await);
+        assert!(graph.cycle(BlockedBy, "a", "d").await);
+    }
+
+    #[tokio::test]
+    async fn missing_tasks_end_the_search() {
+        let graph = Graph::new(&[("b", Relation::BlockedBy, "missing")]);
+
+        assert!(!graph.cycle(Relation::BlockedBy, "a", "b").await);
+    }
+}
@@ -0,0 +1,449 @@
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{DateTime, TimeZone, Utc};
+use cqrs_es::{
+    persist::{PersistenceError, ViewRepository},
+    EventEnvelope,
+};
+
+use crate::config::Config;
+
+use super::{Command, Task, View};
+
+/// The single partition that pending deadlines are indexed under, so they can be queried by time
+const PENDING: &str = "pending";
+
+/// The index used to find deadlines that have passed
+const DUE_INDEX: &str = "DueAtIndex";
+
+/// The kinds of time-based events a Task can schedule
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Deadline {
+    /// The owner asked to be reminded at `remind_at`
+    Reminder,
+
+    /// The Task becomes overdue at `due_at` unless it's completed first
+    Overdue,
+}
+
+impl Deadline {
+    /// The Command the scheduler issues when this deadline passes
+    pub fn command(self) -> Command {
+        match self {
+            Deadline::Reminder => Command::Remind,
+            Deadline::Overdue => Command::MarkOverdue,
+        }
+    }
+
+    /// When this deadline is next due for a Task, if it's still pending
+    pub fn due_at(self, task: &Task) -> Option<DateTime<Utc>> {
+        if task.deleted {
+            return None;
+        }
+
+        match self {
+            Deadline::Reminder if !task.reminded => task.remind_at,
+            Deadline::Overdue if task.status.is_open() && !task.overdue => task.due_at,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Deadline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Deadline::Reminder => f.write_str("Reminder"),
+            Deadline::Overdue => f.write_str("Overdue"),
+        }
+    }
+}
+
+impl FromStr for Deadline {
+    type Err = PersistenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Reminder" => Ok(Deadline::Reminder),
+            "Overdue" => Ok(Deadline::Overdue),
+            other => Err(PersistenceError::DeserializationError(
+                format!("Unknown deadline: {other}").into(),
+            )),
+        }
+    }
+}
+
+/// A pending deadline for a single Task
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    /// The tenant-scoped aggregate ID of the Task
+    pub aggregate_id: String,
+
+    /// Which deadline this is
+    pub deadline: Deadline,
+
+    /// When the deadline passes
+    pub due_at: DateTime<Utc>,
+}
+
+/// Durable storage for pending Task deadlines, with at most one entry per Task and deadline
+#[async_trait]
+pub trait Schedule: Send + Sync {
+    /// Bring a Task's entries in line with its current state
+    async fn sync(&self, aggregate_id: &str, task: &Task) -> Result<(), PersistenceError>;
+
+    /// Find up to `limit` entries that are due at or before `now`
+    async fn due(&self, now: DateTime<Utc>, limit: i32) -> Result<Vec<Entry>, PersistenceError>;
+
+    /// Remove an entry, but only if it hasn't been rescheduled since it was read
+    async fn complete(&self, entry: &Entry) -> Result<(), PersistenceError>;
+}
+
+/// Pending Task deadlines in a DynamoDB table keyed by `ScheduleId`
+///
+/// Entries are indexed by time in the `DueAtIndex`, so the scheduler can pick up everything that
+/// has passed, even after a restart.
+pub struct DynamoSchedule {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl DynamoSchedule {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Initialize the Task Schedule from the config
+    pub fn init(client: aws_sdk_dynamodb::Client, config: &Config) -> Arc<Self> {
+        Arc::new(Self::new(client, &config.tables.tasks_schedule))
+    }
+
+    async fn put(&self, entry: &Entry) -> Result<(), PersistenceError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(encode(entry)))
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        aggregate_id: &str,
+        deadline: Deadline,
+        due_at: Option<DateTime<Utc>>,
+    ) -> Result<(), PersistenceError> {
+        let mut request = self.client.delete_item().table_name(&self.table_name).key(
+            "ScheduleId",
+            AttributeValue::S(schedule_id(aggregate_id, deadline)),
+        );
+
+        if let Some(due_at) = due_at {
+            request = request
+                .condition_expression("DueAt = :due_at")
+                .expression_attribute_values(
+                    ":due_at",
+                    AttributeValue::N(due_at.timestamp_millis().to_string()),
+                );
+        }
+
+        match request.send().await {
+            Ok(_) => Ok(()),
+            // The entry was rescheduled, so leave the new deadline in place
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+            {
+                Ok(())
+            }
+            Err(err) => Err(PersistenceError::ConnectionError(Box::new(err))),
+        }
+    }
+}
+
+#[async_trait]
+impl Schedule for DynamoSchedule {
+    async fn sync(&self, aggregate_id: &str, task: &Task) -> Result<(), PersistenceError> {
+        for deadline in [Deadline::Reminder, Deadline::Overdue] {
+            match deadline.due_at(task) {
+                Some(due_at) => {
+                    self.put(&Entry {
+                        aggregate_id: aggregate_id.to_string(),
+                        deadline,
+                        due_at,
+                    })
+                    .await?
+                }
+                None => self.delete(aggregate_id, deadline, None).await?,
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: i32) -> Result<Vec<Entry>, PersistenceError> {
+        let output = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name(DUE_INDEX)
+            .key_condition_expression("#bucket = :bucket AND DueAt <= :now")
+            .expression_attribute_names("#bucket", "Bucket")
+            .expression_attribute_values(":bucket", AttributeValue::S(PENDING.to_string()))
+            .expression_attribute_values(
+                ":now",
+                AttributeValue::N(now.timestamp_millis().to_string()),
+            )
+            .limit(limit)
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        output.items().iter().map(decode).collect()
+    }
+
+    async fn complete(&self, entry: &Entry) -> Result<(), PersistenceError> {
+        self.delete(&entry.aggregate_id, entry.deadline, Some(entry.due_at))
+            .await
+    }
+}
+
+fn schedule_id(aggregate_id: &str, deadline: Deadline) -> String {
+    format!("{aggregate_id}|{deadline}")
+}
+
+fn encode(entry: &Entry) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        (
+            "ScheduleId".to_string(),
+            AttributeValue::S(schedule_id(&entry.aggregate_id, entry.deadline)),
+        ),
+        ("Bucket".to_string(), AttributeValue::S(PENDING.to_string())),
+        (
+            "DueAt".to_string(),
+            AttributeValue::N(entry.due_at.timestamp_millis().to_string()),
+        ),
+        (
+            "AggregateId".to_string(),
+            AttributeValue::S(entry.aggregate_id.clone()),
+        ),
+        (
+            "Deadline".to_string(),
+            AttributeValue::S(entry.deadline.to_string()),
+        ),
+    ])
+}
+
+fn decode(item: &HashMap<String, AttributeValue>) -> Result<Entry, PersistenceError> {
+    let missing =
+        |field: &str| PersistenceError::DeserializationError(format!("Missing {field}").into());
+
+    let aggregate_id = item
+        .get("AggregateId")
+        .and_then(|v| v.as_s().ok())
+        .ok_or_else(|| missing("AggregateId"))?;
+
+    let deadline = item
+        .get("Deadline")
+        .and_then(|v| v.as_s().ok())
+        .ok_or_else(|| missing("Deadline"))?
+        .parse()?;
+
+    let due_at = item
+        .get("DueAt")
+        .and_then(|v| v.as_n().ok())
+        .and_then(|n| n.parse::<i64>().ok())
+        .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+        .ok_or_else(|| missing("DueAt"))?;
+
+    Ok(Entry {
+        aggregate_id: aggregate_id.clone(),
+        deadline,
+        due_at,
+    })
+}
+
+/// A Query that keeps the Schedule in line with each Task's deadlines
+///
+/// This reads the Task state from the default View, so it must be registered after the view
+/// `Query` that keeps it up to date.
+pub struct ScheduleQuery {
+    tasks: Arc<Box<dyn ViewRepository<View, Task>>>,
+    schedule: Arc<dyn Schedule>,
+}
+
+impl ScheduleQuery {
+    /// Create a new instance
+    pub fn new(
+        tasks: Arc<Box<dyn ViewRepository<View, Task>>>,
+        schedule: Arc<dyn Schedule>,
+    ) -> Self {
+        Self { tasks, schedule }
+    }
+
+    async fn update(&self, task_id: &str) -> Result<(), PersistenceError> {
+        let Some(view) = self.tasks.load(task_id).await? else {
+            return Ok(());
+        };
+
+        self.schedule.sync(task_id, &view.task).await
+    }
+}
+
+#[async_trait]
+impl cqrs_es::Query<Task> for ScheduleQuery {
+    async fn dispatch(&self, task_id: &str, _events: &[EventEnvelope<Task>]) {
+        if let Err(err) = self.update(task_id).await {
+            error!(
+                err:err = err,
+                task_id = task_id;
+                "ScheduleQuery: {}",
+                err,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::domains::tasks::Status;
+
+    fn task(remind_at: Option<DateTime<Utc>>, due_at: Option<DateTime<Utc>>) -> Task {
+        Task {
+            id: "task-1".to_string(),
+            remind_at,
+            due_at,
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn open_tasks_are_due_at_their_deadlines() {
+        let remind_at = Utc::now();
+        let due_at = remind_at + Duration::days(1);
+        let task = task(Some(remind_at), Some(due_at));
+
+        assert_eq!(Deadline::Reminder.due_at(&task), Some(remind_at));
+        assert_eq!(Deadline::Overdue.due_at(&task), Some(due_at));
+
+        let without = self::task(None, None);
+
+        assert_eq!(Deadline::Reminder.due_at(&without), None);
+        assert_eq!(Deadline::Overdue.due_at(&without), None);
+    }
+
+    #[test]
+    fn deadlines_that_fired_are_no_longer_due() {
+        let now = Utc::now();
+
+        let reminded = Task {
+            reminded: true,
+            ..task(Some(now), Some(now))
+        };
+
+        assert_eq!(Deadline::Reminder.due_at(&reminded), None);
+        assert_eq!(Deadline::Overdue.due_at(&reminded), Some(now));
+
+        let overdue = Task {
+            overdue: true,
+            ..task(Some(now), Some(now))
+        };
+
+        assert_eq!(Deadline::Reminder.due_at(&overdue), Some(now));
+        assert_eq!(Deadline::Overdue.due_at(&overdue), None);
+    }
+
+    #[test]
+    fn closed_tasks_are_never_overdue() {
+        let now = Utc::now();
+
+        for status in [Status::Done, Status::Archived] {
+            let task = Task {
+                status,
+                ..task(Some(now), Some(now))
+            };
+
+            // The owner still asked to be reminded, whatever became of the Task
+            assert_eq!(Deadline::Reminder.due_at(&task), Some(now));
+            assert_eq!(Deadline::Overdue.due_at(&task), None, "{status:?}");
+        }
+
+        for status in [Status::Todo, Status::InProgress, Status::Blocked] {
+            let task = Task {
+                status,
+                ..task(None, Some(now))
+            };
+
+            assert_eq!(Deadline::Overdue.due_at(&task), Some(now), "{status:?}");
+        }
+    }
+
+    #[test]
+    fn deleted_tasks_have_no_deadlines() {
+        let now = Utc::now();
+        let task = Task {
+            deleted: true,
+            ..task(Some(now), Some(now))
+        };
+
+        assert_eq!(Deadline::Reminder.due_at(&task), None);
+        assert_eq!(Deadline::Overdue.due_at(&task), None);
+    }
+
+    #[test]
+    fn entries_round_trip_through_items() {
+        // DynamoDB keeps the time to the millisecond
+        let due_at = Utc.timestamp_millis_opt(1_700_000_000_123).unwrap();
+
+        for deadline in [Deadline::Reminder, Deadline::Overdue] {
+            let entry = Entry {
+                aggregate_id: "acme#task-1".to_string(),
+                deadline,
+                due_at,
+            };
+
+            let item = encode(&entry);
+
+            assert_eq!(
+                item["ScheduleId"],
+                AttributeValue::S(format!("acme#task-1|{deadline}"))
+            );
+            assert_eq!(decode(&item).unwrap(), entry);
+        }
+    }
+
+    #[test]
+    fn malformed_items_are_rejected() {
+        let entry = Entry {
+            aggregate_id: "acme#task-1".to_string(),
+            deadline: Deadline::Reminder,
+            due_at: Utc::now(),
+        };
+
+        let mut unknown = encode(&entry);
+        unknown.insert(
+            "Deadline".to_string(),
+            AttributeValue::S("Nudge".to_string()),
+        );
+        assert!(decode(&unknown).is_err());
+
+        for field in ["AggregateId", "Deadline", "DueAt"] {
+            let mut missing = encode(&entry);
+            missing.remove(field);
+
+            assert!(decode(&missing).is_err(), "{field} is required");
+        }
+    }
+}
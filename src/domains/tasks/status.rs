@@ -0,0 +1,123 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Where a Task is in its lifecycle
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+pub enum Status {
+    /// Not started yet
+    #[default]
+    Todo,
+
+    /// Being worked on
+    InProgress,
+
+    /// Waiting on something before work can continue
+    Blocked,
+
+    /// Completed
+    Done,
+
+    /// Put away, and no longer changed
+    Archived,
+}
+
+impl Status {
+    /// Whether a Task with this status is allowed to move to the `next` one
+    pub fn can_become(self, next: Status) -> bool {
+        use Status::{Archived, Blocked, Done, InProgress, Todo};
+
+        matches!(
+            (self, next),
+            (Todo | Blocked, InProgress)
+                | (Todo | InProgress, Blocked)
+                | (Todo | InProgress, Done)
+                | (Done | Archived, Todo)
+                | (Todo | InProgress | Blocked | Done, Archived)
+        )
+    }
+
+    /// Whether the Task still has work left to do
+    pub fn is_open(self) -> bool {
+        !matches!(self, Status::Done | Status::Archived)
+    }
+
+    /// Read a status, or the `done` flag that Task snapshots and views recorded before the
+    /// lifecycle was introduced
+    pub(super) fn deserialize_legacy<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum StatusOrDone {
+            Status(Status),
+            Done(bool),
+        }
+
+        Ok(match StatusOrDone::deserialize(deserializer)? {
+            StatusOrDone::Status(status) => status,
+            StatusOrDone::Done(true) => Status::Done,
+            StatusOrDone::Done(false) => Status::Todo,
+        })
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Todo => f.write_str("Todo"),
+            Status::InProgress => f.write_str("InProgress"),
+            Status::Blocked => f.write_str("Blocked"),
+            Status::Done => f.write_str("Done"),
+            Status::Archived => f.write_str("Archived"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Status::{self, Archived, Blocked, Done, InProgress, Todo};
+
+    const ALL: [Status; 5] = [Todo, InProgress, Blocked, Done, Archived];
+
+    #[test]
+    fn only_lifecycle_transitions_are_allowed() {
+        let allowed = [
+            (Todo, InProgress),
+            (Todo, Blocked),
+            (Todo, Done),
+            (Todo, Archived),
+            (InProgress, Blocked),
+            (InProgress, Done),
+            (InProgress, Archived),
+            (Blocked, InProgress),
+            (Blocked, Archived),
+            (Done, Todo),
+            (Done, Archived),
+            (Archived, Todo),
+        ];
+
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(
+                    from.can_become(to),
+                    allowed.contains(&(from, to)),
+                    "{from} -> {to}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_done_flags_are_read_as_statuses() {
+        let read = |json: &str| {
+            Status::deserialize_legacy(&mut serde_json::Deserializer::from_str(json)).unwrap()
+        };
+
+        assert_eq!(read("true"), Done);
+        assert_eq!(read("false"), Todo);
+        assert_eq!(read("\"InProgress\""), InProgress);
+    }
+}
@@ -5,12 +5,13 @@ use cqrs_es::{
     persist::{PersistenceError, ViewContext, ViewRepository},
     Aggregate, EventEnvelope, View as CqrsView,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::{Task, AGGREGATE_TYPE};
 
 /// The default View for a Task
-#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
 pub struct View {
     /// The Aggregage type, to differentiate the many types stored in the default view
     pub aggregate_type: String,
@@ -18,11 +19,19 @@ pub struct View {
     /// The last Command ID processed
     pub command_id: String,
 
+    /// The tenant the Task belongs to
+    #[serde(default)]
+    pub tenant: String,
+
     /// The Task id
     pub id: String,
 
     /// The primary entity, a Task
     pub task: Task,
+
+    /// The IDs of this Task's subtasks, copied from their `Task:Linked` events
+    #[serde(default)]
+    pub children: Vec<String>,
 }
 
 impl cqrs_es::View<Task> for View {
@@ -36,6 +45,10 @@ impl cqrs_es::View<Task> for View {
             .unwrap_or(&"".to_string())
             .to_string();
 
+        if let Some(tenant) = event.metadata.get("tenant") {
+            self.tenant.clone_from(tenant);
+        }
+
         self.task.apply(event.payload.clone());
     }
 }
@@ -0,0 +1,36 @@
+/// The separator between the tenant and the entity ID within a tenant-scoped aggregate ID
+pub const SEPARATOR: char = '#';
+
+/// The longest tenant ID accepted
+const MAX_LENGTH: usize = 64;
+
+/// Check that a tenant ID is usable as a prefix for aggregate IDs, partition keys, and object keys
+pub fn is_valid(tenant: &str) -> bool {
+    !tenant.is_empty()
+        && tenant.len() <= MAX_LENGTH
+        && tenant
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Scope an entity ID to a tenant, so that it can only be addressed within that tenant
+///
+/// ```rust
+/// use event_driven_architecture::domains::tenants;
+///
+/// let id = tenants::scoped_id("acme", "01J73SBWHE373VXWZTF7SJADD9");
+///
+/// assert_eq!(id, "acme#01J73SBWHE373VXWZTF7SJADD9");
+/// assert_eq!(
+///     tenants::split_id(&id),
+///     Some(("acme", "01J73SBWHE373VXWZTF7SJADD9"))
+/// );
+/// ```
+pub fn scoped_id(tenant: &str, id: &str) -> String {
+    format!("{tenant}{SEPARATOR}{id}")
+}
+
+/// Split a tenant-scoped aggregate ID into the tenant and the entity ID
+pub fn split_id(scoped_id: &str) -> Option<(&str, &str)> {
+    scoped_id.split_once(SEPARATOR)
+}
@@ -0,0 +1,487 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use cqrs_es::persist::{EventUpcaster, SerializedEvent};
+use serde_json::Value;
+
+use super::{projects, tasks, DomainEvent};
+
+/// A function that transforms a stored event payload into the shape of the next version
+pub type UpcastFn = dyn Fn(Value) -> Value + Send + Sync;
+
+/// A function that transforms a stored event payload into the next version, returning the event
+/// type it becomes along with the new payload
+pub type RetypeFn = dyn Fn(Value) -> (String, Value) + Send + Sync;
+
+/// A single registered migration from one event version to the next
+struct Upcast {
+    to_version: String,
+    upcast: Box<RetypeFn>,
+}
+
+/// A registry of event upcasters keyed by `(event_type, event_version)`
+///
+/// Each entry migrates a stored payload from one version to the next, so older events are
+/// upcast step by step until no further migration is registered for the resulting version.
+///
+/// ```rust
+/// use event_driven_architecture::domains::upcasters::Upcasters;
+/// use serde_json::{json, Value};
+///
+/// let upcasters = Upcasters::default()
+///     .register("Task:Created", "1.0", "1.1", |mut payload: Value| {
+///         payload["priority"] = json!("normal");
+///         payload
+///     })
+///     .register("Task:Created", "1.1", "2.0", |mut payload: Value| {
+///         payload["title"] = payload["name"].take();
+///         payload
+///     });
+///
+/// let (version, payload) =
+///     upcasters.upcast_payload("Task:Created", "1.0", json!({ "name": "My Task" }));
+///
+/// assert_eq!(version, "2.0");
+/// assert_eq!(
+///     payload,
+///     json!({ "title": "My Task", "name": null, "priority": "normal" })
+/// );
+///
+/// // Events that are already current are left as-is
+/// let (version, payload) =
+///     upcasters.upcast_payload("Task:Created", "2.0", json!({ "title": "My Task" }));
+///
+/// assert_eq!(version, "2.0");
+/// assert_eq!(payload, json!({ "title": "My Task" }));
+/// ```
+#[derive(Default)]
+pub struct Upcasters {
+    upcasts: HashMap<(String, String), Upcast>,
+}
+
+impl Upcasters {
+    /// Register an upcast from `from_version` to `to_version` for the given event type
+    pub fn register<F>(self, event_type: &str, from_version: &str, to_version: &str, f: F) -> Self
+    where
+        F: Fn(Value) -> Value + Send + Sync + 'static,
+    {
+        let retyped = event_type.to_string();
+
+        self.register_retyped(event_type, from_version, to_version, move |payload| {
+            (retyped.clone(), f(payload))
+        })
+    }
+
+    /// Register an upcast that may also change the event type, for when one kind of event is
+    /// replaced by another in the next version
+    ///
+    /// Upcasting continues from the returned event type, so later versions of it are applied too.
+    pub fn register_retyped<F>(
+        mut self,
+        event_type: &str,
+        from_version: &str,
+        to_version: &str,
+        f: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> (String, Value) + Send + Sync + 'static,
+    {
+        self.upcasts.insert(
+            (event_type.to_string(), from_version.to_string()),
+            Upcast {
+                to_version: to_version.to_string(),
+                upcast: Box::new(f),
+            },
+        );
+
+        self
+    }
+
+    /// Upcast the payload through every registered version, returning the final version
+    pub fn upcast_payload(
+        &self,
+        event_type: &str,
+        event_version: &str,
+        payload: Value,
+    ) -> (String, Value) {
+        let (_, version, payload) = self.upcast_event(event_type, event_version, payload);
+
+        (version, payload)
+    }
+
+    /// Upcast an event through every registered version, returning the final event type and
+    /// version along with the payload
+    pub fn upcast_event(
+        &self,
+        event_type: &str,
+        event_version: &str,
+        payload: Value,
+    ) -> (String, String, Value) {
+        let mut event_type = event_type.to_string();
+        let mut version = event_version.to_string();
+        let mut payload = payload;
+        let mut seen = HashSet::new();
+
+        while let Some(step) = self.upcasts.get(&(event_type.clone(), version.clone())) {
+            // Guard against a misconfigured registry looping back to a version already visited
+            if !seen.insert((event_type.clone(), version.clone())) {
+                break;
+            }
+
+            (event_type, payload) = (step.upcast)(payload);
+            version.clone_from(&step.to_version);
+        }
+
+        (event_type, version, payload)
+    }
+
+    /// Upcast the payload of a published Domain Event before it is decoded by a projector
+    pub fn upcast_domain_event(
+        &self,
+        event: DomainEvent,
+    ) -> Result<DomainEvent, serde_json::Error> {
+        if !self.can_upcast(&event.event_type, &event.event_version) {
+            return Ok(event);
+        }
+
+        let payload: Value = serde_json::from_str(&event.payload)?;
+        let (event_type, event_version, payload) =
+            self.upcast_event(&event.event_type, &event.event_version, payload);
+
+        Ok(DomainEvent {
+            event_type,
+            event_version,
+            payload: serde_json::to_string(&payload)?,
+            ..event
+        })
+    }
+}
+
+impl EventUpcaster for Upcasters {
+    fn can_upcast(&self, event_type: &str, event_version: &str) -> bool {
+        self.upcasts
+            .contains_key(&(event_type.to_string(), event_version.to_string()))
+    }
+
+    fn upcast(&self, event: SerializedEvent) -> SerializedEvent {
+        let (event_type, event_version, payload) =
+            self.upcast_event(&event.event_type, &event.event_version, event.payload);
+
+        SerializedEvent {
+            event_type,
+            event_version,
+            payload,
+            ..event
+        }
+    }
+}
+
+impl fmt::Debug for Upcasters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(
+                self.upcasts
+                    .iter()
+                    .map(|((event_type, from), step)| (event_type, from, &step.to_version)),
+            )
+            .finish()
+    }
+}
+
+/// Build the upcaster registry for every aggregate type in the domain
+pub fn upcasters() -> Upcasters {
+    let upcasters = tasks::events::register_upcasters(Upcasters::default());
+
+    projects::events::register_upcasters(upcasters)
+}
+
+#[cfg(test)]
+mod tests {
+    use cqrs_es::{
+        persist::{EventUpcaster, SerializedEvent},
+        DomainEvent as _,
+    };
+    use serde_json::{json, Value};
+
+    use crate::domains::tasks::{self, Priority, Relation, Status};
+
+    const ID: &str = "task-1";
+    const AT: &str = "2024-01-02T03:04:05Z";
+
+    /// Replay a stored Task event through the domain's upcasters and decode it as the current
+    /// `Event`, returning the version it ended up at
+    fn replay(event_type: &str, event_version: &str, payload: Value) -> (String, tasks::Event) {
+        let upcasters = super::upcasters();
+
+        let stored = SerializedEvent::new(
+            ID.to_string(),
+            1,
+            tasks::AGGREGATE_TYPE.to_string(),
+            event_type.to_string(),
+            event_version.to_string(),
+            payload,
+            json!({}),
+        );
+
+        let event = if upcasters.can_upcast(event_type, event_version) {
+            upcasters.upcast(stored)
+        } else {
+            stored
+        };
+
+        assert!(
+            !upcasters.can_upcast(&event.event_type, &event.event_version),
+            "{event_type} {event_version} stopped at {} before the last upcast",
+            event.event_version,
+        );
+
+        let decoded: tasks::Event = serde_json::from_value(event.payload)
+            .unwrap_or_else(|err| panic!("{event_type} {event_version} doesn't decode: {err}"));
+
+        assert_eq!(decoded.event_type(), event.event_type);
+
+        (event.event_version, decoded)
+    }
+
+    fn created_1x(done: bool) -> Value {
+        json!({
+            "type": "Created",
+            "id": ID,
+            "created_at": AT,
+            "task": {
+                "id": ID,
+                "created_at": AT,
+                "updated_at": AT,
+                "name": "My Task",
+                "summary": null,
+                "done": done,
+                "deleted": false,
+            },
+        })
+    }
+
+    #[test]
+    fn created_with_done_flag_gets_a_status() {
+        for version in ["1.0", "1.1", "1.2", "1.3", "1.4", "1.5"] {
+            for (done, status) in [(true, Status::Done), (false, Status::Todo)] {
+                let (to_version, event) = replay("Task:Created", version, created_1x(done));
+
+                assert_eq!(to_version, "2.0");
+
+                let tasks::Event::Created { task, .. } = event else {
+                    panic!("{version} Created became {event:?}");
+                };
+
+                assert_eq!(task.status, status);
+                assert_eq!(task.name, "My Task");
+            }
+        }
+    }
+
+    #[test]
+    fn updated_with_only_done_becomes_a_lifecycle_event() {
+        for version in ["1.1", "1.2", "1.3", "1.4", "1.5"] {
+            for (done, event_type) in [(true, "Task:Completed"), (false, "Task:Reopened")] {
+                let payload = json!({
+                    "type": "Updated",
+                    "id": ID,
+                    "updated_at": AT,
+                    "update": { "name": null, "done": done },
+                });
+
+                let (to_version, event) = replay("Task:Updated", version, payload);
+
+                assert_eq!(to_version, "2.0");
+                assert_eq!(event.event_type(), event_type);
+                assert_eq!(event.id(), ID);
+            }
+        }
+    }
+
+    #[test]
+    fn updated_with_other_changes_keeps_the_status() {
+        let payload = json!({
+            "type": "Updated",
+            "id": ID,
+            "updated_at": AT,
+            "update": { "name": "Renamed", "summary": "A summary", "done": true },
+        });
+
+        for version in ["1.0", "1.1", "1.2", "1.3", "1.4", "1.5"] {
+            let (to_version, event) = replay("Task:Updated", version, payload.clone());
+
+            assert_eq!(to_version, "2.0");
+
+            let tasks::Event::Updated { update, status, .. } = event else {
+                panic!("{version} Updated became {event:?}");
+            };
+
+            assert_eq!(status, Some(Status::Done));
+            assert_eq!(update.name.as_deref(), Some("Renamed"));
+            assert_eq!(
+                update.summary.value().map(String::as_str),
+                Some("A summary")
+            );
+        }
+    }
+
+    #[test]
+    fn updated_without_done_is_unchanged() {
+        let payload = json!({
+            "type": "Updated",
+            "id": ID,
+            "updated_at": AT,
+            "update": { "name": "Renamed" },
+        });
+
+        let (_, event) = replay("Task:Updated", "1.1", payload);
+
+        let tasks::Event::Updated { update, status, .. } = event else {
+            panic!("Updated became {event:?}");
+        };
+
+        assert_eq!(status, None);
+        assert_eq!(update.name.as_deref(), Some("Renamed"));
+        assert!(update.summary.is_unchanged());
+    }
+
+    #[test]
+    fn later_events_are_read_as_is() {
+        let events = [
+            (
+                "Task:Deleted",
+                "1.0",
+                json!({ "type": "Deleted", "id": ID, "updated_at": AT }),
+            ),
+            (
+                "Task:Restored",
+                "1.1",
+                json!({ "type": "Restored", "id": ID, "updated_at": AT }),
+            ),
+            (
+                "Task:Erased",
+                "1.2",
+                json!({ "type": "Erased", "id": ID, "updated_at": AT }),
+            ),
+            (
+                "Task:Completed",
+                "2.0",
+                json!({ "type": "Completed", "id": ID, "updated_at": AT }),
+            ),
+            (
+                "Task:Moved",
+                "2.1",
+                json!({
+                    "type": "Moved",
+                    "id": ID,
+                    "from_project_id": null,
+                    "project_id": "project-1",
+                    "updated_at": AT,
+                }),
+            ),
+            (
+                "Task:Linked",
+                "2.2",
+                json!({
+                    "type": "Linked",
+                    "id": ID,
+                    "relation": "BlockedBy",
+                    "task_id": "task-2",
+                    "updated_at": AT,
+                }),
+            ),
+            (
+                "Task:CommentDeleted",
+                "2.3",
+                json!({
+                    "type": "CommentDeleted",
+                    "id": ID,
+                    "comment_id": "comment-1",
+                    "updated_at": AT,
+                }),
+            ),
+            (
+                "Task:PriorityChanged",
+                "2.4",
+                json!({ "type": "PriorityChanged", "id": ID, "priority": "High", "updated_at": AT }),
+            ),
+            (
+                "Task:Assigned",
+                "2.5",
+                json!({
+                    "type": "Assigned",
+                    "id": ID,
+                    "assignee": "user-2",
+                    "assigned_by": "user-1",
+                    "updated_at": AT,
+                }),
+            ),
+        ];
+
+        for (event_type, version, payload) in events {
+            let (to_version, event) = replay(event_type, version, payload.clone());
+
+            assert_eq!(to_version, version);
+            assert_eq!(serde_json::to_value(&event).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn created_before_later_fields_gets_their_defaults() {
+        let payload = json!({
+            "type": "Created",
+            "id": ID,
+            "created_at": AT,
+            "task": {
+                "id": ID,
+                "created_at": AT,
+                "updated_at": AT,
+                "owner": "user-1",
+                "name": "My Task",
+                "summary": "A summary",
+                "status": "InProgress",
+                "deleted": false,
+            },
+        });
+
+        let (to_version, event) = replay("Task:Created", "2.0", payload);
+
+        assert_eq!(to_version, "2.0");
+
+        let tasks::Event::Created { task, .. } = event else {
+            panic!("Created became {event:?}");
+        };
+
+        assert_eq!(task.status, Status::InProgress);
+        assert_eq!(task.priority, Priority::default());
+        assert!(task.labels.is_empty());
+        assert!(task.assignees.is_empty());
+        assert!(task.blocked_by.is_empty());
+        assert_eq!(task.recurrence, None);
+        assert_eq!(task.project_id, None);
+    }
+
+    #[test]
+    fn current_events_are_left_alone() {
+        let payload = json!({
+            "type": "Linked",
+            "id": ID,
+            "relation": "Parent",
+            "task_id": "task-2",
+            "updated_at": AT,
+        });
+
+        let (to_version, event) = replay("Task:Linked", tasks::events::EVENT_VERSION, payload);
+
+        assert_eq!(to_version, tasks::events::EVENT_VERSION);
+        assert!(matches!(
+            event,
+            tasks::Event::Linked {
+                relation: Relation::Parent,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+use std::{sync::Arc, time::Duration};
+
+use aws_sdk_dynamodb::{
+    error::DisplayErrorContext,
+    types::{KeyType, TableDescription, TableStatus},
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+use tokio::time::Instant;
+
+use event_driven_architecture::config::Config;
+
+use crate::AppState;
+
+/// How long a dependency has to respond before it's reported as unavailable
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A DynamoDB table the API can't serve requests without, and the key schema it expects
+#[derive(Clone, Debug)]
+struct Dependency {
+    name: &'static str,
+    table: String,
+    keys: &'static [(&'static str, KeyType)],
+}
+
+/// Checks that the API's DynamoDB tables are reachable and keyed the way the API expects
+pub struct Checks {
+    client: aws_sdk_dynamodb::Client,
+    dependencies: Vec<Dependency>,
+}
+
+impl Checks {
+    /// Check the event log, snapshot, and tasks view tables from the config
+    pub fn init(client: aws_sdk_dynamodb::Client, config: &Config) -> Arc<Self> {
+        let tables = &config.tables;
+
+        let dependencies = vec![
+            Dependency {
+                name: "event_log",
+                table: tables.event_log.clone(),
+                keys: &[
+                    ("AggregateTypeAndId", KeyType::Hash),
+                    ("AggregateIdSequence", KeyType::Range),
+                ],
+            },
+            Dependency {
+                name: "event_snapshots",
+                table: tables.event_snapshots.clone(),
+                keys: &[("AggregateTypeAndId", KeyType::Hash)],
+            },
+            Dependency {
+                name: "tasks_view",
+                table: tables.tasks_view.clone(),
+                keys: &[("ViewId", KeyType::Hash)],
+            },
+        ];
+
+        Arc::new(Self {
+            client,
+            dependencies,
+        })
+    }
+
+    /// Check every dependency at once, returning their statuses in order
+    async fn run(&self) -> Vec<DependencyStatus> {
+        let handles: Vec<_> = self
+            .dependencies
+            .iter()
+            .cloned()
+            .map(|dependency| tokio::spawn(check(self.client.clone(), dependency)))
+            .collect();
+
+        let mut statuses = Vec::with_capacity(handles.len());
+
+        for (handle, dependency) in handles.into_iter().zip(&self.dependencies) {
+            statuses.push(handle.await.unwrap_or_else(|error| DependencyStatus {
+                name: dependency.name,
+                table: dependency.table.clone(),
+                status: Status::Unavailable,
+                latency_ms: 0,
+                error: Some(format!("The check failed to run: {error}")),
+            }));
+        }
+
+        statuses
+    }
+}
+
+/// Whether the API or one of its dependencies is usable
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Usable
+    Ok,
+
+    /// Unreachable, or not set up the way the API expects
+    Unavailable,
+}
+
+/// The result of checking a single dependency
+#[derive(Debug, Serialize)]
+pub struct DependencyStatus {
+    /// The dependency's name
+    pub name: &'static str,
+
+    /// The DynamoDB table checked
+    pub table: String,
+
+    /// Whether the table is usable
+    pub status: Status,
+
+    /// How long the check took
+    pub latency_ms: u128,
+
+    /// Why the table isn't usable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The body of the health and readiness responses
+#[derive(Debug, Serialize)]
+pub struct Health {
+    /// Ok if every dependency is
+    pub status: Status,
+
+    /// Each dependency's status, left out of the liveness check
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+/// Describe a table, and make sure it's active and has the expected key schema
+async fn check(client: aws_sdk_dynamodb::Client, dependency: Dependency) -> DependencyStatus {
+    let started = Instant::now();
+
+    let result = tokio::time::timeout(
+        CHECK_TIMEOUT,
+        client.describe_table().table_name(&dependency.table).send(),
+    )
+    .await;
+
+    let error = match result {
+        Err(_) => Some(format!(
+            "No response within {}ms",
+            CHECK_TIMEOUT.as_millis()
+        )),
+        Ok(Err(error)) => Some(DisplayErrorContext(error).to_string()),
+        Ok(Ok(output)) => match output.table {
+            Some(table) => problem(&table, dependency.keys),
+            None => Some("The table wasn't described".to_string()),
+        },
+    };
+
+    let latency_ms = started.elapsed().as_millis();
+
+    if let Some(error) = &error {
+        tracing::warn!(
+            dependency = dependency.name,
+            table = dependency.table,
+            error = error,
+            "Dependency is unavailable"
+        );
+    }
+
+    DependencyStatus {
+        name: dependency.name,
+        table: dependency.table,
+        status: if error.is_none() {
+            Status::Ok
+        } else {
+            Status::Unavailable
+        },
+        latency_ms,
+        error,
+    }
+}
+
+/// What's wrong with a table, if it isn't active or isn't keyed as expected
+fn problem(table: &TableDescription, expected: &[(&str, KeyType)]) -> Option<String> {
+    if let Some(status) = table.table_status() {
+        if *status != TableStatus::Active && *status != TableStatus::Updating {
+            return Some(format!("The table is {}", status.as_str()));
+        }
+    }
+
+    let actual = table.key_schema();
+
+    let matches = actual.len() == expected.len()
+        && expected.iter().all(|(name, kind)| {
+            actual
+                .iter()
+                .any(|key| key.attribute_name() == *name && key.key_type() == kind)
+        });
+
+    if matches {
+        return None;
+    }
+
+    let describe = |keys: Vec<(&str, &KeyType)>| {
+        keys.iter()
+            .map(|(name, kind)| format!("{name} ({})", kind.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    Some(format!(
+        "Expected the key schema [{}], found [{}]",
+        describe(expected.iter().map(|(name, kind)| (*name, kind)).collect()),
+        describe(
+            actual
+                .iter()
+                .map(|key| (key.attribute_name(), key.key_type()))
+                .collect()
+        ),
+    ))
+}
+
+/// Liveness, which only reports that the process is up and serving requests
+pub async fn healthz() -> impl IntoResponse {
+    Json(Health {
+        status: Status::Ok,
+        dependencies: Vec::new(),
+    })
+}
+
+/// Readiness, which is only ok while every DynamoDB table the API needs is usable. Responds with
+/// 503 Service Unavailable otherwise, so load balancers stop routing to this instance.
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let dependencies = state.health.run().await;
+
+    let status = if dependencies
+        .iter()
+        .all(|dependency| dependency.status == Status::Ok)
+    {
+        Status::Ok
+    } else {
+        Status::Unavailable
+    };
+
+    let code = match status {
+        Status::Ok => StatusCode::OK,
+        Status::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (
+        code,
+        Json(Health {
+            status,
+            dependencies,
+        }),
+    )
+}
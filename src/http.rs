@@ -1,52 +1,265 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
+use aide::{axum::IntoApiResponse, openapi::OpenApi, transform::TransformOperation};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
+use cqrs_es::AggregateError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use ulid::Ulid;
 
-use crate::{domains::tasks, AppState};
+use event_driven_architecture::{
+    domains::{self, projects, schemas, tasks, tenants},
+    utils::{metrics, telemetry::TracedExecute},
+};
+
+use crate::{auth::Identity, correlation::Correlation, AppState};
+
+/// Path parameters for routes that address a single Task
+#[derive(Deserialize, JsonSchema)]
+pub struct TaskPath {
+    /// The Task ID
+    pub id: String,
+}
+
+/// Path parameters for routes that address a single comment on a Task
+#[derive(Deserialize, JsonSchema)]
+pub struct CommentPath {
+    /// The Task ID
+    pub id: String,
+
+    /// The comment ID
+    pub comment_id: String,
+}
+
+/// Query parameters for paging through a Task's activity feed
+#[derive(Deserialize, JsonSchema)]
+pub struct ActivityParams {
+    /// Return entries older than this sequence number, from the `next` field of the last page
+    pub before: Option<usize>,
+
+    /// The most entries to return, up to 100 (defaults to 50)
+    pub limit: Option<i32>,
+}
+
+/// Query parameters for listing Tasks
+#[derive(Deserialize, JsonSchema)]
+pub struct TaskListParams {
+    /// Only include Tasks with all of these comma-separated labels
+    pub labels: Option<String>,
+
+    /// Only include Tasks with this priority
+    pub priority: Option<tasks::Priority>,
+
+    /// Only include Tasks in this Project
+    pub project_id: Option<String>,
+
+    /// Return Tasks after this cursor, from the `next` field of the last page
+    pub after: Option<String>,
+
+    /// The most Tasks to return, up to 100 (defaults to 50)
+    pub limit: Option<i32>,
+}
+
+/// The response to adding a comment
+#[derive(Serialize, JsonSchema)]
+pub struct CommentCreated {
+    /// The ID of the new comment
+    pub comment_id: String,
+}
+
+/// Path parameters for routes that address a single user
+#[derive(Deserialize, JsonSchema)]
+pub struct UserPath {
+    /// The user's subject
+    pub id: String,
+}
+
+/// Query parameters for listing a user's assigned Tasks
+#[derive(Deserialize, JsonSchema)]
+pub struct InboxParams {
+    /// Return Tasks after this cursor, from the `next` field of the last page
+    pub after: Option<String>,
+
+    /// The most Tasks to return, up to 100 (defaults to 50)
+    pub limit: Option<i32>,
+}
+
+/// Path parameters for routes that address a single Project
+#[derive(Deserialize, JsonSchema)]
+pub struct ProjectPath {
+    /// The Project ID
+    pub id: String,
+}
+
+/// Path parameters for routes that address a single event schema
+#[derive(Deserialize, JsonSchema)]
+pub struct SchemaPath {
+    /// The event type, such as "Task:Created"
+    pub event_type: String,
+
+    /// The event version
+    pub version: String,
+}
+
+pub async fn openapi(Extension(api): Extension<Arc<OpenApi>>) -> impl IntoApiResponse {
+    Json(api.as_ref().clone())
+}
+
+/// The metrics recorded by this server, in the Prometheus text exposition format
+pub async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+}
+
+/// An embedded API reference that renders the OpenAPI document served alongside it
+///
+/// Its script and styles are compiled into the server, so nothing is loaded from a CDN at
+/// runtime.
+#[cfg(feature = "api-docs")]
+pub fn api_docs<S>() -> aide::axum::routing::ApiMethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    aide::redoc::Redoc::new("openapi.json")
+        .with_title("Event Driven Architecture API")
+        .axum_route()
+}
+
+pub async fn schemas_list() -> impl IntoApiResponse {
+    Json(schemas::registry())
+}
+
+pub fn schemas_list_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List the current schema for every published event type")
+        .tag("Schemas")
+        .response::<200, Json<Vec<schemas::EventSchema>>>()
+}
+
+pub async fn schemas_envelope() -> impl IntoApiResponse {
+    Json(schemas::envelope())
+}
+
+pub fn schemas_envelope_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get the schema for the DomainEvent envelope")
+        .tag("Schemas")
+        .response::<200, Json<serde_json::Value>>()
+}
+
+pub async fn schemas_get(
+    Path(SchemaPath {
+        event_type,
+        version,
+    }): Path<SchemaPath>,
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
+    // Current schemas that haven't been generated into `schemas/` yet are served too
+    schemas::published()
+        .into_iter()
+        .chain(schemas::registry())
+        .find(|schema| schema.event_type == event_type && schema.event_version == version)
+        .map(|schema| Json(schema.schema))
+        .ok_or((StatusCode::NOT_FOUND, "Schema not found".to_string()))
+}
+
+pub fn schemas_get_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get the schema for an event type at a specific version")
+        .tag("Schemas")
+        .response::<200, Json<serde_json::Value>>()
+        .response_with::<404, String, _>(|res| res.description("Schema not found"))
+}
 
 pub async fn tasks_get(
-    Path(id): Path<String>,
+    Path(TaskPath { id }): Path<TaskPath>,
+    Identity { subject, tenant }: Identity,
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
     let task = state
         .tasks_repo
-        .load(&id)
+        .load(&tenants::scoped_id(&tenant, &id))
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     if let Some(task) = task {
+        authorize_read(&state, &subject, &task)?;
+
         return Ok(Json(task));
     }
 
     Err((StatusCode::NOT_FOUND, "Task not found".to_string()))
 }
 
+pub fn tasks_get_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get a Task")
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner or an assignee"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+}
+
+pub async fn tasks_list(
+    Query(params): Query<TaskListParams>,
+    Identity { subject, tenant }: Identity,
+    State(state): State<AppState>,
+) -> Result<Json<tasks::index::TaskPage>, (StatusCode, String)> {
+    let filter = tasks::index::Filter {
+        labels: tasks::labels::normalize(params.labels.unwrap_or_default().split(',')),
+        priority: params.priority,
+        project_id: params.project_id,
+    };
+
+    let page = state
+        .tasks_index
+        .search(
+            &tenant,
+            &subject,
+            &filter,
+            params.after,
+            params.limit.unwrap_or(50),
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(page))
+}
+
+pub fn tasks_list_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List your Tasks")
+        .description(
+            "Lists the Tasks you own, optionally filtered by Project, labels, and priority. Pass the \
+             `next` value from one page as `after` to fetch the following one.",
+        )
+        .tag("Tasks")
+        .response::<200, Json<tasks::index::TaskPage>>()
+}
+
 pub async fn tasks_create(
+    identity: Identity,
+    correlation: Correlation,
     State(state): State<AppState>,
     Json(input): Json<tasks::inputs::Create>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
-    let command_id = Ulid::new().to_string();
-    let aggregate_id = Ulid::new().to_string();
-
-    let mut metadata = HashMap::<String, String>::new();
-    metadata.insert("command_id".to_string(), command_id);
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
+    let id = Ulid::new().to_string();
+    let aggregate_id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
 
     let command = tasks::Command::Create {
-        id: aggregate_id.clone(),
+        id,
+        owner: subject,
         input,
     };
 
     state
         .tasks_cqrs
-        .execute_with_metadata(&aggregate_id, command, metadata)
+        .execute_traced(&aggregate_id, command, metadata)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(command_error)?;
 
     // Now that the command is committed, retrieve the result from the view
     let task = state
@@ -65,23 +278,30 @@ pub async fn tasks_create(
     ))
 }
 
+pub fn tasks_create_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Create a Task")
+        .tag("Tasks")
+        .response::<201, Json<tasks::View>>()
+}
+
 pub async fn tasks_update(
-    Path(id): Path<String>,
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
     State(state): State<AppState>,
     Json(input): Json<tasks::inputs::Update>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
-    let command_id = Ulid::new().to_string();
-
-    let mut metadata = HashMap::<String, String>::new();
-    metadata.insert("command_id".to_string(), command_id);
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
 
-    let command = tasks::Command::Update(input);
+    let command = tasks::Command::Update { subject, input };
 
     state
         .tasks_cqrs
-        .execute_with_metadata(&id, command, metadata)
+        .execute_traced(&id, command, metadata)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(command_error)?;
 
     // Now that the command is committed, retrieve the result from the view
     let task = state
@@ -100,22 +320,845 @@ pub async fn tasks_update(
     ))
 }
 
+pub fn tasks_update_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Update a Task")
+        .description(
+            "Partially update a Task. Omitted fields are left unchanged. Fields that can be \
+             cleared, like `summary`, are cleared by setting them to `null`.",
+        )
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+}
+
 pub async fn tasks_delete(
-    Path(id): Path<String>,
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
     State(state): State<AppState>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-    let command_id = Ulid::new().to_string();
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
 
-    let mut metadata = HashMap::<String, String>::new();
-    metadata.insert("command_id".to_string(), command_id);
+    let command = tasks::Command::Delete { subject };
+
+    state
+        .tasks_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    Ok((StatusCode::OK, "Task deleted".to_string()))
+}
+
+pub fn tasks_delete_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Delete a Task")
+        .tag("Tasks")
+        .response_with::<200, String, _>(|res| res.description("Task deleted"))
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+}
 
-    let command = tasks::Command::Delete;
+pub async fn tasks_restore(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
+
+    let command = tasks::Command::Restore { subject };
 
     state
         .tasks_cqrs
-        .execute_with_metadata(&id, command, metadata)
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    // Now that the command is committed, retrieve the result from the view
+    let task = state
+        .tasks_repo
+        .load(&id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok((StatusCode::OK, "Task deleted".to_string()))
+    if let Some(task) = task {
+        return Ok(Json(task));
+    }
+
+    Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Task was not found after restore".to_string(),
+    ))
+}
+
+pub fn tasks_restore_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Restore a deleted Task")
+        .description("Deleted Tasks can be restored by their owner within a grace period.")
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+        .response_with::<409, String, _>(|res| {
+            res.description("The Task isn't deleted, or the grace period has expired")
+        })
+}
+
+pub async fn tasks_erase(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
+
+    let command = tasks::Command::Erase { subject };
+
+    state
+        .tasks_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    // Now that the command is committed, retrieve the result from the view
+    let task = state
+        .tasks_repo
+        .load(&id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(task) = task {
+        return Ok(Json(task));
+    }
+
+    Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Task was not found after erasure".to_string(),
+    ))
+}
+
+pub fn tasks_erase_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Permanently erase a Task's personal data")
+        .description(
+            "Destroys the key that protects the Task's name and summary, so they are redacted \
+             everywhere they were recorded. The Task is also deleted, and can't be restored.",
+        )
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+        .response_with::<409, String, _>(|res| res.description("The Task was already erased"))
+}
+
+pub async fn tasks_start(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Start {
+        subject: identity.subject.clone(),
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_start_docs(op: TransformOperation) -> TransformOperation {
+    transition_docs(
+        op.summary("Start work on a Task")
+            .description("Moves a `Todo` or `Blocked` Task to `InProgress`."),
+        "Not the Task owner or an assignee",
+    )
+}
+
+pub async fn tasks_block(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Block>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Block {
+        subject: identity.subject.clone(),
+        input,
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_block_docs(op: TransformOperation) -> TransformOperation {
+    transition_docs(
+        op.summary("Mark a Task as blocked").description(
+            "Moves a `Todo` or `InProgress` Task to `Blocked`, with an optional reason.",
+        ),
+        "Not the Task owner or an assignee",
+    )
+}
+
+pub async fn tasks_complete(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Complete {
+        subject: identity.subject.clone(),
+        tenant: identity.tenant.clone(),
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_complete_docs(op: TransformOperation) -> TransformOperation {
+    transition_docs(
+        op.summary("Complete a Task").description(
+            "Moves a `Todo` or `InProgress` Task to `Done`. Tasks with open blockers can't be \
+             completed.",
+        ),
+        "Not the Task owner or an assignee",
+    )
+}
+
+pub async fn tasks_reopen(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Reopen {
+        subject: identity.subject.clone(),
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_reopen_docs(op: TransformOperation) -> TransformOperation {
+    transition_docs(
+        op.summary("Reopen a Task")
+            .description("Moves a `Done` or `Archived` Task back to `Todo`."),
+        "Not the Task owner",
+    )
+}
+
+pub async fn tasks_archive(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Archive {
+        subject: identity.subject.clone(),
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_archive_docs(op: TransformOperation) -> TransformOperation {
+    transition_docs(
+        op.summary("Archive a Task").description(
+            "Moves a `Todo`, `InProgress`, `Blocked`, or `Done` Task to `Archived`. Archived \
+             Tasks can't be updated until they're reopened.",
+        ),
+        "Not the Task owner",
+    )
+}
+
+pub async fn tasks_move(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Move>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Move {
+        subject: identity.subject.clone(),
+        tenant: identity.tenant.clone(),
+        input,
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_move_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Move a Task into a Project")
+        .description(
+            "Moves a Task into a Project owned by the same subject, or out of its current one \
+             when `project_id` is `null`. Tasks can't be added to an archived Project.",
+        )
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| {
+            res.description("Not the owner of the Task or the Project")
+        })
+        .response_with::<404, String, _>(|res| res.description("Task or Project not found"))
+        .response_with::<409, String, _>(|res| {
+            res.description("The Project is archived, or the Task is already there")
+        })
+}
+
+pub async fn tasks_link(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Link>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Link {
+        subject: identity.subject.clone(),
+        tenant: identity.tenant.clone(),
+        input,
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_link_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Link a Task to its parent or a blocking Task")
+        .description(
+            "A `Parent` link makes the Task a subtask of the other one, which lists it in its \
+             `children`. A `BlockedBy` link keeps the Task from being completed until the other \
+             one is done. Links that would create a cycle are rejected.",
+        )
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the owner of both Tasks"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+        .response_with::<409, String, _>(|res| {
+            res.description("The link already exists, or would create a cycle")
+        })
+}
+
+pub async fn tasks_unlink(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Link>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Unlink {
+        subject: identity.subject.clone(),
+        input,
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_unlink_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Remove a link to a parent or blocking Task")
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+        .response_with::<409, String, _>(|res| res.description("The link doesn't exist"))
+}
+
+pub async fn tasks_assign(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Assignment>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Assign {
+        subject: identity.subject.clone(),
+        input,
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_assign_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Assign a Task to a user")
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+        .response_with::<409, String, _>(|res| {
+            res.description("The Task is already assigned to them")
+        })
+}
+
+pub async fn tasks_unassign(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Assignment>,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let command = tasks::Command::Unassign {
+        subject: identity.subject.clone(),
+        input,
+    };
+
+    transition(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn tasks_unassign_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Unassign a user from a Task")
+        .tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+        .response_with::<409, String, _>(|res| res.description("The Task isn't assigned to them"))
+}
+
+pub async fn users_tasks(
+    Path(UserPath { id }): Path<UserPath>,
+    Query(InboxParams { after, limit }): Query<InboxParams>,
+    Identity { subject, tenant }: Identity,
+    State(state): State<AppState>,
+) -> Result<Json<tasks::inbox::InboxPage>, (StatusCode, String)> {
+    if id != subject {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only the user can list their own Tasks".to_string(),
+        ));
+    }
+
+    let page = state
+        .tasks_inbox
+        .page(&tenant, &id, after, limit.unwrap_or(50))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(page))
+}
+
+pub fn users_tasks_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List the Tasks assigned to a user")
+        .description(
+            "The user's inbox, kept up to date from the event stream, so a new assignment can \
+             take a moment to appear. Pass the `next` value from one page as `after` to fetch \
+             the following one.",
+        )
+        .tag("Users")
+        .response::<200, Json<tasks::inbox::InboxPage>>()
+        .response_with::<403, String, _>(|res| res.description("Not the user"))
+}
+
+pub async fn tasks_activity(
+    Path(TaskPath { id }): Path<TaskPath>,
+    Query(ActivityParams { before, limit }): Query<ActivityParams>,
+    Identity { subject, tenant }: Identity,
+    State(state): State<AppState>,
+) -> Result<Json<tasks::activity::ActivityPage>, (StatusCode, String)> {
+    let id = tenants::scoped_id(&tenant, &id);
+
+    let task = state
+        .tasks_repo
+        .load(&id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Task not found".to_string()))?;
+
+    authorize_read(&state, &subject, &task)?;
+
+    let page = state
+        .tasks_activity
+        .page(&id, before, limit.unwrap_or(50))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(page))
+}
+
+pub fn tasks_activity_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("List a Task's activity")
+        .description(
+            "Every change to the Task and its comments, newest first. Pass the `next` value from \
+             one page as `before` to fetch the following one.",
+        )
+        .tag("Tasks")
+        .response::<200, Json<tasks::activity::ActivityPage>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner or an assignee"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+}
+
+pub async fn tasks_comments_create(
+    Path(TaskPath { id }): Path<TaskPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Comment>,
+) -> Result<(StatusCode, Json<CommentCreated>), (StatusCode, String)> {
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
+
+    let comment_id = Ulid::new().to_string();
+    let command = tasks::Command::AddComment {
+        subject,
+        comment_id: comment_id.clone(),
+        input,
+    };
+
+    state
+        .tasks_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    Ok((StatusCode::CREATED, Json(CommentCreated { comment_id })))
+}
+
+pub fn tasks_comments_create_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Comment on a Task")
+        .tag("Tasks")
+        .response::<201, Json<CommentCreated>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Task owner or an assignee"))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+}
+
+pub async fn tasks_comments_update(
+    Path(CommentPath { id, comment_id }): Path<CommentPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<tasks::inputs::Comment>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
+
+    let command = tasks::Command::EditComment {
+        subject,
+        comment_id,
+        input,
+    };
+
+    state
+        .tasks_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    Ok((StatusCode::OK, "Comment updated".to_string()))
+}
+
+pub fn tasks_comments_update_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Edit a comment on a Task")
+        .tag("Tasks")
+        .response_with::<200, String, _>(|res| res.description("Comment updated"))
+        .response_with::<403, String, _>(|res| res.description("Not the comment author"))
+        .response_with::<404, String, _>(|res| res.description("Task or comment not found"))
+}
+
+pub async fn tasks_comments_delete(
+    Path(CommentPath { id, comment_id }): Path<CommentPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
+
+    let command = tasks::Command::DeleteComment {
+        subject,
+        comment_id,
+    };
+
+    state
+        .tasks_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    Ok((StatusCode::OK, "Comment deleted".to_string()))
+}
+
+pub fn tasks_comments_delete_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Delete a comment on a Task")
+        .description("The comment's text is also removed from the activity feed.")
+        .tag("Tasks")
+        .response_with::<200, String, _>(|res| res.description("Comment deleted"))
+        .response_with::<403, String, _>(|res| res.description("Not the comment author"))
+        .response_with::<404, String, _>(|res| res.description("Task or comment not found"))
+}
+
+/// Execute a command against an existing Task and return its updated view
+async fn transition(
+    state: &AppState,
+    identity: &Identity,
+    correlation: &Correlation,
+    id: &str,
+    command: tasks::Command,
+) -> Result<Json<tasks::View>, (StatusCode, String)> {
+    let id = tenants::scoped_id(&identity.tenant, id);
+    let metadata = command_metadata(identity, correlation);
+
+    state
+        .tasks_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    // Now that the command is committed, retrieve the result from the view
+    let task = state
+        .tasks_repo
+        .load(&id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    task.map(Json).ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Task was not found after a status change".to_string(),
+    ))
+}
+
+/// The responses shared by every lifecycle route
+fn transition_docs<'t>(op: TransformOperation<'t>, forbidden: &str) -> TransformOperation<'t> {
+    op.tag("Tasks")
+        .response::<200, Json<tasks::View>>()
+        .response_with::<403, String, _>(|res| res.description(forbidden))
+        .response_with::<404, String, _>(|res| res.description("Task not found"))
+        .response_with::<409, String, _>(|res| {
+            res.description("The Task can't move to that status from its current one")
+        })
+}
+
+pub async fn projects_get(
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    Identity { subject, tenant }: Identity,
+    State(state): State<AppState>,
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
+    let project = state
+        .projects_repo
+        .load(&tenants::scoped_id(&tenant, &id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(project) = project {
+        project.project.authorize(&subject).map_err(domain_error)?;
+
+        return Ok(Json(project));
+    }
+
+    Err((StatusCode::NOT_FOUND, "Project not found".to_string()))
+}
+
+pub fn projects_get_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Get a Project")
+        .tag("Projects")
+        .response::<200, Json<projects::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Project owner"))
+        .response_with::<404, String, _>(|res| res.description("Project not found"))
+}
+
+pub async fn projects_create(
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<projects::inputs::Create>,
+) -> Result<impl IntoApiResponse, impl IntoApiResponse> {
+    let id = Ulid::new().to_string();
+    let command = projects::Command::Create {
+        id: id.clone(),
+        owner: identity.subject.clone(),
+        input,
+    };
+
+    let project = project_command(&state, &identity, &correlation, &id, command).await?;
+
+    Ok::<_, (StatusCode, String)>((StatusCode::CREATED, project))
+}
+
+pub fn projects_create_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Create a Project")
+        .tag("Projects")
+        .response::<201, Json<projects::View>>()
+}
+
+pub async fn projects_update(
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+    Json(input): Json<projects::inputs::Update>,
+) -> Result<Json<projects::View>, (StatusCode, String)> {
+    let command = projects::Command::Update {
+        subject: identity.subject.clone(),
+        input,
+    };
+
+    project_command(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn projects_update_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Update a Project")
+        .description(
+            "Partially update a Project. Omitted fields are left unchanged, and `description` is \
+             cleared by setting it to `null`.",
+        )
+        .tag("Projects")
+        .response::<200, Json<projects::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Project owner"))
+        .response_with::<404, String, _>(|res| res.description("Project not found"))
+}
+
+pub async fn projects_delete(
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let id = tenants::scoped_id(&identity.tenant, &id);
+    let metadata = command_metadata(&identity, &correlation);
+    let Identity { subject, .. } = identity;
+
+    let command = projects::Command::Delete { subject };
+
+    state
+        .projects_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    Ok((StatusCode::OK, "Project deleted".to_string()))
+}
+
+pub fn projects_delete_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Delete a Project")
+        .description("Tasks already in the Project keep their `project_id`, and can be moved out.")
+        .tag("Projects")
+        .response_with::<200, String, _>(|res| res.description("Project deleted"))
+        .response_with::<403, String, _>(|res| res.description("Not the Project owner"))
+        .response_with::<404, String, _>(|res| res.description("Project not found"))
+}
+
+pub async fn projects_archive(
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<Json<projects::View>, (StatusCode, String)> {
+    let command = projects::Command::Archive {
+        subject: identity.subject.clone(),
+    };
+
+    project_command(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn projects_archive_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Archive a Project")
+        .description("Archived Projects keep their Tasks, but no new Tasks can be moved into them.")
+        .tag("Projects")
+        .response::<200, Json<projects::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Project owner"))
+        .response_with::<404, String, _>(|res| res.description("Project not found"))
+        .response_with::<409, String, _>(|res| res.description("The Project is already archived"))
+}
+
+pub async fn projects_unarchive(
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    identity: Identity,
+    correlation: Correlation,
+    State(state): State<AppState>,
+) -> Result<Json<projects::View>, (StatusCode, String)> {
+    let command = projects::Command::Unarchive {
+        subject: identity.subject.clone(),
+    };
+
+    project_command(&state, &identity, &correlation, &id, command).await
+}
+
+pub fn projects_unarchive_docs(op: TransformOperation) -> TransformOperation {
+    op.summary("Unarchive a Project")
+        .tag("Projects")
+        .response::<200, Json<projects::View>>()
+        .response_with::<403, String, _>(|res| res.description("Not the Project owner"))
+        .response_with::<404, String, _>(|res| res.description("Project not found"))
+        .response_with::<409, String, _>(|res| res.description("The Project isn't archived"))
+}
+
+/// Execute a command against a Project and return its updated view
+async fn project_command(
+    state: &AppState,
+    identity: &Identity,
+    correlation: &Correlation,
+    id: &str,
+    command: projects::Command,
+) -> Result<Json<projects::View>, (StatusCode, String)> {
+    let id = tenants::scoped_id(&identity.tenant, id);
+    let metadata = command_metadata(identity, correlation);
+
+    state
+        .projects_cqrs
+        .execute_traced(&id, command, metadata)
+        .await
+        .map_err(command_error)?;
+
+    // Now that the command is committed, retrieve the result from the view
+    let project = state
+        .projects_repo
+        .load(&id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    project.map(Json).ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Project was not found after the change".to_string(),
+    ))
+}
+
+/// Build the metadata recorded with every command, identifying the command, its author, the
+/// tenant it applies to, and the request it came from
+fn command_metadata(identity: &Identity, correlation: &Correlation) -> HashMap<String, String> {
+    let mut metadata = HashMap::<String, String>::new();
+    metadata.insert("command_id".to_string(), Ulid::new().to_string());
+    metadata.insert("subject".to_string(), identity.subject.clone());
+    metadata.insert("tenant".to_string(), identity.tenant.clone());
+    metadata.insert(
+        "correlation_id".to_string(),
+        correlation.correlation_id.clone(),
+    );
+    metadata.insert("causation_id".to_string(), correlation.causation_id.clone());
+
+    metadata
+}
+
+/// Check that a subject may read a Task. Assignees can read the Tasks in their inbox, along with
+/// their activity, but only the subjects the authorizer allows can change them.
+fn authorize_read(
+    state: &AppState,
+    subject: &str,
+    task: &tasks::View,
+) -> Result<(), (StatusCode, String)> {
+    if task.task.assignees.contains(subject) {
+        return Ok(());
+    }
+
+    state
+        .tasks_authorizer
+        .authorize(subject, &task.task)
+        .map_err(domain_error)
+}
+
+/// Map a failed command to a response, using the status for domain errors
+fn command_error(err: AggregateError<domains::Error>) -> (StatusCode, String) {
+    match err {
+        AggregateError::UserError(err) => domain_error(err),
+        AggregateError::AggregateConflict => (StatusCode::CONFLICT, err.to_string()),
+        err => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
+
+fn domain_error(err: domains::Error) -> (StatusCode, String) {
+    let status = match err {
+        domains::Error::NotFound { .. } => StatusCode::NOT_FOUND,
+        domains::Error::Forbidden => StatusCode::FORBIDDEN,
+        domains::Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        domains::Error::InvalidState { .. } | domains::Error::Uniqueness { .. } => {
+            StatusCode::CONFLICT
+        }
+        domains::Error::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, err.to_string())
 }
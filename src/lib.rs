@@ -1,5 +1,8 @@
 //! A demo project for a simple CQRS/ES workflow
 
+/// Typed application configuration
+pub mod config;
+
 /// Event domains
 pub mod domains;
 
@@ -9,6 +12,15 @@ pub mod publishers;
 /// Event projectors
 pub mod projectors;
 
+/// Process managers that issue commands in response to events
+pub mod process_managers;
+
+/// Time-based command schedulers
+pub mod schedulers;
+
+/// Local runners that feed stream records to handlers without Lambda
+pub mod runners;
+
 /// Utils
 pub mod utils;
 
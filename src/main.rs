@@ -1,42 +1,58 @@
 //! A demo project for a simple CQRS/ES workflow
 #![forbid(unsafe_code)]
 
-mod domains;
+mod auth;
+mod correlation;
+mod health;
 mod http;
-mod utils;
+mod pipeline;
+mod shutdown;
 
 #[macro_use]
 extern crate log;
 
-use std::{io, panic::PanicInfo, sync::Arc};
+use std::{io, panic::PanicHookInfo, sync::Arc};
 
-use anyhow::anyhow;
-use aws_config::BehaviorVersion;
-use axum::{
-    routing::{get, post},
-    Router,
+use aide::{
+    axum::{
+        routing::{get_with, patch_with, post_with},
+        ApiRouter,
+    },
+    openapi::{Info, OpenApi, SecurityScheme},
 };
+use anyhow::anyhow;
+use axum::{response::Response, routing::get, Extension, Router};
 use backtrace::Backtrace;
-use cqrs_es::{
-    persist::{PersistedEventStore, ViewRepository},
-    CqrsFramework,
-};
+use cqrs_es::{persist::ViewRepository, CqrsFramework};
 use crossterm::{execute, style::Print};
-use domains::tasks::{self, cqrs::init_repo, Task};
-use dynamo_es::DynamoEventRepository;
+use event_driven_architecture::{
+    config::Config,
+    domains::{
+        projects::{self, Project},
+        tasks::{self, activity::Feed, cqrs::init_repo, inbox::Inbox, index::TaskIndex, Task},
+    },
+    utils::{lambda, metrics, telemetry},
+};
 use tower_http::trace;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use utils::lambda;
 
 #[derive(Clone)]
 struct AppState {
+    auth: Arc<auth::Authenticator>,
     tasks_repo: Arc<Box<dyn ViewRepository<tasks::View, Task>>>,
-    tasks_cqrs: Arc<CqrsFramework<Task, PersistedEventStore<DynamoEventRepository, Task>>>,
+    tasks_cqrs: Arc<CqrsFramework<Task, tasks::cqrs::EventStore>>,
+    tasks_authorizer: Arc<dyn tasks::Authorizer>,
+    tasks_activity: Arc<Feed>,
+    tasks_index: Arc<TaskIndex>,
+    tasks_inbox: Arc<Inbox>,
+    projects_repo: Arc<Box<dyn ViewRepository<projects::View, Project>>>,
+    projects_cqrs: Arc<CqrsFramework<Project, projects::cqrs::EventStore>>,
+    health: Arc<health::Checks>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    if is_running_on_lambda() {
+    if lambda::is_running_on_lambda() {
         lambda::tracing_subscriber_fmt();
     } else {
         tracing_subscriber::registry()
@@ -44,31 +60,68 @@ async fn main() -> anyhow::Result<()> {
                 std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
             ))
             .with(tracing_subscriber::fmt::layer())
+            .with(telemetry::layer())
             .init();
     }
 
-    let localstack_endpoint = std::env::var("LOCALSTACK_ENDPOINT").unwrap_or_default();
-    let environment = std::env::var("ENV").unwrap_or_default();
+    let config = Config::load()?;
+    let sdk_config = config.aws().await;
 
-    let mut config = aws_config::defaults(BehaviorVersion::latest());
-    if localstack_endpoint != "" {
-        config = config.endpoint_url(&localstack_endpoint);
-    }
-    let config = config.load().await;
+    let client = aws_sdk_dynamodb::Client::new(&sdk_config);
+
+    let tasks_repo = init_repo(client.clone(), &config);
+    let projects_repo = projects::cqrs::init_repo(client.clone(), &config);
+
+    // Run the publisher and the consumers in-process too, rather than on Lambda
+    let pipeline = (config.all_in_one && !lambda::is_running_on_lambda())
+        .then(|| pipeline::Pipeline::new(client.clone(), &config));
 
-    let client = aws_sdk_dynamodb::Client::new(&config);
+    let tasks_services = tasks::cqrs::init_services(client.clone(), &config, tasks_repo.clone());
+    let tasks_authorizer = tasks_services.authorizer.clone();
 
-    let tasks_repo = init_repo(client.clone());
+    let tasks_cqrs = tasks::cqrs::init_with_queries(
+        client.clone(),
+        &config,
+        tasks_repo.clone(),
+        tasks_services,
+        pipeline.as_ref().map(|p| p.queries()).unwrap_or_default(),
+    );
+    let projects_cqrs = projects::cqrs::init_with_queries(
+        client.clone(),
+        &config,
+        projects_repo.clone(),
+        pipeline.as_ref().map(|p| p.queries()).unwrap_or_default(),
+    );
+
+    if let Some(pipeline) = &pipeline {
+        info!("Running the publisher and consumers in-process");
+
+        pipeline.spawn(
+            client.clone(),
+            &config,
+            tasks_cqrs.clone(),
+            tasks_repo.clone(),
+            projects_cqrs.clone(),
+        );
+    }
 
     let state = AppState {
-        tasks_repo: tasks_repo.clone(),
-        tasks_cqrs: tasks::cqrs::init(client.clone(), tasks_repo),
+        auth: Arc::new(auth::Authenticator::init(&config)?),
+        tasks_repo,
+        tasks_cqrs,
+        tasks_authorizer,
+        tasks_activity: Feed::init(client.clone(), &config),
+        tasks_index: TaskIndex::init(client.clone(), &config),
+        tasks_inbox: Inbox::init(client.clone(), &config),
+        projects_repo,
+        projects_cqrs,
+        health: health::Checks::init(client.clone(), &config),
     };
 
-    let env_path = if environment == "local" {
+    let env_path = if config.is_local() {
         "".to_string()
     } else {
-        format!("/{}", environment)
+        format!("/{}", config.environment)
     };
 
     let app = Router::new()
@@ -77,20 +130,12 @@ async fn main() -> anyhow::Result<()> {
                 .make_span_with(trace::DefaultMakeSpan::new().level(tracing::Level::INFO))
                 .on_response(trace::DefaultOnResponse::new().level(tracing::Level::INFO)),
         )
-        .nest(
-            &env_path,
-            Router::new()
-                .route("/tasks", post(http::tasks_create))
-                .route(
-                    "/tasks/:id",
-                    get(http::tasks_get)
-                        .patch(http::tasks_update)
-                        .delete(http::tasks_delete),
-                )
-                .with_state(state),
-        );
+        .nest(&env_path, api_router(state));
+
+    if lambda::is_running_on_lambda() {
+        // Export each request's spans and metrics before the Lambda can be frozen
+        let app = app.layer(axum::middleware::from_fn(flush_telemetry));
 
-    if is_running_on_lambda() {
         // To run with AWS Lambda runtime, wrap in our `LambdaLayer`
         let app = tower::ServiceBuilder::new()
             .layer(axum_aws_lambda::LambdaLayer::default())
@@ -100,7 +145,7 @@ async fn main() -> anyhow::Result<()> {
             return Err(anyhow!("Lambda HTTP error: {}", error));
         };
     } else {
-        let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+        let listener = tokio::net::TcpListener::bind(config.bind_address)
             .await
             .expect("Unable to bind TcpListener");
 
@@ -109,18 +154,195 @@ async fn main() -> anyhow::Result<()> {
             port = listener.local_addr().expect("No server address found")
         );
 
-        axum::serve(listener, app).await?;
+        // Count requests in flight, and turn new ones away once the server starts draining
+        let shutdown = shutdown::Shutdown::new();
+        let app = app.layer(axum::middleware::from_fn_with_state(
+            shutdown.clone(),
+            shutdown::track,
+        ));
+
+        let server = axum::serve(listener, app).with_graceful_shutdown(shutdown.clone().listen());
+
+        tokio::select! {
+            result = server => result?,
+            () = shutdown.timed_out(config.shutdown_drain_timeout()) => {}
+        }
+
+        info!("Flushing telemetry");
+
+        telemetry::shutdown().await;
     }
 
     Ok(())
 }
 
-fn is_running_on_lambda() -> bool {
-    std::env::var("AWS_LAMBDA_RUNTIME_API").is_ok()
+/// Build the API routes, generating the OpenAPI document from the handlers as they're registered
+fn api_router(state: AppState) -> Router {
+    let mut api = OpenApi {
+        info: Info {
+            title: "Event Driven Architecture".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Info::default()
+        },
+        ..OpenApi::default()
+    };
+
+    let router = ApiRouter::new()
+        .api_route(
+            "/schemas",
+            get_with(http::schemas_list, http::schemas_list_docs),
+        )
+        .api_route(
+            "/schemas/DomainEvent",
+            get_with(http::schemas_envelope, http::schemas_envelope_docs),
+        )
+        .api_route(
+            "/schemas/:event_type/:version",
+            get_with(http::schemas_get, http::schemas_get_docs),
+        )
+        .api_route(
+            "/tasks",
+            get_with(http::tasks_list, http::tasks_list_docs)
+                .post_with(http::tasks_create, http::tasks_create_docs),
+        )
+        .api_route(
+            "/tasks/:id",
+            get_with(http::tasks_get, http::tasks_get_docs)
+                .patch_with(http::tasks_update, http::tasks_update_docs)
+                .delete_with(http::tasks_delete, http::tasks_delete_docs),
+        )
+        .api_route(
+            "/tasks/:id/restore",
+            post_with(http::tasks_restore, http::tasks_restore_docs),
+        )
+        .api_route(
+            "/tasks/:id/erase",
+            post_with(http::tasks_erase, http::tasks_erase_docs),
+        )
+        .api_route(
+            "/tasks/:id/start",
+            post_with(http::tasks_start, http::tasks_start_docs),
+        )
+        .api_route(
+            "/tasks/:id/block",
+            post_with(http::tasks_block, http::tasks_block_docs),
+        )
+        .api_route(
+            "/tasks/:id/complete",
+            post_with(http::tasks_complete, http::tasks_complete_docs),
+        )
+        .api_route(
+            "/tasks/:id/reopen",
+            post_with(http::tasks_reopen, http::tasks_reopen_docs),
+        )
+        .api_route(
+            "/tasks/:id/archive",
+            post_with(http::tasks_archive, http::tasks_archive_docs),
+        )
+        .api_route(
+            "/tasks/:id/move",
+            post_with(http::tasks_move, http::tasks_move_docs),
+        )
+        .api_route(
+            "/tasks/:id/link",
+            post_with(http::tasks_link, http::tasks_link_docs),
+        )
+        .api_route(
+            "/tasks/:id/unlink",
+            post_with(http::tasks_unlink, http::tasks_unlink_docs),
+        )
+        .api_route(
+            "/tasks/:id/assign",
+            post_with(http::tasks_assign, http::tasks_assign_docs),
+        )
+        .api_route(
+            "/tasks/:id/unassign",
+            post_with(http::tasks_unassign, http::tasks_unassign_docs),
+        )
+        .api_route(
+            "/tasks/:id/activity",
+            get_with(http::tasks_activity, http::tasks_activity_docs),
+        )
+        .api_route(
+            "/tasks/:id/comments",
+            post_with(
+                http::tasks_comments_create,
+                http::tasks_comments_create_docs,
+            ),
+        )
+        .api_route(
+            "/tasks/:id/comments/:comment_id",
+            patch_with(
+                http::tasks_comments_update,
+                http::tasks_comments_update_docs,
+            )
+            .delete_with(
+                http::tasks_comments_delete,
+                http::tasks_comments_delete_docs,
+            ),
+        )
+        .api_route(
+            "/users/:id/tasks",
+            get_with(http::users_tasks, http::users_tasks_docs),
+        )
+        .api_route(
+            "/projects",
+            post_with(http::projects_create, http::projects_create_docs),
+        )
+        .api_route(
+            "/projects/:id",
+            get_with(http::projects_get, http::projects_get_docs)
+                .patch_with(http::projects_update, http::projects_update_docs)
+                .delete_with(http::projects_delete, http::projects_delete_docs),
+        )
+        .api_route(
+            "/projects/:id/archive",
+            post_with(http::projects_archive, http::projects_archive_docs),
+        )
+        .api_route(
+            "/projects/:id/unarchive",
+            post_with(http::projects_unarchive, http::projects_unarchive_docs),
+        )
+        .route("/openapi.json", get(http::openapi))
+        .route("/metrics", get(http::metrics))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz));
+
+    #[cfg(feature = "api-docs")]
+    let router = router.route("/docs", http::api_docs());
+
+    router
+        .finish_api_with(&mut api, |api| {
+            api.security_scheme(
+                auth::SECURITY_SCHEME,
+                SecurityScheme::Http {
+                    scheme: "bearer".to_string(),
+                    bearer_format: Some("JWT".to_string()),
+                    description: None,
+                    extensions: Default::default(),
+                },
+            )
+        })
+        .layer(Extension(Arc::new(api)))
+        .layer(axum::middleware::from_fn(correlation::propagate))
+        .with_state(state)
+}
+
+/// Export the spans and metrics recorded for a request once it has been handled
+async fn flush_telemetry(
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let response = next.run(request).await;
+
+    telemetry::flush().await;
+    metrics::flush();
+
+    response
 }
 
 /// A generic function to log stacktraces on panic
-pub fn handle_panic(info: &PanicInfo<'_>) {
+pub fn handle_panic(info: &PanicHookInfo<'_>) {
     if cfg!(debug_assertions) {
         let location = info.location().unwrap();
 
@@ -0,0 +1,147 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use aws_lambda_events::{kinesis::KinesisEvent, streams::KinesisEventResponse};
+use chrono::Utc;
+use cqrs_es::{persist::ViewRepository, Aggregate, CqrsFramework, Query};
+use event_driven_architecture::{
+    config::Config,
+    domains::{
+        projects::{self, Project},
+        shredding::Shredder,
+        tasks::{self, inbox::Inbox, index::TaskIndex, Task},
+    },
+    process_managers::{
+        saga::{CqrsBus, DynamoSagaStore, SagaRunner, Sagas},
+        ProjectArchive, Recurrences,
+    },
+    projectors::{inbox::InboxProjector, objects::FileObjectStore, s3_audit::S3Audit},
+    publishers::InMemory,
+    runners::{MemoryStream, Runner},
+};
+use lambda_runtime::LambdaEvent;
+
+/// How often events that couldn't be published are retried
+const REDELIVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often saga timeouts are checked, matching the schedule the saga Lambda runs on
+const SAGA_TIMEOUT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The publisher and Kinesis consumers, run in the API process for the all-in-one mode
+///
+/// Committed events are published to an in-memory stream by a Query on each CqrsFramework, and
+/// every consumer reads the whole stream with its own runner, in the same event shape and with
+/// the same batch item failure retries it gets on Lambda. The audit trail is written to the local
+/// filesystem instead of S3, and saga timeouts are checked on a timer rather than a schedule.
+pub struct Pipeline {
+    stream: MemoryStream,
+    shredder: Arc<Shredder>,
+    publisher: InMemory,
+}
+
+impl Pipeline {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_dynamodb::Client, config: &Config) -> Self {
+        let stream = MemoryStream::new();
+        let shredder = Shredder::init(client, config);
+
+        Self {
+            publisher: InMemory::new(stream.clone(), shredder.clone()),
+            stream,
+            shredder,
+        }
+    }
+
+    /// The queries that publish an aggregate's committed events to the stream
+    pub fn queries<A>(&self) -> Vec<Box<dyn Query<A>>>
+    where
+        A: Aggregate,
+        A::Event: 'static,
+    {
+        vec![Box::new(self.publisher.clone())]
+    }
+
+    /// Start a runner for each consumer, and redeliver events that couldn't be published
+    pub fn spawn(
+        &self,
+        client: aws_sdk_dynamodb::Client,
+        config: &Config,
+        tasks_cqrs: Arc<CqrsFramework<Task, tasks::cqrs::EventStore>>,
+        tasks_repo: Arc<Box<dyn ViewRepository<tasks::View, Task>>>,
+        projects_cqrs: Arc<CqrsFramework<Project, projects::cqrs::EventStore>>,
+    ) {
+        let publisher = self.publisher.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REDELIVERY_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let waiting = publisher.redeliver().await;
+                if waiting > 0 {
+                    warn!("{} events are still waiting to be published", waiting);
+                }
+            }
+        });
+
+        let audit = S3Audit::new(
+            Arc::new(FileObjectStore::new(&config.audit_dir)),
+            self.shredder.clone(),
+        );
+        self.run("projector_s3_audit", move |event| {
+            let audit = audit.clone();
+            async move { audit.handle(event).await }
+        });
+
+        let inbox = InboxProjector::new(Inbox::init(client.clone(), config));
+        self.run("projector_inbox", move |event| {
+            let inbox = inbox.clone();
+            async move { inbox.handle(event).await }
+        });
+
+        let recurrences = Recurrences::new(tasks_cqrs.clone(), tasks_repo);
+        self.run("process_manager_recurrence", move |event| {
+            let recurrences = recurrences.clone();
+            async move { recurrences.handle(event).await }
+        });
+
+        let sagas = Arc::new(Sagas::new(vec![Arc::new(SagaRunner::new(
+            ProjectArchive::new(TaskIndex::init(client.clone(), config)),
+            DynamoSagaStore::init(client, config),
+            Arc::new(CqrsBus::new(tasks_cqrs, projects_cqrs)),
+        ))]));
+        self.run("process_manager_sagas", {
+            let sagas = sagas.clone();
+            move |event| {
+                let sagas = sagas.clone();
+                async move { sagas.handle_records(event).await }
+            }
+        });
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SAGA_TIMEOUT_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let fired = sagas.fire_timeouts(Utc::now()).await;
+                if fired > 0 {
+                    info!("Handled {} saga timeouts", fired);
+                }
+            }
+        });
+    }
+
+    fn run<H, F>(&self, name: &'static str, handler: H)
+    where
+        H: Fn(LambdaEvent<KinesisEvent>) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<KinesisEventResponse, lambda_runtime::Error>> + Send,
+    {
+        let mut runner = Runner::in_memory(name, self.stream.clone());
+
+        tokio::spawn(async move {
+            if let Err(error) = runner.run(handler).await {
+                error!("{} stopped: {}", name, error);
+            }
+        });
+    }
+}
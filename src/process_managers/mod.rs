@@ -0,0 +1,11 @@
+/// The recurring Task process manager
+pub mod recurrence;
+
+/// The saga framework for workflows that span aggregates
+pub mod saga;
+
+/// The saga that archives a Project's Tasks along with it
+pub mod project_archive;
+
+pub use project_archive::ProjectArchive;
+pub use recurrence::Recurrences;
@@ -0,0 +1,226 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use chrono::Duration;
+use cqrs_es::persist::PersistenceError;
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+
+use crate::domains::{
+    self, projects,
+    tasks::{
+        self,
+        index::{TaskIndex, MAX_PAGE_SIZE},
+        Status,
+    },
+    tenants, DomainEvent,
+};
+
+use super::saga::{Error, Reaction, Saga, SagaCommand};
+
+/// How long to wait for the Tasks to be archived before checking on them again
+const TIMEOUT_MINUTES: i64 = 15;
+
+/// How many times the remaining Tasks are archived again before the saga gives up
+const MAX_RETRIES: u32 = 3;
+
+/// Lists the Tasks in a Project
+#[async_trait]
+pub trait ProjectTasks: Send + Sync {
+    /// The Views of every Task in a Project, whoever owns them
+    async fn list(
+        &self,
+        tenant: &str,
+        project_id: &str,
+    ) -> Result<Vec<tasks::View>, PersistenceError>;
+}
+
+#[async_trait]
+impl ProjectTasks for TaskIndex {
+    async fn list(
+        &self,
+        tenant: &str,
+        project_id: &str,
+    ) -> Result<Vec<tasks::View>, PersistenceError> {
+        let mut views = Vec::new();
+        let mut after = None;
+
+        loop {
+            let page = self
+                .in_project(tenant, project_id, after, MAX_PAGE_SIZE)
+                .await?;
+
+            views.extend(page.tasks);
+
+            if page.next.is_none() {
+                return Ok(views);
+            }
+
+            after = page.next;
+        }
+    }
+}
+
+/// The state of a single Project archive
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct State {
+    /// The subject that archived the Project, who owns it
+    pub owner: String,
+
+    /// The aggregate IDs of the Tasks that haven't been archived yet
+    pub pending: BTreeSet<String>,
+
+    /// How many times the pending Tasks have been archived again after a timeout
+    pub retries: u32,
+
+    /// The Tasks that couldn't be archived, by aggregate ID, with the reason why
+    #[serde(default)]
+    pub failed: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Give up on a Task, recording why it couldn't be archived
+    fn fail(&mut self, aggregate_id: &str, reason: String) {
+        self.pending.remove(aggregate_id);
+        self.failed.insert(aggregate_id.to_string(), reason);
+    }
+
+    /// Complete once no Tasks are pending, reporting any that couldn't be archived
+    fn settle(&self) -> Reaction {
+        let complete = self.pending.is_empty();
+
+        if complete && !self.failed.is_empty() {
+            tracing::warn!(
+                failed = ?self.failed,
+                "Finished archiving a Project, with Tasks that couldn't be archived"
+            );
+        }
+
+        Reaction {
+            complete,
+            ..Reaction::default()
+        }
+    }
+
+    /// Archive the pending Tasks, waiting for them to be archived unless there are none
+    fn archive_pending(&self) -> Reaction {
+        let commands = self
+            .pending
+            .iter()
+            .map(|aggregate_id| SagaCommand::Task {
+                aggregate_id: aggregate_id.clone(),
+                command: tasks::Command::Archive {
+                    subject: self.owner.clone(),
+                },
+            })
+            .collect();
+
+        Reaction {
+            commands,
+            timeout: Some(Duration::minutes(TIMEOUT_MINUTES)),
+            ..self.settle()
+        }
+    }
+}
+
+/// Archives a Project's Tasks when the Project is archived
+///
+/// Each archive of a Project is its own instance, correlated by the Project's aggregate ID and
+/// the sequence of its `Project:Archived` event. The instance finishes once every Task it
+/// archived has recorded its `Task:Archived` event, or has been given up on. Tasks that reject the
+/// command, because they were deleted or belong to someone else, and Tasks that are still open
+/// after the last retry, are recorded in the state's `failed` Tasks rather than retried.
+#[derive(new)]
+pub struct ProjectArchive {
+    tasks: Arc<dyn ProjectTasks>,
+}
+
+#[async_trait]
+impl Saga for ProjectArchive {
+    type State = State;
+
+    const NAME: &'static str = "ProjectArchive";
+
+    fn correlate(&self, event: &DomainEvent) -> Option<String> {
+        match (event.entity.as_str(), event.event_type.as_str()) {
+            (projects::AGGREGATE_TYPE, "Project:Archived") => {
+                Some(format!("{}@{}", event.id, event.sequence))
+            }
+            (tasks::AGGREGATE_TYPE, "Task:Archived")
+                if event.metadata_value("saga").as_deref() == Some(Self::NAME) =>
+            {
+                event.metadata_value("saga_correlation_id")
+            }
+            _ => None,
+        }
+    }
+
+    async fn handle(&self, state: &mut State, event: &DomainEvent) -> Result<Reaction, Error> {
+        if event.entity == tasks::AGGREGATE_TYPE {
+            state.pending.remove(&event.id);
+
+            return Ok(state.settle());
+        }
+
+        let Some((tenant, project_id)) = tenants::split_id(&event.id) else {
+            // Projects were introduced after multi-tenancy, so their IDs are always scoped
+            return Ok(Reaction {
+                complete: true,
+                ..Reaction::default()
+            });
+        };
+
+        // Only the owner can archive a Project, so its Tasks are archived on their behalf
+        state.owner = event.metadata_value("subject").unwrap_or_default();
+
+        let views = self
+            .tasks
+            .list(tenant, project_id)
+            .await
+            .map_err(|err| Error::Unavailable(err.into()))?;
+
+        state.pending = views
+            .into_iter()
+            .filter(|view| view.task.status != Status::Archived && !view.task.deleted)
+            .map(|view| view.id)
+            .collect();
+
+        Ok(state.archive_pending())
+    }
+
+    async fn on_timeout(&self, state: &mut State) -> Result<Reaction, Error> {
+        if state.retries >= MAX_RETRIES {
+            for aggregate_id in std::mem::take(&mut state.pending) {
+                state.fail(
+                    &aggregate_id,
+                    format!("Not archived after {MAX_RETRIES} retries"),
+                );
+            }
+
+            return Ok(state.settle());
+        }
+
+        state.retries += 1;
+
+        Ok(state.archive_pending())
+    }
+
+    async fn compensate(
+        &self,
+        state: &mut State,
+        command: &SagaCommand,
+        error: &domains::Error,
+    ) -> Result<Reaction, Error> {
+        tracing::info!(
+            error = ?error, aggregate_id = command.aggregate_id(),
+            "Leaving a Task that couldn't be archived"
+        );
+
+        state.fail(command.aggregate_id(), error.to_string());
+
+        Ok(state.settle())
+    }
+}
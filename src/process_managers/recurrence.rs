@@ -0,0 +1,183 @@
+use std::{str::Utf8Error, sync::Arc};
+
+use aws_lambda_events::{
+    kinesis::{KinesisEvent, KinesisEventRecord},
+    streams::{KinesisBatchItemFailure, KinesisEventResponse},
+};
+use cqrs_es::{
+    persist::{PersistenceError, ViewRepository},
+    AggregateError, CqrsFramework,
+};
+use derive_new::new;
+use lambda_runtime::LambdaEvent;
+use tracing::Instrument;
+use ulid::Ulid;
+
+use crate::{
+    domains::{
+        self,
+        tasks::{self, cqrs::EventStore, Status, Task},
+        tenants, DomainEvent,
+    },
+    utils::{
+        metrics,
+        telemetry::{self, TracedExecute},
+    },
+};
+
+/// The subject recorded on commands issued by the recurrence process manager
+pub const RECURRENCE_SUBJECT: &str = "system:recurrence";
+
+/// The Task event types that can leave a recurring Task completed
+const EVENT_TYPES: [&str; 2] = ["Task:Completed", "Task:Updated"];
+
+/// Creates the next occurrence of a recurring Task once it's completed
+///
+/// Completing a recurring Task, or giving a completed Task a recurrence rule, creates the
+/// occurrence after it. Each occurrence's ID is derived from its series and position, so a
+/// redelivered event addresses a Task that already exists and is skipped rather than duplicated.
+#[derive(Clone, new)]
+pub struct Recurrences {
+    cqrs: Arc<CqrsFramework<Task, EventStore>>,
+    tasks_repo: Arc<Box<dyn ViewRepository<tasks::View, Task>>>,
+}
+
+impl Recurrences {
+    /// Handle the Kinesis event and create any occurrences that are due
+    pub async fn handle(
+        &self,
+        event: LambdaEvent<KinesisEvent>,
+    ) -> Result<KinesisEventResponse, lambda_runtime::Error> {
+        tracing::info!(
+            "Processing batch of {} events from Kinesis",
+            event.payload.records.len(),
+        );
+
+        let mut batch_item_failures = Vec::new();
+
+        for record in event.payload.records.iter() {
+            let sequence_number = record.kinesis.sequence_number.clone();
+
+            let span = tracing::info_span!(
+                "process_manager.recurrence.record",
+                sequence_number = sequence_number
+            );
+
+            if let Err(error) = self.handle_record(record).instrument(span).await {
+                tracing::error!(
+                    error = ?error, sequence_number = sequence_number,
+                    "Failed to process event"
+                );
+
+                metrics::increment(
+                    metrics::BATCH_ITEM_FAILURES,
+                    &[("consumer", "process_manager.recurrence")],
+                );
+
+                batch_item_failures.push(KinesisBatchItemFailure {
+                    item_identifier: sequence_number,
+                });
+            };
+        }
+
+        Ok(KinesisEventResponse {
+            batch_item_failures,
+        })
+    }
+
+    async fn handle_record(&self, record: &KinesisEventRecord) -> Result<(), Error> {
+        let record_data = std::str::from_utf8(&record.kinesis.data).map_err(Error::Utf8)?;
+        let event: DomainEvent = serde_json::from_str(record_data).map_err(Error::Json)?;
+
+        telemetry::continue_trace(&event);
+        metrics::observe_lag("process_manager.recurrence", event.recorded_at);
+
+        if event.entity != tasks::AGGREGATE_TYPE
+            || !EVENT_TYPES.contains(&event.event_type.as_str())
+        {
+            return Ok(());
+        }
+
+        // Decide from the Task's current state rather than the event, so a Task that was
+        // reopened or stopped repeating since is left alone
+        let Some(view) = self.tasks_repo.load(&event.id).await? else {
+            return Ok(());
+        };
+
+        self.recur(&event, view.task).await
+    }
+
+    /// Create the occurrence after a Task, if it's completed and its series hasn't ended
+    async fn recur(&self, event: &DomainEvent, previous: Task) -> Result<(), Error> {
+        let aggregate_id = event.id.as_str();
+
+        if previous.status != Status::Done || previous.deleted {
+            return Ok(());
+        }
+
+        let Some(task_id) = previous
+            .next_occurrence()
+            .and_then(|(series, _)| series.task_id())
+        else {
+            return Ok(());
+        };
+
+        let tenant = tenants::split_id(aggregate_id).map(|(tenant, _)| tenant);
+        let next_id = match tenant {
+            Some(tenant) => tenants::scoped_id(tenant, &task_id),
+            None => task_id,
+        };
+
+        let mut metadata = event.reaction_metadata();
+        metadata.insert("command_id".to_string(), Ulid::new().to_string());
+        metadata.insert("subject".to_string(), RECURRENCE_SUBJECT.to_string());
+        metadata.insert("tenant".to_string(), tenant.unwrap_or_default().to_string());
+
+        let command = tasks::Command::CreateOccurrence {
+            previous: Box::new(previous),
+        };
+
+        match self.cqrs.execute_traced(&next_id, command, metadata).await {
+            Ok(()) => {
+                tracing::info!(
+                    aggregate_id = aggregate_id,
+                    next_id = next_id,
+                    correlation_id = event.correlation(),
+                    "Created the next occurrence"
+                );
+
+                Ok(())
+            }
+            Err(AggregateError::UserError(domains::Error::Uniqueness { .. })) => {
+                tracing::info!(
+                    aggregate_id = aggregate_id,
+                    next_id = next_id,
+                    "The next occurrence already exists"
+                );
+
+                Ok(())
+            }
+            Err(error) => Err(Error::Command(error)),
+        }
+    }
+}
+
+/// Recurrence process manager errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Utf8 conversion error
+    #[error("Utf8 conversion error: {0}")]
+    Utf8(#[from] Utf8Error),
+
+    /// JSON conversion error
+    #[error("JSON conversion error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// Task view error
+    #[error("Task view error: {0}")]
+    Persistence(#[from] PersistenceError),
+
+    /// The next occurrence couldn't be created
+    #[error("Command error: {0}")]
+    Command(#[from] AggregateError<domains::Error>),
+}
@@ -0,0 +1,71 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use cqrs_es::{AggregateError, CqrsFramework, EventStore};
+use derive_new::new;
+
+use crate::{
+    domains::{self, projects::Project, tasks::Task},
+    utils::telemetry::TracedExecute,
+};
+
+use super::SagaCommand;
+
+/// Issues saga commands to the aggregates they're addressed to
+#[async_trait]
+pub trait CommandBus: Send + Sync {
+    /// Execute a command with the given metadata
+    async fn execute(
+        &self,
+        command: SagaCommand,
+        metadata: HashMap<String, String>,
+    ) -> Result<(), AggregateError<domains::Error>>;
+}
+
+/// A CommandBus backed by the Task and Project CqrsFrameworks
+///
+/// It's generic over the event stores, so sagas can run against the DynamoDB event store when
+/// deployed and against `cqrs_es::mem_store::MemStore` in-process.
+#[derive(new)]
+pub struct CqrsBus<T, P>
+where
+    T: EventStore<Task>,
+    P: EventStore<Project>,
+{
+    tasks: Arc<CqrsFramework<Task, T>>,
+    projects: Arc<CqrsFramework<Project, P>>,
+}
+
+#[async_trait]
+impl<T, P> CommandBus for CqrsBus<T, P>
+where
+    T: EventStore<Task> + Send + Sync,
+    T::AC: Send,
+    P: EventStore<Project> + Send + Sync,
+    P::AC: Send,
+{
+    async fn execute(
+        &self,
+        command: SagaCommand,
+        metadata: HashMap<String, String>,
+    ) -> Result<(), AggregateError<domains::Error>> {
+        match command {
+            SagaCommand::Task {
+                aggregate_id,
+                command,
+            } => {
+                self.tasks
+                    .execute_traced(&aggregate_id, command, metadata)
+                    .await
+            }
+            SagaCommand::Project {
+                aggregate_id,
+                command,
+            } => {
+                self.projects
+                    .execute_traced(&aggregate_id, command, metadata)
+                    .await
+            }
+        }
+    }
+}
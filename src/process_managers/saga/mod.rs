@@ -0,0 +1,201 @@
+use std::{collections::BTreeSet, fmt};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use cqrs_es::{persist::PersistenceError, AggregateError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::domains::{self, projects, tasks, DomainEvent};
+
+/// Issuing saga commands through a `CqrsFramework`
+pub mod bus;
+
+/// Durable saga state
+pub mod store;
+
+/// Running sagas from the Kinesis stream, or in-process
+pub mod runtime;
+
+pub use bus::{CommandBus, CqrsBus};
+pub use runtime::{SagaRunner, Sagas};
+pub use store::{DynamoSagaStore, MemorySagaStore, SagaStore};
+
+/// A command issued by a saga, addressed to one of the domain aggregates
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SagaCommand {
+    /// A command for a Task
+    Task {
+        /// The tenant-scoped aggregate ID of the Task
+        aggregate_id: String,
+
+        /// The command to issue
+        command: tasks::Command,
+    },
+
+    /// A command for a Project
+    Project {
+        /// The tenant-scoped aggregate ID of the Project
+        aggregate_id: String,
+
+        /// The command to issue
+        command: projects::Command,
+    },
+}
+
+impl SagaCommand {
+    /// The aggregate the command is addressed to
+    pub fn aggregate_id(&self) -> &str {
+        match self {
+            SagaCommand::Task { aggregate_id, .. } | SagaCommand::Project { aggregate_id, .. } => {
+                aggregate_id
+            }
+        }
+    }
+}
+
+/// What a saga does in response to an event, a timeout, or a rejected command
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Reaction {
+    /// Commands to issue, in order
+    pub commands: Vec<SagaCommand>,
+
+    /// How long to wait before calling the saga's `on_timeout` if it hasn't completed, replacing
+    /// any earlier timeout. The runner sets the deadline, so reactions don't depend on the clock.
+    pub timeout: Option<Duration>,
+
+    /// Whether the saga is finished. Completed sagas ignore any further events and timeouts.
+    pub complete: bool,
+}
+
+/// A long-running workflow that reacts to Domain Events by issuing commands
+///
+/// Each instance of a saga is identified by a correlation ID, and keeps its own `State` between
+/// events. The runner records which events an instance has handled and which of its commands
+/// were accepted, so redelivered events are skipped and commands are never issued twice. Command
+/// IDs are derived from the saga, the instance, and the event that caused them, so retrying an
+/// event after a failure issues the same commands with the same IDs.
+#[async_trait]
+pub trait Saga: Send + Sync + 'static {
+    /// The state kept for each instance between events
+    type State: Clone + Default + Serialize + DeserializeOwned + Send + Sync;
+
+    /// A unique name for the saga, which its instances are stored under
+    const NAME: &'static str;
+
+    /// The correlation ID of the instance an event starts or continues, or `None` if the saga
+    /// isn't interested in the event
+    fn correlate(&self, event: &DomainEvent) -> Option<String>;
+
+    /// React to an event. `handle` is retried from the same state if issuing its commands fails,
+    /// so it should make the same decisions for the same state and event.
+    async fn handle(&self, state: &mut Self::State, event: &DomainEvent)
+        -> Result<Reaction, Error>;
+
+    /// React to the instance's timeout passing before it completed
+    async fn on_timeout(&self, _state: &mut Self::State) -> Result<Reaction, Error> {
+        Ok(Reaction::default())
+    }
+
+    /// React to one of the instance's commands being rejected, usually by issuing commands that
+    /// undo the steps that came before it. Compensating commands that are rejected are logged
+    /// and skipped.
+    async fn compensate(
+        &self,
+        _state: &mut Self::State,
+        _command: &SagaCommand,
+        _error: &domains::Error,
+    ) -> Result<Reaction, Error> {
+        Ok(Reaction::default())
+    }
+}
+
+/// The durable record of a single saga instance
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Instance {
+    /// The saga's name
+    pub saga: String,
+
+    /// The correlation ID of this instance
+    pub correlation_id: String,
+
+    /// The correlation ID of the user action that started this instance, carried on to every
+    /// command it issues
+    #[serde(default)]
+    pub origin_correlation_id: String,
+
+    /// The saga's own state, as JSON
+    pub state: serde_json::Value,
+
+    /// The events and timeouts that have been handled, so redeliveries are skipped
+    pub handled: BTreeSet<String>,
+
+    /// The IDs of commands that were accepted, so retries don't issue them again
+    pub issued: BTreeSet<String>,
+
+    /// When the saga's timeout is due, if it has one
+    pub timeout_at: Option<DateTime<Utc>>,
+
+    /// Whether the saga has finished
+    pub completed: bool,
+
+    /// The version this record was loaded at, used to detect concurrent changes
+    pub version: u64,
+}
+
+impl Instance {
+    /// A new instance, with no state recorded yet
+    pub fn new(saga: &str, correlation_id: &str) -> Self {
+        Instance {
+            saga: saga.to_string(),
+            correlation_id: correlation_id.to_string(),
+            state: serde_json::Value::Null,
+            ..Instance::default()
+        }
+    }
+
+    /// Decode the saga's state, starting from the default for a new instance
+    pub fn state<T: Default + DeserializeOwned>(&self) -> Result<T, Error> {
+        if self.state.is_null() {
+            return Ok(T::default());
+        }
+
+        serde_json::from_value(self.state.clone()).map_err(Error::Json)
+    }
+
+    /// Record the saga's state
+    pub fn set_state<T: Serialize>(&mut self, state: &T) -> Result<(), Error> {
+        self.state = serde_json::to_value(state).map_err(Error::Json)?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.saga, self.correlation_id)
+    }
+}
+
+/// Saga errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// JSON conversion error
+    #[error("JSON conversion error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// Saga storage error
+    #[error("Saga storage error: {0}")]
+    Persistence(#[from] PersistenceError),
+
+    /// The instance was changed by another invocation since it was loaded
+    #[error("Saga {0} was changed concurrently")]
+    Conflict(String),
+
+    /// A command failed for a reason other than the aggregate rejecting it
+    #[error("Command error: {0}")]
+    Command(#[from] AggregateError<domains::Error>),
+
+    /// A saga couldn't read what it needed to decide how to react
+    #[error("Unavailable: {0}")]
+    Unavailable(#[source] anyhow::Error),
+}
@@ -0,0 +1,768 @@
+use std::{collections::HashMap, str::Utf8Error, sync::Arc};
+
+use async_trait::async_trait;
+use aws_lambda_events::{
+    kinesis::{KinesisEvent, KinesisEventRecord},
+    streams::{KinesisBatchItemFailure, KinesisEventResponse},
+};
+use chrono::{DateTime, Utc};
+use cqrs_es::AggregateError;
+use lambda_runtime::LambdaEvent;
+use tracing::Instrument;
+
+use crate::{
+    domains::{self, tenants, DomainEvent, Upcasters},
+    utils::{metrics, telemetry},
+};
+
+use super::{CommandBus, Error, Instance, Reaction, Saga, SagaCommand, SagaStore};
+
+/// The most timeouts fired for each saga in a single run
+const TIMEOUT_BATCH_SIZE: i32 = 100;
+
+/// Runs a single saga, keeping its instances in a SagaStore and issuing its commands to a
+/// CommandBus
+pub struct SagaRunner<S: Saga> {
+    saga: S,
+    store: Arc<dyn SagaStore>,
+    bus: Arc<dyn CommandBus>,
+}
+
+impl<S: Saga> SagaRunner<S> {
+    /// Create a new instance
+    pub fn new(saga: S, store: Arc<dyn SagaStore>, bus: Arc<dyn CommandBus>) -> Self {
+        Self { saga, store, bus }
+    }
+
+    /// The subject recorded on commands issued by this saga
+    fn subject() -> String {
+        format!("system:saga:{}", S::NAME)
+    }
+
+    /// Handle an event, if the saga is interested in it
+    pub async fn dispatch(&self, event: &DomainEvent) -> Result<(), Error> {
+        let Some(correlation_id) = self.saga.correlate(event) else {
+            return Ok(());
+        };
+
+        let mut instance = self
+            .store
+            .load(S::NAME, &correlation_id)
+            .await?
+            .unwrap_or_else(|| Instance::new(S::NAME, &correlation_id));
+
+        let cause = event.reference();
+
+        if instance.completed || instance.handled.contains(&cause) {
+            return Ok(());
+        }
+
+        if instance.origin_correlation_id.is_empty() {
+            instance.origin_correlation_id = event.correlation();
+        }
+
+        let mut state: S::State = instance.state()?;
+        let reaction = self.saga.handle(&mut state, event).await?;
+
+        self.settle(&mut instance, state, reaction, cause, Utc::now(), false)
+            .await
+    }
+
+    /// Handle the timeouts that are due by `now`, returning how many were handled
+    pub async fn fire_timeouts(&self, now: DateTime<Utc>) -> Result<usize, Error> {
+        let due = self.store.due(S::NAME, now, TIMEOUT_BATCH_SIZE).await?;
+
+        let mut fired = 0;
+
+        for correlation_id in due {
+            match self.fire_timeout(&correlation_id, now).await {
+                Ok(true) => fired += 1,
+                Ok(false) => {}
+                Err(error) => {
+                    // The timeout is left in place, so it's retried on the next run
+                    tracing::error!(
+                        error = ?error, saga = S::NAME, correlation_id = correlation_id,
+                        "Failed to handle saga timeout"
+                    );
+                }
+            }
+        }
+
+        Ok(fired)
+    }
+
+    async fn fire_timeout(&self, correlation_id: &str, now: DateTime<Utc>) -> Result<bool, Error> {
+        let Some(mut instance) = self.store.load(S::NAME, correlation_id).await? else {
+            return Ok(false);
+        };
+
+        let Some(timeout_at) = instance.timeout_at.filter(|at| *at <= now) else {
+            return Ok(false);
+        };
+
+        if instance.completed {
+            return Ok(false);
+        }
+
+        let cause = format!("timeout/{}", timeout_at.timestamp());
+
+        let mut state: S::State = instance.state()?;
+        let reaction = self.saga.on_timeout(&mut state).await?;
+
+        // The timeout has passed, so it only stays set if the saga asks for another one
+        self.settle(&mut instance, state, reaction, cause, now, true)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Issue a reaction's commands and save the instance, with any timeout it asks for counted
+    /// from `now`
+    ///
+    /// If a command fails for a reason other than being rejected, the commands that were accepted
+    /// are saved but the new state isn't, so the cause is retried from the same state and only
+    /// the remaining commands are issued.
+    async fn settle(
+        &self,
+        instance: &mut Instance,
+        mut state: S::State,
+        reaction: Reaction,
+        cause: String,
+        now: DateTime<Utc>,
+        clear_timeout: bool,
+    ) -> Result<(), Error> {
+        let lifecycle = (instance.timeout_at, instance.completed);
+
+        if clear_timeout {
+            instance.timeout_at = None;
+        }
+
+        match self
+            .react(instance, &mut state, reaction, &cause, now)
+            .await
+        {
+            Ok(()) => {
+                instance.handled.insert(cause);
+                instance.set_state(&state)?;
+                self.store.save(instance).await
+            }
+            Err(error) => {
+                (instance.timeout_at, instance.completed) = lifecycle;
+                self.store.save(instance).await?;
+
+                Err(error)
+            }
+        }
+    }
+
+    async fn react(
+        &self,
+        instance: &mut Instance,
+        state: &mut S::State,
+        reaction: Reaction,
+        cause: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        // Number commands per aggregate rather than overall, so a retry that addresses fewer
+        // aggregates still gives each command the same ID
+        let mut counts = HashMap::<String, usize>::new();
+
+        for command in &reaction.commands {
+            let count = counts
+                .entry(command.aggregate_id().to_string())
+                .or_default();
+            let command_id = format!("{instance}:{cause}:{}:{count}", command.aggregate_id());
+            *count += 1;
+
+            let Some(error) = self
+                .issue(instance, command.clone(), &command_id, cause)
+                .await?
+            else {
+                continue;
+            };
+
+            tracing::info!(
+                error = ?error, command_id = command_id,
+                "Saga command was rejected, compensating"
+            );
+
+            let compensation = self.saga.compensate(state, command, &error).await?;
+
+            for (j, command) in compensation.commands.iter().enumerate() {
+                let compensation_id = format!("{command_id}:compensation:{j}");
+
+                if let Some(error) = self
+                    .issue(instance, command.clone(), &compensation_id, cause)
+                    .await?
+                {
+                    tracing::warn!(
+                        error = ?error, command_id = compensation_id,
+                        "Compensating saga command was rejected"
+                    );
+                }
+            }
+
+            apply(instance, &compensation, now);
+        }
+
+        apply(instance, &reaction, now);
+
+        Ok(())
+    }
+
+    /// Issue a command unless it was accepted before, returning the domain error if the
+    /// aggregate rejected it. The command is correlated with the action that started the
+    /// instance, and caused by the event or timeout being handled.
+    async fn issue(
+        &self,
+        instance: &mut Instance,
+        command: SagaCommand,
+        command_id: &str,
+        cause: &str,
+    ) -> Result<Option<domains::Error>, Error> {
+        if instance.issued.contains(command_id) {
+            return Ok(None);
+        }
+
+        let tenant = tenants::split_id(command.aggregate_id())
+            .map(|(tenant, _)| tenant)
+            .unwrap_or_default();
+
+        let mut metadata = HashMap::<String, String>::new();
+        metadata.insert("command_id".to_string(), command_id.to_string());
+        metadata.insert("subject".to_string(), Self::subject());
+        metadata.insert("tenant".to_string(), tenant.to_string());
+        metadata.insert("saga".to_string(), S::NAME.to_string());
+        metadata.insert(
+            "saga_correlation_id".to_string(),
+            instance.correlation_id.clone(),
+        );
+        metadata.insert(
+            "correlation_id".to_string(),
+            if instance.origin_correlation_id.is_empty() {
+                instance.to_string()
+            } else {
+                instance.origin_correlation_id.clone()
+            },
+        );
+        metadata.insert("causation_id".to_string(), cause.to_string());
+
+        match self.bus.execute(command, metadata).await {
+            Ok(()) => {
+                instance.issued.insert(command_id.to_string());
+
+                Ok(None)
+            }
+            Err(AggregateError::UserError(error)) => Ok(Some(error)),
+            Err(error) => Err(Error::Command(error)),
+        }
+    }
+}
+
+/// Apply the lifecycle changes a reaction asks for
+fn apply(instance: &mut Instance, reaction: &Reaction, now: DateTime<Utc>) {
+    if reaction.complete {
+        instance.completed = true;
+    }
+
+    if instance.completed {
+        instance.timeout_at = None;
+    } else if let Some(timeout) = reaction.timeout {
+        instance.timeout_at = Some(now + timeout);
+    }
+}
+
+/// A saga runner with its state type erased, so different sagas can run side by side
+#[async_trait]
+pub trait Dispatch: Send + Sync {
+    /// The saga's name
+    fn name(&self) -> &'static str;
+
+    /// Handle an event, if the saga is interested in it
+    async fn dispatch(&self, event: &DomainEvent) -> Result<(), Error>;
+
+    /// Handle the timeouts that are due by `now`, returning how many were handled
+    async fn fire_timeouts(&self, now: DateTime<Utc>) -> Result<usize, Error>;
+}
+
+#[async_trait]
+impl<S: Saga> Dispatch for SagaRunner<S> {
+    fn name(&self) -> &'static str {
+        S::NAME
+    }
+
+    async fn dispatch(&self, event: &DomainEvent) -> Result<(), Error> {
+        SagaRunner::dispatch(self, event).await
+    }
+
+    async fn fire_timeouts(&self, now: DateTime<Utc>) -> Result<usize, Error> {
+        SagaRunner::fire_timeouts(self, now).await
+    }
+}
+
+/// Every saga, fed from the Kinesis stream when deployed, or directly when run in-process
+pub struct Sagas {
+    runners: Vec<Arc<dyn Dispatch>>,
+
+    /// Upcasters applied to Domain Event payloads before the sagas see them
+    upcasters: Arc<Upcasters>,
+}
+
+impl Sagas {
+    /// Create a new instance
+    pub fn new(runners: Vec<Arc<dyn Dispatch>>) -> Self {
+        Self {
+            runners,
+            upcasters: Arc::new(domains::upcasters()),
+        }
+    }
+
+    /// Handle a Lambda invocation, which is either a batch of Kinesis records or a scheduled
+    /// check for timeouts
+    pub async fn handle(
+        &self,
+        event: LambdaEvent<serde_json::Value>,
+    ) -> Result<serde_json::Value, lambda_runtime::Error> {
+        if event.payload.get("Records").is_none() {
+            let fired = self.fire_timeouts(Utc::now()).await;
+
+            tracing::info!("Handled {} saga timeouts", fired);
+
+            return Ok(serde_json::Value::Null);
+        }
+
+        let (payload, context) = event.into_parts();
+        let batch = LambdaEvent::new(serde_json::from_value(payload)?, context);
+
+        Ok(serde_json::to_value(self.handle_records(batch).await?)?)
+    }
+
+    /// Handle a batch of Kinesis records, reporting the records that failed so they're retried
+    pub async fn handle_records(
+        &self,
+        event: LambdaEvent<KinesisEvent>,
+    ) -> Result<KinesisEventResponse, lambda_runtime::Error> {
+        let batch = event.payload;
+
+        tracing::info!(
+            "Processing batch of {} events from Kinesis",
+            batch.records.len(),
+        );
+
+        let mut batch_item_failures = Vec::new();
+
+        for record in batch.records.iter() {
+            let sequence_number = record.kinesis.sequence_number.clone();
+
+            let span = tracing::info_span!(
+                "process_manager.sagas.record",
+                sequence_number = sequence_number
+            );
+
+            if let Err(error) = self.handle_record(record).instrument(span).await {
+                tracing::error!(
+                    error = ?error, sequence_number = sequence_number,
+                    "Failed to process event"
+                );
+
+                metrics::increment(
+                    metrics::BATCH_ITEM_FAILURES,
+                    &[("consumer", "process_manager.sagas")],
+                );
+
+                batch_item_failures.push(KinesisBatchItemFailure {
+                    item_identifier: sequence_number,
+                });
+            };
+        }
+
+        Ok(KinesisEventResponse {
+            batch_item_failures,
+        })
+    }
+
+    async fn handle_record(&self, record: &KinesisEventRecord) -> Result<(), RecordError> {
+        let record_data = std::str::from_utf8(&record.kinesis.data)?;
+        let event: DomainEvent = serde_json::from_str(record_data).map_err(Error::Json)?;
+
+        telemetry::continue_trace(&event);
+        metrics::observe_lag("process_manager.sagas", event.recorded_at);
+
+        Ok(self.dispatch(event).await?)
+    }
+
+    /// Hand an event to every saga, stopping at the first that fails so the event is retried.
+    /// Sagas that already handled it skip it on the retry.
+    pub async fn dispatch(&self, event: DomainEvent) -> Result<(), Error> {
+        let event = self.upcasters.upcast_domain_event(event)?;
+
+        for runner in &self.runners {
+            runner.dispatch(&event).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle every saga's timeouts that are due by `now`, returning how many were handled
+    pub async fn fire_timeouts(&self, now: DateTime<Utc>) -> usize {
+        let mut fired = 0;
+
+        for runner in &self.runners {
+            match runner.fire_timeouts(now).await {
+                Ok(count) => fired += count,
+                Err(error) => {
+                    tracing::error!(
+                        error = ?error, saga = runner.name(),
+                        "Failed to handle saga timeouts"
+                    );
+                }
+            }
+        }
+
+        fired
+    }
+}
+
+/// Errors reading a saga event from a Kinesis record
+#[derive(thiserror::Error, Debug)]
+enum RecordError {
+    /// Utf8 conversion error
+    #[error("Utf8 conversion error: {0}")]
+    Utf8(#[from] Utf8Error),
+
+    /// Saga error
+    #[error("Saga error: {0}")]
+    Saga(#[from] Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, sync::Mutex};
+
+    use chrono::Duration;
+    use cqrs_es::persist::PersistenceError;
+    use serde_json::json;
+
+    use crate::{
+        domains::tasks::{self, Task},
+        process_managers::{
+            project_archive::{ProjectTasks, State},
+            saga::MemorySagaStore,
+            ProjectArchive,
+        },
+    };
+
+    use super::*;
+
+    const PROJECT_ID: &str = "acme#project-1";
+    const OWNER: &str = "user-1";
+
+    /// A Project with the given Tasks in it
+    struct FakeProjectTasks(Vec<&'static str>);
+
+    #[async_trait]
+    impl ProjectTasks for FakeProjectTasks {
+        async fn list(
+            &self,
+            _tenant: &str,
+            _project_id: &str,
+        ) -> Result<Vec<tasks::View>, PersistenceError> {
+            Ok(self
+                .0
+                .iter()
+                .map(|id| tasks::View {
+                    id: id.to_string(),
+                    tenant: "acme".to_string(),
+                    task: Task {
+                        id: id.to_string(),
+                        owner: OWNER.to_string(),
+                        ..Task::default()
+                    },
+                    ..tasks::View::default()
+                })
+                .collect())
+        }
+    }
+
+    /// A CommandBus that records the commands it accepts
+    #[derive(Default)]
+    struct FakeBus {
+        /// The command IDs of every accepted command, in order
+        accepted: Mutex<Vec<String>>,
+
+        /// Aggregates that reject every command
+        rejecting: BTreeSet<String>,
+
+        /// Aggregates that can't be reached the next time a command is issued to them
+        unavailable: Mutex<BTreeSet<String>>,
+    }
+
+    impl FakeBus {
+        fn accepted(&self) -> Vec<String> {
+            self.accepted.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl CommandBus for FakeBus {
+        async fn execute(
+            &self,
+            command: SagaCommand,
+            metadata: HashMap<String, String>,
+        ) -> Result<(), AggregateError<domains::Error>> {
+            let aggregate_id = command.aggregate_id();
+
+            if self.unavailable.lock().unwrap().remove(aggregate_id) {
+                return Err(AggregateError::DatabaseConnectionError(
+                    "Connection reset".into(),
+                ));
+            }
+
+            if self.rejecting.contains(aggregate_id) {
+                return Err(AggregateError::UserError(domains::Error::InvalidState {
+                    reason: "Task is deleted".to_string(),
+                }));
+            }
+
+            self.accepted
+                .lock()
+                .unwrap()
+                .push(metadata["command_id"].clone());
+
+            Ok(())
+        }
+    }
+
+    struct Harness {
+        runner: SagaRunner<ProjectArchive>,
+        store: Arc<MemorySagaStore>,
+        bus: Arc<FakeBus>,
+    }
+
+    impl Harness {
+        fn new(task_ids: Vec<&'static str>, bus: FakeBus) -> Self {
+            let store = Arc::new(MemorySagaStore::default());
+            let bus = Arc::new(bus);
+
+            let runner = SagaRunner::new(
+                ProjectArchive::new(Arc::new(FakeProjectTasks(task_ids))),
+                store.clone(),
+                bus.clone(),
+            );
+
+            Self { runner, store, bus }
+        }
+
+        async fn instance(&self) -> Instance {
+            self.store
+                .load(ProjectArchive::NAME, &format!("{PROJECT_ID}@3"))
+                .await
+                .unwrap()
+                .expect("The instance was saved")
+        }
+
+        async fn state(&self) -> State {
+            self.instance().await.state().unwrap()
+        }
+    }
+
+    fn project_archived() -> DomainEvent {
+        DomainEvent::new(
+            PROJECT_ID.to_string(),
+            "Project".to_string(),
+            3,
+            "Project:Archived".to_string(),
+            "1.0".to_string(),
+            json!({ "type": "Archived" }).to_string(),
+            json!({ "subject": OWNER }).to_string(),
+        )
+    }
+
+    fn task_archived(task_id: &str) -> DomainEvent {
+        DomainEvent::new(
+            task_id.to_string(),
+            "Task".to_string(),
+            5,
+            "Task:Archived".to_string(),
+            tasks::events::EVENT_VERSION.to_string(),
+            json!({ "type": "Archived" }).to_string(),
+            json!({
+                "saga": ProjectArchive::NAME,
+                "saga_correlation_id": format!("{PROJECT_ID}@3"),
+            })
+            .to_string(),
+        )
+    }
+
+    fn pending(ids: &[&str]) -> BTreeSet<String> {
+        ids.iter().map(ToString::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn redelivered_events_are_skipped() {
+        let harness = Harness::new(vec!["acme#task-1", "acme#task-2"], FakeBus::default());
+
+        harness.runner.dispatch(&project_archived()).await.unwrap();
+        harness.runner.dispatch(&project_archived()).await.unwrap();
+
+        assert_eq!(harness.bus.accepted().len(), 2);
+        assert!(harness
+            .instance()
+            .await
+            .handled
+            .contains(&project_archived().reference()));
+    }
+
+    #[tokio::test]
+    async fn accepted_commands_are_not_reissued_after_a_partial_failure() {
+        let bus = FakeBus {
+            unavailable: Mutex::new(pending(&["acme#task-2"])),
+            ..FakeBus::default()
+        };
+        let harness = Harness::new(vec!["acme#task-1", "acme#task-2"], bus);
+
+        let result = harness.runner.dispatch(&project_archived()).await;
+        assert!(matches!(result, Err(Error::Command(_))));
+
+        let accepted = harness.bus.accepted();
+        assert_eq!(accepted.len(), 1);
+
+        // The accepted command is recorded, but the event is retried from the earlier state
+        let instance = harness.instance().await;
+        assert!(instance.issued.contains(&accepted[0]));
+        assert!(instance.handled.is_empty());
+        assert_eq!(instance.timeout_at, None);
+
+        harness.runner.dispatch(&project_archived()).await.unwrap();
+
+        let retried = harness.bus.accepted();
+        assert_eq!(retried.len(), 2);
+        assert_eq!(retried[0], accepted[0]);
+        assert!(retried[1].contains("acme#task-2"));
+        assert_eq!(
+            harness.state().await.pending,
+            pending(&["acme#task-1", "acme#task-2"])
+        );
+    }
+
+    #[tokio::test]
+    async fn timeouts_fire_once_they_are_due() {
+        let harness = Harness::new(vec!["acme#task-1", "acme#task-2"], FakeBus::default());
+
+        harness.runner.dispatch(&project_archived()).await.unwrap();
+        harness
+            .runner
+            .dispatch(&task_archived("acme#task-1"))
+            .await
+            .unwrap();
+
+        assert_eq!(harness.runner.fire_timeouts(Utc::now()).await.unwrap(), 0);
+
+        let timeout_at = harness
+            .instance()
+            .await
+            .timeout_at
+            .expect("A timeout is set");
+        assert_eq!(harness.runner.fire_timeouts(timeout_at).await.unwrap(), 1);
+
+        // Only the Task that hasn't been archived yet is archived again
+        let accepted = harness.bus.accepted();
+        assert_eq!(accepted.len(), 3);
+        assert!(accepted[2].contains("timeout/") && accepted[2].contains("acme#task-2"));
+
+        let instance = harness.instance().await;
+        let state: State = instance.state().unwrap();
+        assert_eq!(state.retries, 1);
+        assert_eq!(state.pending, pending(&["acme#task-2"]));
+        assert!(instance.timeout_at.is_some_and(|at| at > timeout_at));
+
+        harness
+            .runner
+            .dispatch(&task_archived("acme#task-2"))
+            .await
+            .unwrap();
+
+        let instance = harness.instance().await;
+        assert!(instance.completed);
+        assert_eq!(instance.timeout_at, None);
+        assert_eq!(
+            harness
+                .runner
+                .fire_timeouts(Utc::now() + Duration::days(1))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_commands_are_compensated() {
+        let bus = FakeBus {
+            rejecting: pending(&["acme#task-2"]),
+            ..FakeBus::default()
+        };
+        let harness = Harness::new(vec!["acme#task-1", "acme#task-2"], bus);
+
+        harness.runner.dispatch(&project_archived()).await.unwrap();
+
+        // The rejected Task is recorded as failed, rather than waited on
+        assert_eq!(harness.bus.accepted().len(), 1);
+
+        let state = harness.state().await;
+        assert_eq!(state.pending, pending(&["acme#task-1"]));
+        assert_eq!(state.failed.keys().collect::<Vec<_>>(), vec!["acme#task-2"]);
+        assert!(state.failed["acme#task-2"].contains("Task is deleted"));
+        assert!(!harness.instance().await.completed);
+
+        harness
+            .runner
+            .dispatch(&task_archived("acme#task-1"))
+            .await
+            .unwrap();
+
+        assert!(harness.instance().await.completed);
+    }
+
+    #[tokio::test]
+    async fn rejecting_every_command_completes_the_saga() {
+        let bus = FakeBus {
+            rejecting: pending(&["acme#task-1"]),
+            ..FakeBus::default()
+        };
+        let harness = Harness::new(vec!["acme#task-1"], bus);
+
+        harness.runner.dispatch(&project_archived()).await.unwrap();
+
+        let instance = harness.instance().await;
+        assert!(instance.completed);
+        assert_eq!(instance.timeout_at, None);
+        assert!(harness.bus.accepted().is_empty());
+
+        let state: State = instance.state().unwrap();
+        assert!(state.failed.contains_key("acme#task-1"));
+    }
+
+    #[tokio::test]
+    async fn tasks_still_open_after_the_last_retry_are_recorded_as_failed() {
+        let harness = Harness::new(vec!["acme#task-1", "acme#task-2"], FakeBus::default());
+
+        harness.runner.dispatch(&project_archived()).await.unwrap();
+        harness
+            .runner
+            .dispatch(&task_archived("acme#task-1"))
+            .await
+            .unwrap();
+
+        while let Some(timeout_at) = harness.instance().await.timeout_at {
+            assert_eq!(harness.runner.fire_timeouts(timeout_at).await.unwrap(), 1);
+        }
+
+        let instance = harness.instance().await;
+        assert!(instance.completed);
+
+        let state: State = instance.state().unwrap();
+        assert_eq!(state.retries, 3);
+        assert!(state.pending.is_empty());
+        assert_eq!(state.failed.keys().collect::<Vec<_>>(), vec!["acme#task-2"]);
+    }
+}
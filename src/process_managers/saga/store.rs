@@ -0,0 +1,208 @@
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::{error::SdkError, types::AttributeValue};
+use chrono::{DateTime, Utc};
+use cqrs_es::persist::PersistenceError;
+
+use crate::config::Config;
+
+use super::{Error, Instance};
+
+/// The index used to find instances whose timeouts are due
+const TIMEOUT_INDEX: &str = "TimeoutIndex";
+
+/// Durable storage for saga instances
+#[async_trait]
+pub trait SagaStore: Send + Sync {
+    /// Load an instance, if it has been saved before
+    async fn load(&self, saga: &str, correlation_id: &str) -> Result<Option<Instance>, Error>;
+
+    /// Save an instance, failing with `Error::Conflict` if it changed since it was loaded. The
+    /// instance's version is advanced once it's saved.
+    async fn save(&self, instance: &mut Instance) -> Result<(), Error>;
+
+    /// The correlation IDs of up to `limit` instances of a saga with timeouts due by `now`
+    async fn due(&self, saga: &str, now: DateTime<Utc>, limit: i32) -> Result<Vec<String>, Error>;
+}
+
+/// Saga instances in a DynamoDB table keyed by `Saga` and `CorrelationId`
+///
+/// Instances with a timeout carry a numeric `TimeoutAt`, so the sparse `TimeoutIndex` lists only
+/// the instances that are waiting on one.
+pub struct DynamoSagaStore {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl DynamoSagaStore {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Initialize the saga store from the config
+    pub fn init(client: aws_sdk_dynamodb::Client, config: &Config) -> Arc<Self> {
+        Arc::new(Self::new(client, &config.tables.sagas))
+    }
+}
+
+#[async_trait]
+impl SagaStore for DynamoSagaStore {
+    async fn load(&self, saga: &str, correlation_id: &str) -> Result<Option<Instance>, Error> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("Saga", AttributeValue::S(saga.to_string()))
+            .key(
+                "CorrelationId",
+                AttributeValue::S(correlation_id.to_string()),
+            )
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        let Some(item) = output.item() else {
+            return Ok(None);
+        };
+
+        let instance = item
+            .get("Instance")
+            .and_then(|v| v.as_s().ok())
+            .ok_or_else(|| PersistenceError::DeserializationError("Missing Instance".into()))?;
+
+        let version = item
+            .get("Version")
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or_default();
+
+        Ok(Some(Instance {
+            version,
+            ..serde_json::from_str(instance)?
+        }))
+    }
+
+    async fn save(&self, instance: &mut Instance) -> Result<(), Error> {
+        let version = instance.version + 1;
+
+        let mut request = self
+            .client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("Saga", AttributeValue::S(instance.saga.clone()))
+            .item(
+                "CorrelationId",
+                AttributeValue::S(instance.correlation_id.clone()),
+            )
+            .item(
+                "Instance",
+                AttributeValue::S(serde_json::to_string(instance)?),
+            )
+            .item("Completed", AttributeValue::Bool(instance.completed))
+            .item("Version", AttributeValue::N(version.to_string()));
+
+        if let Some(timeout_at) = instance.timeout_at {
+            request = request.item(
+                "TimeoutAt",
+                AttributeValue::N(timeout_at.timestamp().to_string()),
+            );
+        }
+
+        request = if instance.version == 0 {
+            request.condition_expression("attribute_not_exists(Saga)")
+        } else {
+            request
+                .condition_expression("Version = :version")
+                .expression_attribute_values(
+                    ":version",
+                    AttributeValue::N(instance.version.to_string()),
+                )
+        };
+
+        match request.send().await {
+            Ok(_) => {
+                instance.version = version;
+
+                Ok(())
+            }
+            Err(SdkError::ServiceError(err))
+                if err.err().is_conditional_check_failed_exception() =>
+            {
+                Err(Error::Conflict(instance.to_string()))
+            }
+            Err(err) => Err(PersistenceError::ConnectionError(Box::new(err)).into()),
+        }
+    }
+
+    async fn due(&self, saga: &str, now: DateTime<Utc>, limit: i32) -> Result<Vec<String>, Error> {
+        let output = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name(TIMEOUT_INDEX)
+            .key_condition_expression("Saga = :saga AND TimeoutAt <= :now")
+            .expression_attribute_values(":saga", AttributeValue::S(saga.to_string()))
+            .expression_attribute_values(":now", AttributeValue::N(now.timestamp().to_string()))
+            .limit(limit)
+            .send()
+            .await
+            .map_err(|err| PersistenceError::ConnectionError(Box::new(err)))?;
+
+        Ok(output
+            .items()
+            .iter()
+            .filter_map(|item| item.get("CorrelationId")?.as_s().ok().cloned())
+            .collect())
+    }
+}
+
+/// Saga instances held in memory, for running sagas in-process
+#[derive(Default)]
+pub struct MemorySagaStore {
+    instances: Mutex<HashMap<(String, String), Instance>>,
+}
+
+#[async_trait]
+impl SagaStore for MemorySagaStore {
+    async fn load(&self, saga: &str, correlation_id: &str) -> Result<Option<Instance>, Error> {
+        let instances = self.instances.lock().expect("Saga store lock poisoned");
+
+        Ok(instances
+            .get(&(saga.to_string(), correlation_id.to_string()))
+            .cloned())
+    }
+
+    async fn save(&self, instance: &mut Instance) -> Result<(), Error> {
+        let mut instances = self.instances.lock().expect("Saga store lock poisoned");
+
+        let key = (instance.saga.clone(), instance.correlation_id.clone());
+        let stored_version = instances.get(&key).map_or(0, |stored| stored.version);
+
+        if stored_version != instance.version {
+            return Err(Error::Conflict(instance.to_string()));
+        }
+
+        instance.version += 1;
+        instances.insert(key, instance.clone());
+
+        Ok(())
+    }
+
+    async fn due(&self, saga: &str, now: DateTime<Utc>, limit: i32) -> Result<Vec<String>, Error> {
+        let instances = self.instances.lock().expect("Saga store lock poisoned");
+
+        Ok(instances
+            .values()
+            .filter(|instance| instance.saga == saga)
+            .filter(|instance| instance.timeout_at.is_some_and(|at| at <= now))
+            .take(limit.max(0) as usize)
+            .map(|instance| instance.correlation_id.clone())
+            .collect())
+    }
+}
@@ -0,0 +1,157 @@
+use std::{str::Utf8Error, sync::Arc};
+
+use aws_lambda_events::{
+    kinesis::{KinesisEvent, KinesisEventRecord},
+    streams::{KinesisBatchItemFailure, KinesisEventResponse},
+};
+use cqrs_es::persist::PersistenceError;
+use derive_new::new;
+use lambda_runtime::LambdaEvent;
+use tracing::Instrument;
+
+use crate::{
+    domains::{
+        self,
+        tasks::{
+            self,
+            inbox::{Inbox, InboxEntry},
+        },
+        DomainEvent, Upcasters,
+    },
+    utils::{metrics, telemetry},
+};
+
+/// The Task event types that change who a Task is assigned to, or whether it's listed at all
+const EVENT_TYPES: [&str; 5] = [
+    "Task:Assigned",
+    "Task:Unassigned",
+    "Task:Deleted",
+    "Task:Restored",
+    "Task:Erased",
+];
+
+/// The Inbox projector, which maintains the per-subject "my tasks" read model
+#[derive(Clone, new)]
+pub struct InboxProjector {
+    inbox: Arc<Inbox>,
+
+    /// Upcasters applied to Domain Event payloads before they are decoded
+    #[new(value = "Arc::new(domains::upcasters())")]
+    upcasters: Arc<Upcasters>,
+}
+
+impl InboxProjector {
+    /// Handle the Kinesis event and run the projection
+    pub async fn handle(
+        &self,
+        event: LambdaEvent<KinesisEvent>,
+    ) -> Result<KinesisEventResponse, lambda_runtime::Error> {
+        tracing::info!(
+            "Processing batch of {} events from Kinesis",
+            event.payload.records.len(),
+        );
+
+        let mut batch_item_failures = Vec::new();
+
+        for record in event.payload.records.iter() {
+            let sequence_number = record.kinesis.sequence_number.clone();
+
+            let span =
+                tracing::info_span!("projector.inbox.record", sequence_number = sequence_number);
+
+            if let Err(error) = self.handle_record(record).instrument(span).await {
+                tracing::error!(
+                    error = ?error, sequence_number = sequence_number,
+                    "Failed to process event"
+                );
+
+                metrics::increment(
+                    metrics::BATCH_ITEM_FAILURES,
+                    &[("consumer", "projector.inbox")],
+                );
+
+                batch_item_failures.push(KinesisBatchItemFailure {
+                    item_identifier: sequence_number,
+                });
+            };
+        }
+
+        Ok(KinesisEventResponse {
+            batch_item_failures,
+        })
+    }
+
+    async fn handle_record(&self, record: &KinesisEventRecord) -> Result<(), Error> {
+        let record_data = std::str::from_utf8(&record.kinesis.data).map_err(Error::Utf8)?;
+        let event: DomainEvent = serde_json::from_str(record_data).map_err(Error::Json)?;
+
+        telemetry::continue_trace(&event);
+        metrics::observe_lag("projector.inbox", event.recorded_at);
+
+        if event.entity != tasks::AGGREGATE_TYPE
+            || !EVENT_TYPES.contains(&event.event_type.as_str())
+        {
+            return Ok(());
+        }
+
+        tracing::info!(
+            aggregate_id = event.id,
+            event_type = event.event_type,
+            correlation_id = event.correlation_id,
+            causation_id = event.causation_id,
+            "Projecting event into the inbox"
+        );
+
+        let event = self
+            .upcasters
+            .upcast_domain_event(event)
+            .map_err(Error::Json)?;
+        let payload: tasks::Event = serde_json::from_str(&event.payload).map_err(Error::Json)?;
+
+        match payload {
+            tasks::Event::Assigned {
+                id,
+                assignee,
+                assigned_by,
+                updated_at,
+            } => {
+                let entry = InboxEntry {
+                    task_id: id,
+                    assigned_by,
+                    assigned_at: updated_at,
+                };
+
+                self.inbox
+                    .assign(&event.tenant, &event.id, &assignee, &entry)
+                    .await?
+            }
+            tasks::Event::Unassigned { assignee, .. } => {
+                self.inbox
+                    .unassign(&event.tenant, &event.id, &assignee)
+                    .await?
+            }
+            tasks::Event::Deleted { .. } => self.inbox.set_hidden(&event.id, true).await?,
+            tasks::Event::Restored { .. } => self.inbox.set_hidden(&event.id, false).await?,
+            tasks::Event::Erased { .. } => self.inbox.remove_task(&event.id).await?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Inbox projector errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Utf8 conversion error
+    #[error("Utf8 conversion error: {0}")]
+    Utf8(#[from] Utf8Error),
+
+    /// JSON conversion error
+    #[error("JSON conversion error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// Inbox storage error
+    #[error("Inbox storage error: {0}")]
+    Persistence(#[from] PersistenceError),
+}
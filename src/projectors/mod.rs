@@ -1,2 +1,8 @@
+/// Object storage for projections
+pub mod objects;
+
 /// The S3 Audit Projector
 pub mod s3_audit;
+
+/// The per-subject Task Inbox Projector
+pub mod inbox;
@@ -0,0 +1,442 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::Instrument;
+
+/// The content type of the JSON documents projections write
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// The content type objects are read back with when none was stored
+pub const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// The suffix of the file an object's content type and metadata are kept in on the filesystem
+const INFO_SUFFIX: &str = ".metadata.json";
+
+/// An object, with the content type and user metadata it's stored with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Object {
+    /// The object's contents
+    pub body: Vec<u8>,
+
+    /// The MIME type of the contents
+    pub content_type: String,
+
+    /// User metadata, such as the correlation ID of an audited event
+    pub metadata: HashMap<String, String>,
+}
+
+impl Object {
+    /// A JSON document
+    pub fn json(body: Vec<u8>, metadata: HashMap<String, String>) -> Self {
+        Self {
+            body,
+            content_type: JSON_CONTENT_TYPE.to_string(),
+            metadata,
+        }
+    }
+}
+
+/// Storage for the objects a projection writes, such as the audit trail
+///
+/// Every backend accepts the same keys: relative, `/`-separated paths without empty or `..`
+/// segments, as they'd be laid out under an S3 bucket. So a projection writes the same layout
+/// whether it's deployed, running locally, or under test.
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Write an object, replacing any that's already stored under the key
+    async fn put(&self, key: &str, object: Object) -> Result<(), Error>;
+
+    /// Read an object, or `None` if there isn't one under the key
+    async fn get(&self, key: &str) -> Result<Option<Object>, Error>;
+}
+
+/// An ObjectStore backed by an S3 bucket
+pub struct S3ObjectStore {
+    client: aws_sdk_s3::Client,
+    bucket_name: String,
+}
+
+impl S3ObjectStore {
+    /// Create a new instance
+    pub fn new(client: aws_sdk_s3::Client, bucket_name: &str) -> Self {
+        Self {
+            client,
+            bucket_name: bucket_name.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3ObjectStore {
+    async fn put(&self, key: &str, object: Object) -> Result<(), Error> {
+        validate_key(key)?;
+
+        let span = tracing::info_span!("s3.put_object", bucket = self.bucket_name, key = key);
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .content_type(object.content_type)
+            .set_metadata(Some(object.metadata))
+            .body(ByteStream::from(object.body))
+            .send()
+            .instrument(span)
+            .await
+            .map_err(|err| Error::Store(Box::new(err)))?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Object>, Error> {
+        validate_key(key)?;
+
+        let span = tracing::info_span!("s3.get_object", bucket = self.bucket_name, key = key);
+
+        let result = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .send()
+            .instrument(span)
+            .await;
+
+        let output = match result {
+            Ok(output) => output,
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_no_such_key()) => {
+                return Ok(None)
+            }
+            Err(err) => return Err(Error::Store(Box::new(err))),
+        };
+
+        let content_type = output
+            .content_type
+            .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+        let metadata = output.metadata.unwrap_or_default();
+        let body = output
+            .body
+            .collect()
+            .await
+            .map_err(|err| Error::Store(Box::new(err)))?
+            .to_vec();
+
+        Ok(Some(Object {
+            body,
+            content_type,
+            metadata,
+        }))
+    }
+}
+
+/// An ObjectStore that writes each object to a file under a root directory, for local
+/// development
+///
+/// Each object's content type and metadata are kept alongside it, in a `.metadata.json` file
+/// with the same name.
+pub struct FileObjectStore {
+    root: PathBuf,
+}
+
+/// The content type and metadata of a stored file
+#[derive(Serialize, Deserialize)]
+struct ObjectInfo {
+    content_type: String,
+
+    // Sorted, so the files are stable to diff
+    metadata: BTreeMap<String, String>,
+}
+
+impl FileObjectStore {
+    /// Create a new instance
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// The paths of an object's contents and its info file
+    fn paths(&self, key: &str) -> Result<(PathBuf, PathBuf), Error> {
+        validate_key(key)?;
+
+        // An object's info file can't be addressed as an object of its own
+        if key.ends_with(INFO_SUFFIX) {
+            return Err(Error::InvalidKey(key.to_string()));
+        }
+
+        Ok((
+            self.root.join(key),
+            self.root.join(format!("{key}{INFO_SUFFIX}")),
+        ))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FileObjectStore {
+    async fn put(&self, key: &str, object: Object) -> Result<(), Error> {
+        let (path, info_path) = self.paths(key)?;
+
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        let info = ObjectInfo {
+            content_type: object.content_type,
+            metadata: object.metadata.into_iter().collect(),
+        };
+        let info = serde_json::to_vec_pretty(&info).map_err(|err| Error::Store(err.into()))?;
+
+        tokio::fs::write(&path, object.body).await?;
+        tokio::fs::write(info_path, info).await?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Object>, Error> {
+        let (path, info_path) = self.paths(key)?;
+
+        let body = match tokio::fs::read(&path).await {
+            Ok(body) => body,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        // Files copied in by hand may not have an info file
+        let info = match tokio::fs::read(&info_path).await {
+            Ok(info) => serde_json::from_slice(&info).map_err(|err| Error::Store(err.into()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => ObjectInfo {
+                content_type: DEFAULT_CONTENT_TYPE.to_string(),
+                metadata: BTreeMap::new(),
+            },
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Some(Object {
+            body,
+            content_type: info.content_type,
+            metadata: info.metadata.into_iter().collect(),
+        }))
+    }
+}
+
+/// An ObjectStore held in memory, for tests and examples
+#[derive(Default)]
+pub struct MemoryObjectStore {
+    objects: RwLock<BTreeMap<String, Object>>,
+}
+
+impl MemoryObjectStore {
+    /// The keys of every stored object, in order
+    pub async fn keys(&self) -> Vec<String> {
+        self.objects.read().await.keys().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MemoryObjectStore {
+    async fn put(&self, key: &str, object: Object) -> Result<(), Error> {
+        validate_key(key)?;
+
+        self.objects.write().await.insert(key.to_string(), object);
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Object>, Error> {
+        validate_key(key)?;
+
+        Ok(self.objects.read().await.get(key).cloned())
+    }
+}
+
+/// Check that a key is a relative path every backend lays out the same way
+fn validate_key(key: &str) -> Result<(), Error> {
+    let valid = !key.is_empty()
+        && key
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..");
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidKey(key.to_string()))
+    }
+}
+
+/// Object store errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The store couldn't be reached, or rejected the object
+    #[error("Object store error: {0}")]
+    Store(Box<dyn std::error::Error + Send + Sync>),
+
+    /// A local file couldn't be read or written
+    #[error("Object file error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The key isn't a valid relative path
+    #[error("Invalid object key: {0}")]
+    InvalidKey(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A FileObjectStore under a new temporary directory, removed when it's dropped
+    struct TempStore {
+        root: PathBuf,
+        store: FileObjectStore,
+    }
+
+    impl TempStore {
+        fn new() -> Self {
+            let root = std::env::temp_dir().join(format!("objects-{}", ulid::Ulid::new()));
+
+            Self {
+                store: FileObjectStore::new(&root),
+                root,
+            }
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn object() -> Object {
+        Object::json(
+            br#"{"id":"1"}"#.to_vec(),
+            HashMap::from([
+                ("correlation-id".to_string(), "request-1".to_string()),
+                ("causation-id".to_string(), "request-1/cause".to_string()),
+            ]),
+        )
+    }
+
+    #[test]
+    fn keys_are_relative_paths_without_empty_or_dot_segments() {
+        for key in ["events/Task/1-1.json", "a", "a/b.c/..d"] {
+            assert!(validate_key(key).is_ok(), "{key}");
+        }
+
+        for key in [
+            "",
+            "/events/1.json",
+            "events/",
+            "events//1.json",
+            ".",
+            "events/./1.json",
+            "..",
+            "events/../1.json",
+        ] {
+            assert!(
+                matches!(validate_key(key), Err(Error::InvalidKey(invalid)) if invalid == key),
+                "{key}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_keys_are_rejected_by_every_store() {
+        let temp = TempStore::new();
+        let memory = MemoryObjectStore::default();
+        let stores: [&dyn ObjectStore; 2] = [&temp.store, &memory];
+
+        for store in stores {
+            assert!(matches!(
+                store.put("../outside.json", object()).await,
+                Err(Error::InvalidKey(_))
+            ));
+            assert!(matches!(store.get("").await, Err(Error::InvalidKey(_))));
+        }
+
+        assert!(!temp.root.exists());
+    }
+
+    #[tokio::test]
+    async fn files_are_laid_out_like_the_keys() {
+        let temp = TempStore::new();
+
+        temp.store
+            .put("tenants/acme/events/Task/1-1.json", object())
+            .await
+            .unwrap();
+
+        let path = temp.root.join("tenants/acme/events/Task/1-1.json");
+        assert_eq!(std::fs::read(&path).unwrap(), object().body);
+
+        let info: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(
+                temp.root
+                    .join("tenants/acme/events/Task/1-1.json.metadata.json"),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(info["content_type"], JSON_CONTENT_TYPE);
+        assert_eq!(info["metadata"]["correlation-id"], "request-1");
+
+        // Info files can't be read or overwritten as objects of their own
+        assert!(matches!(
+            temp.store
+                .get("tenants/acme/events/Task/1-1.json.metadata.json")
+                .await,
+            Err(Error::InvalidKey(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn objects_round_trip_with_their_content_type_and_metadata() {
+        let temp = TempStore::new();
+        let memory = MemoryObjectStore::default();
+        let stores: [&dyn ObjectStore; 2] = [&temp.store, &memory];
+
+        let text = Object {
+            body: b"hello".to_vec(),
+            content_type: "text/plain".to_string(),
+            metadata: HashMap::new(),
+        };
+
+        for store in stores {
+            assert_eq!(store.get("events/1.json").await.unwrap(), None);
+
+            store.put("events/1.json", object()).await.unwrap();
+            store.put("notes/hello.txt", text.clone()).await.unwrap();
+
+            assert_eq!(store.get("events/1.json").await.unwrap(), Some(object()));
+            assert_eq!(
+                store.get("notes/hello.txt").await.unwrap(),
+                Some(text.clone())
+            );
+
+            // Writing again replaces the object, including its metadata
+            store.put("events/1.json", text.clone()).await.unwrap();
+            assert_eq!(
+                store.get("events/1.json").await.unwrap(),
+                Some(text.clone())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn files_without_an_info_file_are_read_as_untyped() {
+        let temp = TempStore::new();
+
+        std::fs::create_dir_all(temp.root.join("events")).unwrap();
+        std::fs::write(temp.root.join("events/copied.json"), b"{}").unwrap();
+
+        let object = temp.store.get("events/copied.json").await.unwrap().unwrap();
+        assert_eq!(object.body, b"{}");
+        assert_eq!(object.content_type, DEFAULT_CONTENT_TYPE);
+        assert!(object.metadata.is_empty());
+    }
+}
@@ -1,22 +1,106 @@
-use std::str::Utf8Error;
+use std::{collections::HashMap, str::Utf8Error, sync::Arc};
 
 use aws_lambda_events::{
     kinesis::{KinesisEvent, KinesisEventRecord},
     streams::{KinesisBatchItemFailure, KinesisEventResponse},
 };
-use aws_sdk_s3::{error::SdkError, operation::put_object::PutObjectError, primitives::ByteStream};
 use derive_new::new;
 use lambda_runtime::LambdaEvent;
+use tracing::Instrument;
 
 use crate::{
-    domains::{tasks, DomainEvent},
-    utils,
+    domains::{
+        self, projects,
+        shredding::{self, Shredder},
+        tasks, tenants, DomainEvent, Upcasters,
+    },
+    utils::{self, metrics, telemetry},
 };
 
+use super::objects::{self, Object, ObjectStore};
+
 /// The S3 Audit projector
-#[derive(Clone, Debug, new)]
+///
+/// Each Domain Event is archived as a JSON object, with sensitive fields left sealed. The store
+/// is an S3 bucket when deployed, but any ObjectStore can stand in for it, so the projector can
+/// run offline:
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use aws_lambda_events::kinesis::KinesisEvent;
+/// use base64::{engine::general_purpose::STANDARD, Engine};
+/// use event_driven_architecture::{
+///     domains::{
+///         shredding::{sensitive_fields, MemoryKeyStore, Shredder},
+///         DomainEvent,
+///     },
+///     projectors::{
+///         objects::{MemoryObjectStore, ObjectStore, JSON_CONTENT_TYPE},
+///         s3_audit::S3Audit,
+///     },
+/// };
+/// use lambda_runtime::{Context, LambdaEvent};
+/// use serde_json::json;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let store = Arc::new(MemoryObjectStore::default());
+/// let shredder = Arc::new(Shredder::new(
+///     Arc::new(MemoryKeyStore::default()),
+///     sensitive_fields(),
+/// ));
+/// let audit = S3Audit::new(store.clone(), shredder);
+///
+/// let event = DomainEvent {
+///     tenant: "acme".to_string(),
+///     ..DomainEvent::new(
+///         "acme#1".to_string(),
+///         "Task".to_string(),
+///         1,
+///         "Task:Deleted".to_string(),
+///         "2.6".to_string(),
+///         json!({ "type": "Deleted", "id": "acme#1", "updated_at": "2024-01-01T00:00:00Z" })
+///             .to_string(),
+///         "{}".to_string(),
+///     )
+/// };
+///
+/// let records: KinesisEvent = serde_json::from_value(json!({
+///     "Records": [{
+///         "kinesis": {
+///             "approximateArrivalTimestamp": 1704067200.0,
+///             "data": STANDARD.encode(serde_json::to_vec(&event).unwrap()),
+///             "sequenceNumber": "1",
+///         },
+///     }],
+/// }))
+/// .unwrap();
+///
+/// let response = audit
+///     .handle(LambdaEvent::new(records, Context::default()))
+///     .await
+///     .unwrap();
+/// assert!(response.batch_item_failures.is_empty());
+///
+/// let key = S3Audit::key(&event);
+/// assert_eq!(key, "tenants/acme/events/Task/1-1.json");
+///
+/// let object = store.get(&key).await.unwrap().unwrap();
+/// assert_eq!(object.content_type, JSON_CONTENT_TYPE);
+/// # }
+/// ```
+#[derive(Clone, new)]
 pub struct S3Audit {
-    client: aws_sdk_s3::Client,
+    /// Where Domain Events are audited, which is an S3 bucket when deployed
+    store: Arc<dyn ObjectStore>,
+
+    /// Opens sealed fields for inspection, and redacts them for erased aggregates
+    shredder: Arc<Shredder>,
+
+    /// Upcasters applied to Domain Event payloads before they are decoded
+    #[new(value = "Arc::new(domains::upcasters())")]
+    upcasters: Arc<Upcasters>,
 }
 
 impl S3Audit {
@@ -41,12 +125,22 @@ impl S3Audit {
                 record.event_id
             );
 
-            if let Err(error) = self.handle_record(record).await {
+            let span = tracing::info_span!(
+                "projector.s3_audit.record",
+                sequence_number = sequence_number
+            );
+
+            if let Err(error) = self.handle_record(record).instrument(span).await {
                 tracing::error!(
                     error = ?error, sequence_number = sequence_number,
                     "Failed to process event"
                 );
 
+                metrics::increment(
+                    metrics::BATCH_ITEM_FAILURES,
+                    &[("consumer", "projector.s3_audit")],
+                );
+
                 batch_item_failures.push(KinesisBatchItemFailure {
                     item_identifier: sequence_number,
                 });
@@ -59,39 +153,94 @@ impl S3Audit {
     }
 
     async fn handle_record(&self, record: &KinesisEventRecord) -> Result<(), Error> {
-        let bucket_name = std::env::var("AUDIT_BUCKET_NAME").unwrap_or_default();
-
         let record_data = std::str::from_utf8(&record.kinesis.data)
             .map_err(Error::Utf8)?
             .to_string();
         let event: DomainEvent = serde_json::from_str(&record_data).map_err(Error::Json)?;
 
+        telemetry::continue_trace(&event);
+        metrics::observe_lag("projector.s3_audit", event.recorded_at);
+
+        // Sensitive fields are audited sealed, so erasing an aggregate shreds its audit trail too
+        let event = self.shredder.redact_domain_event(event).await?;
+
         println!(">- event -> {:?}", event);
 
-        if event.entity == tasks::AGGREGATE_TYPE {
-            let payload: tasks::Event =
-                serde_json::from_str(&event.payload).map_err(Error::Json)?;
-            if let tasks::Event::Updated { update, .. } = payload {
-                if let utils::Update::Value(summary) = update.summary {
-                    if summary == "5" {
-                        return Err(Error::InvalidSummary(summary));
+        tracing::info!(
+            aggregate_id = event.id,
+            event_type = event.event_type,
+            correlation_id = event.correlation_id,
+            causation_id = event.causation_id,
+            "Auditing event"
+        );
+
+        self.inspect(event.clone()).await?;
+
+        let key = Self::key(&event);
+
+        let metadata = HashMap::from([
+            ("correlation-id".to_string(), event.correlation_id.clone()),
+            ("causation-id".to_string(), event.causation_id.clone()),
+        ]);
+
+        let body = serde_json::to_vec(&event).map_err(Error::Json)?;
+
+        self.store.put(&key, Object::json(body, metadata)).await?;
+
+        Ok(())
+    }
+
+    /// The key an event is audited under
+    ///
+    /// Keys are partitioned by tenant, so access can be granted per tenant prefix, and use the
+    /// aggregate's own ID within it. Events recorded before multi-tenancy have no tenant, and keep
+    /// the original key layout.
+    pub fn key(event: &DomainEvent) -> String {
+        if event.tenant.is_empty() {
+            return format!(
+                "events/{}/{}-{}.json",
+                event.entity, event.id, event.sequence
+            );
+        }
+
+        let id = tenants::split_id(&event.id).map_or(event.id.as_str(), |(_, id)| id);
+
+        format!(
+            "tenants/{}/events/{}/{}-{}.json",
+            event.tenant, event.entity, id, event.sequence
+        )
+    }
+
+    /// Decode the event as its own aggregate's event type, so malformed events are retried
+    /// rather than archived
+    async fn inspect(&self, event: DomainEvent) -> Result<(), Error> {
+        let opened = self.shredder.open_domain_event(event).await?;
+        let upcasted = self
+            .upcasters
+            .upcast_domain_event(opened)
+            .map_err(Error::Json)?;
+
+        match upcasted.entity.as_str() {
+            tasks::AGGREGATE_TYPE => {
+                let payload: tasks::Event =
+                    serde_json::from_str(&upcasted.payload).map_err(Error::Json)?;
+                if let tasks::Event::Updated { update, .. } = payload {
+                    if let utils::Update::Value(summary) = update.summary {
+                        if summary == "5" {
+                            return Err(Error::InvalidSummary(summary));
+                        }
                     }
                 }
             }
+            projects::AGGREGATE_TYPE => {
+                serde_json::from_str::<projects::Event>(&upcasted.payload).map_err(Error::Json)?;
+            }
+            entity => {
+                // Archive events from newer aggregate types as-is, so the audit trail is complete
+                tracing::warn!(entity = entity, "Auditing an event for an unknown entity");
+            }
         }
 
-        self.client
-            .put_object()
-            .bucket(bucket_name)
-            .key(format!(
-                "events/{}/{}-{}.json",
-                event.entity, event.id, event.sequence
-            ))
-            .body(ByteStream::from(record_data.into_bytes()))
-            .send()
-            .await
-            .map_err(Error::S3PutError)?;
-
         Ok(())
     }
 }
@@ -111,7 +260,138 @@ pub enum Error {
     #[error("Invalid summary: {0}")]
     InvalidSummary(String),
 
-    /// S3 Put Object error
-    #[error("S3 Put Object error: {0}")]
-    S3PutError(#[from] SdkError<PutObjectError>),
+    /// Object store error
+    #[error("Object store error: {0}")]
+    Store(#[from] objects::Error),
+
+    /// Crypto-shredding error
+    #[error("Shredding error: {0}")]
+    Shredding(#[from] shredding::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use lambda_runtime::Context;
+    use serde_json::json;
+
+    use crate::{
+        domains::shredding::{sensitive_fields, MemoryKeyStore},
+        projectors::objects::{MemoryObjectStore, JSON_CONTENT_TYPE},
+    };
+
+    use super::*;
+
+    fn audit() -> (S3Audit, Arc<MemoryObjectStore>) {
+        let store = Arc::new(MemoryObjectStore::default());
+        let shredder = Arc::new(Shredder::new(
+            Arc::new(MemoryKeyStore::default()),
+            sensitive_fields(),
+        ));
+
+        (S3Audit::new(store.clone(), shredder), store)
+    }
+
+    fn deleted(id: &str, tenant: &str) -> DomainEvent {
+        DomainEvent {
+            tenant: tenant.to_string(),
+            correlation_id: "request-1".to_string(),
+            causation_id: "request-1/cause".to_string(),
+            ..DomainEvent::new(
+                id.to_string(),
+                tasks::AGGREGATE_TYPE.to_string(),
+                2,
+                "Task:Deleted".to_string(),
+                tasks::events::EVENT_VERSION.to_string(),
+                json!({ "type": "Deleted", "id": id, "updated_at": "2024-01-01T00:00:00Z" })
+                    .to_string(),
+                "{}".to_string(),
+            )
+        }
+    }
+
+    /// A batch of Kinesis records, numbered from 1, holding each of the given payloads
+    fn batch(payloads: &[Vec<u8>]) -> LambdaEvent<KinesisEvent> {
+        let records: Vec<_> = payloads
+            .iter()
+            .enumerate()
+            .map(|(i, data)| {
+                json!({
+                    "kinesis": {
+                        "approximateArrivalTimestamp": 1704067200.0,
+                        "data": STANDARD.encode(data),
+                        "sequenceNumber": (i + 1).to_string(),
+                    },
+                })
+            })
+            .collect();
+
+        let records = serde_json::from_value(json!({ "Records": records })).unwrap();
+
+        LambdaEvent::new(records, Context::default())
+    }
+
+    #[tokio::test]
+    async fn events_are_audited_with_their_trace_ids() {
+        let (audit, store) = audit();
+        let event = deleted("acme#1", "acme");
+
+        let response = audit
+            .handle(batch(&[serde_json::to_vec(&event).unwrap()]))
+            .await
+            .unwrap();
+        assert!(response.batch_item_failures.is_empty());
+
+        assert_eq!(store.keys().await, ["tenants/acme/events/Task/1-2.json"]);
+
+        let object = store
+            .get("tenants/acme/events/Task/1-2.json")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(object.content_type, JSON_CONTENT_TYPE);
+        assert_eq!(object.metadata["correlation-id"], "request-1");
+        assert_eq!(object.metadata["causation-id"], "request-1/cause");
+
+        let audited: DomainEvent = serde_json::from_slice(&object.body).unwrap();
+        assert_eq!(audited.id, "acme#1");
+        assert_eq!(audited.payload, event.payload);
+    }
+
+    #[tokio::test]
+    async fn records_that_cant_be_read_are_retried_without_holding_up_the_rest() {
+        let (audit, store) = audit();
+
+        // A payload that doesn't decode as a Task event
+        let malformed = DomainEvent {
+            payload: json!({ "type": "Deleted" }).to_string(),
+            ..deleted("acme#2", "acme")
+        };
+
+        let response = audit
+            .handle(batch(&[
+                b"not json".to_vec(),
+                serde_json::to_vec(&deleted("acme#1", "acme")).unwrap(),
+                serde_json::to_vec(&malformed).unwrap(),
+            ]))
+            .await
+            .unwrap();
+
+        let failed: Vec<_> = response
+            .batch_item_failures
+            .iter()
+            .filter_map(|failure| failure.item_identifier.as_deref())
+            .collect();
+        assert_eq!(failed, ["1", "3"]);
+        assert_eq!(store.keys().await, ["tenants/acme/events/Task/1-2.json"]);
+    }
+
+    #[test]
+    fn events_without_a_tenant_keep_the_original_key_layout() {
+        assert_eq!(S3Audit::key(&deleted("1", "")), "events/Task/1-2.json");
+        assert_eq!(
+            S3Audit::key(&deleted("acme#1", "acme")),
+            "tenants/acme/events/Task/1-2.json"
+        );
+    }
 }
@@ -2,17 +2,30 @@ use aws_lambda_events::{
     dynamodb::{Event, EventRecord},
     streams::{DynamoDbBatchItemFailure, DynamoDbEventResponse},
 };
+use std::{collections::HashMap, sync::Arc};
+
 use aws_sdk_kinesis::primitives::Blob;
 use derive_new::new;
 use lambda_runtime::LambdaEvent;
 use serde::{Deserialize, Serialize};
+use tracing::Instrument;
 
-use crate::domains::DomainEvent;
+use crate::{
+    domains::{shredding::Shredder, DomainEvent},
+    utils::{metrics, telemetry},
+};
 
 /// The Kinesis Publisher
 #[derive(Clone, Debug, new)]
 pub struct Kinesis {
     client: aws_sdk_kinesis::Client,
+
+    /// The Kinesis stream Domain Events are published to
+    stream_name: String,
+
+    /// Redacts sealed fields for aggregates that were erased before their events were published,
+    /// and finishes erasures that were committed before their key could be destroyed
+    shredder: Arc<Shredder>,
 }
 
 /// The Event Log Record decoded from the DynamoDB change "new image"
@@ -40,15 +53,22 @@ impl TryFrom<EventLogRecord> for DomainEvent {
         let metadata =
             String::from_utf8(event.metadata).expect("Cannot convert the Metadata to a String");
 
-        Ok(DomainEvent::new(
-            event.aggregate_id,
-            event.aggregate_type,
-            event.aggregate_id_sequence,
-            event.event_type,
-            event.event_version,
-            payload,
-            metadata,
-        ))
+        let mut values = serde_json::from_str::<HashMap<String, String>>(&metadata)?;
+
+        Ok(DomainEvent {
+            tenant: values.remove("tenant").unwrap_or_default(),
+            correlation_id: values.remove("correlation_id").unwrap_or_default(),
+            causation_id: values.remove("causation_id").unwrap_or_default(),
+            ..DomainEvent::new(
+                event.aggregate_id,
+                event.aggregate_type,
+                event.aggregate_id_sequence,
+                event.event_type,
+                event.event_version,
+                payload,
+                metadata,
+            )
+        })
     }
 }
 
@@ -71,12 +91,16 @@ impl Kinesis {
 
                 tracing::info!("Handling record id: {}", event_id);
 
-                if let Err(error) = self.handle_record(&record).await {
+                let span = tracing::info_span!("publisher.record", event_id = event_id);
+
+                if let Err(error) = self.handle_record(record).instrument(span).await {
                     tracing::error!(
                         error = ?error, event_id = event_id,
                         "Failed to process event"
                     );
 
+                    metrics::increment(metrics::BATCH_ITEM_FAILURES, &[("consumer", "publisher")]);
+
                     batch_item_failures.push(DynamoDbBatchItemFailure {
                         item_identifier: Some(event_id),
                     });
@@ -96,29 +120,63 @@ impl Kinesis {
     }
 
     async fn handle_record(&self, record: &EventRecord) -> Result<(), lambda_runtime::Error> {
-        let stream_name = std::env::var("EVENT_STREAM_NAME").unwrap_or_default();
+        let stream_name = &self.stream_name;
 
         let item = &record.change.new_image;
         let event_log: EventLogRecord = serde_dynamo::from_item(item.clone())?;
 
+        let event = DomainEvent {
+            recorded_at: Some(record.change.approximate_creation_date_time),
+            ..event_log.clone().try_into()?
+        };
+
+        telemetry::continue_trace(&event);
+
         tracing::info!(
+            correlation_id = event.correlation_id,
+            causation_id = event.causation_id,
             "Publishing domain event {} for id {} to {}",
             event_log.event_type,
             event_log.aggregate_id,
             stream_name
         );
 
-        let event: DomainEvent = event_log.clone().try_into()?;
+        // Finish an erasure whose key couldn't be destroyed when it was committed. Destroying a key
+        // again is harmless, and a failure here is retried with the batch.
+        if self.shredder.is_erasure(&event.event_type) {
+            self.shredder.erase(&event.id).await?;
+        }
+
+        // Sensitive fields stay sealed on the stream, so they can still be shredded downstream
+        let event = self.shredder.redact_domain_event(event).await?;
+
         let data = serde_json::to_string(&event)?;
 
+        let partition_key = event.partition_key();
+
+        let span = tracing::info_span!(
+            "kinesis.put_record",
+            stream_name = stream_name,
+            partition_key = partition_key,
+        );
+
         self.client
             .put_record()
             .stream_name(stream_name)
-            .partition_key(event_log.aggregate_type)
+            .partition_key(partition_key)
             .data(Blob::new(data))
             .send()
+            .instrument(span)
             .await?;
 
+        metrics::increment(
+            metrics::EVENTS_PUBLISHED,
+            &[
+                ("aggregate_type", &event.entity),
+                ("event_type", &event.event_type),
+            ],
+        );
+
         Ok(())
     }
 }
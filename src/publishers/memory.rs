@@ -0,0 +1,377 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use cqrs_es::{Aggregate, DomainEvent as _, EventEnvelope};
+use derive_new::new;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::{
+    domains::{
+        shredding::{self, Shredder},
+        DomainEvent,
+    },
+    runners::MemoryStream,
+    utils::metrics,
+};
+
+/// How many times publishing an event is retried before it's set aside for redelivery
+const MAX_RETRIES: u32 = 5;
+
+/// The wait before the first retry, which doubles with each retry after it
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The in-memory Publisher, which hands committed events straight to an in-process stream
+///
+/// It runs as a Query on the CqrsFramework, so events are published in the order each aggregate
+/// commits them, in the same shape the Kinesis publisher gives them: sensitive fields stay sealed,
+/// and the tenant and correlation IDs are lifted out of the metadata.
+///
+/// Events are committed before they're published, so a failure can't fail the command. Failures
+/// are retried with backoff, and an event that still can't be published is kept as a dead letter
+/// and redelivered later, along with any events for the same aggregate committed after it, so
+/// the stream never skips or reorders an aggregate's events. Clones share their dead letters.
+#[derive(Clone, new)]
+pub struct InMemory {
+    stream: MemoryStream,
+
+    /// Seals sensitive fields, as they are in the event log the Kinesis publisher reads from
+    shredder: Arc<Shredder>,
+
+    /// Committed events that haven't been published yet, in the order they were committed
+    #[new(default)]
+    dead_letters: Arc<Mutex<VecDeque<Unpublished>>>,
+}
+
+/// An event payload that can be serialized, whichever aggregate it belongs to
+trait Payload: Send + Sync {
+    fn to_value(&self) -> Result<Value, serde_json::Error>;
+}
+
+impl<T: Serialize + Send + Sync> Payload for T {
+    fn to_value(&self) -> Result<Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+}
+
+/// A committed event waiting to be published
+struct Unpublished {
+    aggregate_id: String,
+    aggregate_type: String,
+    sequence: usize,
+    event_type: String,
+    event_version: String,
+    payload: Box<dyn Payload>,
+    metadata: HashMap<String, String>,
+}
+
+impl Unpublished {
+    fn new<A>(envelope: &EventEnvelope<A>) -> Self
+    where
+        A: Aggregate,
+        A::Event: 'static,
+    {
+        Self {
+            aggregate_id: envelope.aggregate_id.clone(),
+            aggregate_type: A::aggregate_type(),
+            sequence: envelope.sequence,
+            event_type: envelope.payload.event_type(),
+            event_version: envelope.payload.event_version(),
+            payload: Box::new(envelope.payload.clone()),
+            metadata: envelope.metadata.clone(),
+        }
+    }
+}
+
+impl InMemory {
+    /// How many committed events are waiting to be redelivered
+    pub async fn dead_letters(&self) -> usize {
+        self.dead_letters.lock().await.len()
+    }
+
+    /// Try to publish every dead letter again, returning how many are still waiting
+    pub async fn redeliver(&self) -> usize {
+        let mut dead_letters = self.dead_letters.lock().await;
+
+        self.flush(&mut dead_letters).await;
+
+        dead_letters.len()
+    }
+
+    /// Publish the waiting events in order, keeping the ones that fail and any later events for
+    /// the same aggregates
+    async fn flush(&self, waiting: &mut VecDeque<Unpublished>) {
+        let mut blocked = BTreeSet::new();
+        let mut kept = VecDeque::new();
+
+        while let Some(event) = waiting.pop_front() {
+            if blocked.contains(&event.aggregate_id) {
+                kept.push_back(event);
+                continue;
+            }
+
+            if let Err(err) = self.publish_with_retries(&event).await {
+                error!(
+                    err:err = err,
+                    aggregate_id = event.aggregate_id,
+                    sequence = event.sequence;
+                    "InMemory: keeping event for redelivery: {}",
+                    err,
+                );
+
+                metrics::increment(metrics::BATCH_ITEM_FAILURES, &[("consumer", "publisher")]);
+
+                blocked.insert(event.aggregate_id.clone());
+                kept.push_back(event);
+            }
+        }
+
+        *waiting = kept;
+    }
+
+    /// Publish an event, retrying failures that may be transient
+    async fn publish_with_retries(&self, event: &Unpublished) -> Result<(), Error> {
+        let mut retries = 0;
+
+        loop {
+            match self.publish(event).await {
+                Err(err) if retries < MAX_RETRIES => {
+                    let backoff = INITIAL_BACKOFF * 2u32.pow(retries);
+                    retries += 1;
+
+                    warn!(
+                        "InMemory: retrying {} for {} in {}ms (retry {} of {}): {}",
+                        event.sequence,
+                        event.aggregate_id,
+                        backoff.as_millis(),
+                        retries,
+                        MAX_RETRIES,
+                        err,
+                    );
+
+                    tokio::time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn publish(&self, unpublished: &Unpublished) -> Result<(), Error> {
+        let aggregate_id = &unpublished.aggregate_id;
+        let event_type = &unpublished.event_type;
+
+        // Finish an erasure whose key couldn't be destroyed when it was committed, as the Kinesis
+        // publisher does
+        if self.shredder.is_erasure(event_type) {
+            self.shredder.erase(aggregate_id).await?;
+        }
+
+        let mut payload = unpublished.payload.to_value()?;
+        self.shredder
+            .seal_event(aggregate_id, event_type, &mut payload)
+            .await?;
+
+        let metadata = &unpublished.metadata;
+        let value = |key: &str| metadata.get(key).cloned().unwrap_or_default();
+
+        let event = DomainEvent {
+            tenant: value("tenant"),
+            correlation_id: value("correlation_id"),
+            causation_id: value("causation_id"),
+            recorded_at: Some(Utc::now()),
+            ..DomainEvent::new(
+                aggregate_id.clone(),
+                unpublished.aggregate_type.clone(),
+                unpublished.sequence,
+                event_type.clone(),
+                unpublished.event_version.clone(),
+                payload.to_string(),
+                serde_json::to_string(metadata)?,
+            )
+        };
+
+        self.stream
+            .append(event.partition_key(), serde_json::to_vec(&event)?)
+            .await;
+
+        metrics::increment(
+            metrics::EVENTS_PUBLISHED,
+            &[
+                ("aggregate_type", &event.entity),
+                ("event_type", &event.event_type),
+            ],
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<A> cqrs_es::Query<A> for InMemory
+where
+    A: Aggregate,
+    A::Event: 'static,
+{
+    async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        let mut waiting = self.dead_letters.lock().await;
+
+        // Queue behind the dead letters, so they're retried first and ordering is kept
+        waiting.extend(events.iter().map(Unpublished::new));
+
+        self.flush(&mut waiting).await;
+    }
+}
+
+/// In-memory publisher errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// JSON conversion error
+    #[error("JSON conversion error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// Crypto-shredding error
+    #[error("Shredding error: {0}")]
+    Shredding(#[from] shredding::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    use cqrs_es::Query;
+
+    use crate::{
+        domains::{
+            shredding::{sensitive_fields, Key, KeyStore, MemoryKeyStore},
+            tasks::{self, Task},
+        },
+        runners::{StartingPosition, Stream},
+    };
+
+    use super::*;
+
+    const ID: &str = "acme#1";
+
+    /// A KeyStore that can't be reached for the first few keys it's asked for
+    #[derive(Default)]
+    struct FlakyKeyStore {
+        failures: AtomicU32,
+        inner: MemoryKeyStore,
+    }
+
+    #[async_trait]
+    impl KeyStore for FlakyKeyStore {
+        async fn get(&self, aggregate_id: &str) -> Result<Option<Key>, shredding::Error> {
+            self.inner.get(aggregate_id).await
+        }
+
+        async fn get_or_create(&self, aggregate_id: &str) -> Result<Key, shredding::Error> {
+            let remaining = self.failures.load(Ordering::SeqCst);
+
+            if remaining > 0 {
+                self.failures.store(remaining - 1, Ordering::SeqCst);
+                return Err(shredding::Error::KeyStore("Connection reset".into()));
+            }
+
+            self.inner.get_or_create(aggregate_id).await
+        }
+
+        async fn destroy(&self, aggregate_id: &str) -> Result<(), shredding::Error> {
+            self.inner.destroy(aggregate_id).await
+        }
+    }
+
+    fn created(sequence: usize) -> EventEnvelope<Task> {
+        EventEnvelope {
+            aggregate_id: ID.to_string(),
+            sequence,
+            payload: tasks::Event::Created {
+                id: ID.to_string(),
+                created_at: Utc::now(),
+                task: Box::new(Task {
+                    id: ID.to_string(),
+                    name: "My Task".to_string(),
+                    ..Task::default()
+                }),
+            },
+            metadata: HashMap::from([("tenant".to_string(), "acme".to_string())]),
+        }
+    }
+
+    async fn published(stream: &MemoryStream) -> Vec<DomainEvent> {
+        let shard = &stream.shards().await.unwrap()[0].id;
+        let iterator = stream
+            .iterator(shard, None, StartingPosition::TrimHorizon)
+            .await
+            .unwrap();
+
+        stream
+            .read(shard, &iterator, 10)
+            .await
+            .unwrap()
+            .records
+            .iter()
+            .map(|record| serde_json::from_slice(&record.kinesis.data).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn transient_sealing_failures_are_retried() {
+        let keys = FlakyKeyStore {
+            failures: AtomicU32::new(MAX_RETRIES),
+            ..FlakyKeyStore::default()
+        };
+        let stream = MemoryStream::new();
+        let publisher = InMemory::new(
+            stream.clone(),
+            Arc::new(Shredder::new(Arc::new(keys), sensitive_fields())),
+        );
+
+        publisher.dispatch(ID, &[created(1)]).await;
+
+        let events = published(&stream).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "Task:Created");
+        assert_eq!(events[0].tenant, "acme");
+        assert!(!events[0].payload.contains("My Task"));
+        assert_eq!(publisher.dead_letters().await, 0);
+    }
+
+    #[tokio::test]
+    async fn events_that_cannot_be_published_are_redelivered_in_order() {
+        let keys = FlakyKeyStore {
+            failures: AtomicU32::new(MAX_RETRIES + 1),
+            ..FlakyKeyStore::default()
+        };
+        let stream = MemoryStream::new();
+        let publisher = InMemory::new(
+            stream.clone(),
+            Arc::new(Shredder::new(Arc::new(keys), sensitive_fields())),
+        );
+
+        publisher.dispatch(ID, &[created(1)]).await;
+
+        assert!(published(&stream).await.is_empty());
+        assert_eq!(publisher.dead_letters().await, 1);
+
+        // The key store is back, so the dead letter goes out ahead of the next event
+        publisher.dispatch(ID, &[created(2)]).await;
+
+        let sequences: Vec<_> = published(&stream)
+            .await
+            .iter()
+            .map(|event| event.sequence)
+            .collect();
+        assert_eq!(sequences, vec![1, 2]);
+        assert_eq!(publisher.redeliver().await, 0);
+    }
+}
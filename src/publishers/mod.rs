@@ -1,4 +1,8 @@
 /// The Kinesis event publisher
 pub mod kinesis;
 
+/// The in-memory event publisher, for running the pipeline in one process
+pub mod memory;
+
 pub use kinesis::Kinesis;
+pub use memory::InMemory;
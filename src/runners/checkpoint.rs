@@ -0,0 +1,66 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::Error;
+
+/// The last sequence number handled in each shard, kept in a JSON file per runner
+#[derive(Debug)]
+pub struct Checkpoints {
+    path: Option<PathBuf>,
+    positions: BTreeMap<String, String>,
+}
+
+impl Checkpoints {
+    /// Load a runner's checkpoints, starting fresh if it hasn't saved any yet
+    pub fn load(dir: &Path, name: &str) -> Result<Self, Error> {
+        let path = dir.join(format!("{name}.json"));
+
+        let positions = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Self {
+            path: Some(path),
+            positions,
+        })
+    }
+
+    /// Checkpoints that are only kept in memory, for streams that don't outlive the process
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            positions: BTreeMap::new(),
+        }
+    }
+
+    /// The last sequence number handled in a shard
+    pub fn get(&self, shard_id: &str) -> Option<&str> {
+        self.positions.get(shard_id).map(String::as_str)
+    }
+
+    /// Record the last sequence number handled in a shard
+    pub fn save(&mut self, shard_id: &str, sequence_number: &str) -> Result<(), Error> {
+        self.positions
+            .insert(shard_id.to_string(), sequence_number.to_string());
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write to the side and rename, so a runner stopped mid-write keeps its old checkpoint
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec_pretty(&self.positions)?)?;
+        fs::rename(&temporary, path)?;
+
+        Ok(())
+    }
+}
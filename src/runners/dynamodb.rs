@@ -0,0 +1,203 @@
+use std::future::Future;
+
+use async_trait::async_trait;
+use aws_config::SdkConfig;
+use aws_lambda_events::{
+    dynamodb::{Event, EventRecord, StreamRecord},
+    streams::DynamoDbEventResponse,
+};
+use aws_sdk_dynamodbstreams::types::{Record, ShardIteratorType};
+use chrono::{DateTime, Utc};
+use lambda_runtime::{Context, LambdaEvent};
+
+use super::{Error, Page, Runner, Shard, StartingPosition, Stream};
+
+/// A DynamoDB table's stream, read with the DynamoDB Streams API
+pub struct DynamoDbStream {
+    client: aws_sdk_dynamodbstreams::Client,
+    stream_arn: String,
+}
+
+impl DynamoDbStream {
+    /// Find the latest stream for a table
+    pub async fn for_table(config: &SdkConfig, table_name: &str) -> Result<Self, Error> {
+        let stream_arn = aws_sdk_dynamodb::Client::new(config)
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_err(Error::stream)?
+            .table
+            .and_then(|table| table.latest_stream_arn)
+            .ok_or_else(|| Error::NoStream(table_name.to_string()))?;
+
+        Ok(Self {
+            client: aws_sdk_dynamodbstreams::Client::new(config),
+            stream_arn,
+        })
+    }
+}
+
+#[async_trait]
+impl Stream for DynamoDbStream {
+    type Record = EventRecord;
+
+    async fn shards(&self) -> Result<Vec<Shard>, Error> {
+        let mut shards = Vec::new();
+        let mut start = None;
+
+        loop {
+            let Some(description) = self
+                .client
+                .describe_stream()
+                .stream_arn(&self.stream_arn)
+                .set_exclusive_start_shard_id(start)
+                .send()
+                .await
+                .map_err(Error::stream)?
+                .stream_description
+            else {
+                return Ok(shards);
+            };
+
+            shards.extend(
+                description
+                    .shards
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|shard| {
+                        Some(Shard {
+                            id: shard.shard_id?,
+                            parent_id: shard.parent_shard_id,
+                        })
+                    }),
+            );
+
+            start = description.last_evaluated_shard_id;
+
+            if start.is_none() {
+                return Ok(shards);
+            }
+        }
+    }
+
+    async fn iterator(
+        &self,
+        shard_id: &str,
+        after: Option<&str>,
+        position: StartingPosition,
+    ) -> Result<String, Error> {
+        let iterator_type = match (after, position) {
+            (Some(_), _) => ShardIteratorType::AfterSequenceNumber,
+            (None, StartingPosition::TrimHorizon) => ShardIteratorType::TrimHorizon,
+            (None, StartingPosition::Latest) => ShardIteratorType::Latest,
+        };
+
+        self.client
+            .get_shard_iterator()
+            .stream_arn(&self.stream_arn)
+            .shard_id(shard_id)
+            .shard_iterator_type(iterator_type)
+            .set_sequence_number(after.map(str::to_string))
+            .send()
+            .await
+            .map_err(Error::stream)?
+            .shard_iterator
+            .ok_or_else(|| Error::stream(std::io::Error::other("No shard iterator returned")))
+    }
+
+    async fn read(
+        &self,
+        _shard_id: &str,
+        iterator: &str,
+        limit: i32,
+    ) -> Result<Page<EventRecord>, Error> {
+        let output = self
+            .client
+            .get_records()
+            .shard_iterator(iterator)
+            .limit(limit)
+            .send()
+            .await
+            .map_err(Error::stream)?;
+
+        Ok(Page {
+            records: output
+                .records
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|record| self.event_record(record))
+                .collect(),
+            next_iterator: output.next_shard_iterator,
+        })
+    }
+
+    fn sequence_number(record: &EventRecord) -> String {
+        record.change.sequence_number.clone().unwrap_or_default()
+    }
+
+    fn identifies(record: &EventRecord, item_identifier: &str) -> bool {
+        // Lambda expects sequence numbers, but accept event IDs too
+        record.change.sequence_number.as_deref() == Some(item_identifier)
+            || record.event_id == item_identifier
+    }
+}
+
+impl DynamoDbStream {
+    /// Convert a record to the shape it has in a Lambda event, if it has a change to hand over
+    fn event_record(&self, record: Record) -> Option<EventRecord> {
+        let change = record.dynamodb?;
+
+        Some(EventRecord {
+            aws_region: record.aws_region.unwrap_or_default(),
+            change: StreamRecord {
+                approximate_creation_date_time: change
+                    .approximate_creation_date_time
+                    .and_then(|at| DateTime::from_timestamp(at.secs(), at.subsec_nanos()))
+                    .unwrap_or_else(Utc::now),
+                keys: change.keys.unwrap_or_default().into(),
+                new_image: change.new_image.unwrap_or_default().into(),
+                old_image: change.old_image.unwrap_or_default().into(),
+                sequence_number: change.sequence_number,
+                size_bytes: change.size_bytes.unwrap_or_default(),
+                stream_view_type: change
+                    .stream_view_type
+                    .and_then(|view| serde_json::from_value(view.as_str().into()).ok()),
+            },
+            event_id: record.event_id.unwrap_or_default(),
+            event_name: record
+                .event_name
+                .map(|name| name.as_str().to_string())
+                .unwrap_or_default(),
+            event_source: record.event_source,
+            event_version: record.event_version,
+            event_source_arn: Some(self.stream_arn.clone()),
+            user_identity: None,
+            record_format: None,
+            table_name: None,
+        })
+    }
+}
+
+impl Runner<DynamoDbStream> {
+    /// Poll the stream, handing each batch to a handler written for DynamoDB stream Lambda events
+    pub async fn run<H, F>(&mut self, handler: H) -> Result<(), Error>
+    where
+        H: Fn(LambdaEvent<Event>) -> F,
+        F: Future<Output = Result<DynamoDbEventResponse, lambda_runtime::Error>>,
+    {
+        self.poll(|records| {
+            let response = handler(LambdaEvent::new(Event { records }, Context::default()));
+
+            async move {
+                Ok(response
+                    .await?
+                    .batch_item_failures
+                    .into_iter()
+                    .filter_map(|failure| failure.item_identifier)
+                    .collect())
+            }
+        })
+        .await
+    }
+}
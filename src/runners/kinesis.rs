@@ -0,0 +1,201 @@
+use std::future::Future;
+
+use async_trait::async_trait;
+use aws_config::SdkConfig;
+use aws_lambda_events::{
+    encodings::{Base64Data, SecondTimestamp},
+    kinesis::{KinesisEvent, KinesisEventRecord, KinesisRecord},
+    streams::KinesisEventResponse,
+};
+use aws_sdk_kinesis::types::{Record, ShardIteratorType};
+use chrono::{DateTime, Utc};
+use lambda_runtime::{Context, LambdaEvent};
+
+use super::{Error, Page, Runner, Shard, StartingPosition, Stream};
+
+/// A Kinesis data stream
+pub struct KinesisStream {
+    client: aws_sdk_kinesis::Client,
+    stream_name: String,
+    region: Option<String>,
+}
+
+impl KinesisStream {
+    /// Create a new instance
+    pub fn new(config: &SdkConfig, stream_name: &str) -> Self {
+        Self {
+            client: aws_sdk_kinesis::Client::new(config),
+            stream_name: stream_name.to_string(),
+            region: config.region().map(ToString::to_string),
+        }
+    }
+}
+
+#[async_trait]
+impl Stream for KinesisStream {
+    type Record = KinesisEventRecord;
+
+    async fn shards(&self) -> Result<Vec<Shard>, Error> {
+        let mut shards = Vec::new();
+        let mut next_token: Option<String> = None;
+
+        loop {
+            // The stream name can't be given along with a token for the next page
+            let request = match &next_token {
+                Some(token) => self.client.list_shards().next_token(token),
+                None => self.client.list_shards().stream_name(&self.stream_name),
+            };
+
+            let output = request.send().await.map_err(Error::stream)?;
+
+            shards.extend(
+                output
+                    .shards
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|shard| Shard {
+                        id: shard.shard_id,
+                        parent_id: shard.parent_shard_id,
+                    }),
+            );
+
+            next_token = output.next_token;
+
+            if next_token.is_none() {
+                return Ok(shards);
+            }
+        }
+    }
+
+    async fn iterator(
+        &self,
+        shard_id: &str,
+        after: Option<&str>,
+        position: StartingPosition,
+    ) -> Result<String, Error> {
+        let iterator_type = match (after, position) {
+            (Some(_), _) => ShardIteratorType::AfterSequenceNumber,
+            (None, StartingPosition::TrimHorizon) => ShardIteratorType::TrimHorizon,
+            (None, StartingPosition::Latest) => ShardIteratorType::Latest,
+        };
+
+        self.client
+            .get_shard_iterator()
+            .stream_name(&self.stream_name)
+            .shard_id(shard_id)
+            .shard_iterator_type(iterator_type)
+            .set_starting_sequence_number(after.map(str::to_string))
+            .send()
+            .await
+            .map_err(Error::stream)?
+            .shard_iterator
+            .ok_or_else(|| Error::stream(std::io::Error::other("No shard iterator returned")))
+    }
+
+    async fn read(
+        &self,
+        shard_id: &str,
+        iterator: &str,
+        limit: i32,
+    ) -> Result<Page<KinesisEventRecord>, Error> {
+        let output = self
+            .client
+            .get_records()
+            .shard_iterator(iterator)
+            .limit(limit)
+            .send()
+            .await
+            .map_err(Error::stream)?;
+
+        Ok(Page {
+            records: output
+                .records
+                .into_iter()
+                .map(|record| self.event_record(shard_id, record))
+                .collect(),
+            next_iterator: output.next_shard_iterator,
+        })
+    }
+
+    fn sequence_number(record: &KinesisEventRecord) -> String {
+        record.kinesis.sequence_number.clone().unwrap_or_default()
+    }
+}
+
+impl KinesisStream {
+    /// Convert a record to the shape it has in a Lambda event
+    fn event_record(&self, shard_id: &str, record: Record) -> KinesisEventRecord {
+        let arrived_at = record
+            .approximate_arrival_timestamp
+            .and_then(|at| DateTime::from_timestamp(at.secs(), at.subsec_nanos()))
+            .unwrap_or_else(Utc::now);
+
+        let mut event_record = event_record(
+            shard_id,
+            record.sequence_number,
+            record.partition_key,
+            record.data.into_inner(),
+            arrived_at,
+        );
+
+        event_record.aws_region = self.region.clone();
+        event_record.kinesis.encryption_type = record
+            .encryption_type
+            .map(|encryption| encryption.as_str().to_string());
+
+        event_record
+    }
+}
+
+/// A record read from a Kinesis shard, in the shape it has in a Lambda event
+pub(super) fn event_record(
+    shard_id: &str,
+    sequence_number: String,
+    partition_key: String,
+    data: Vec<u8>,
+    arrived_at: DateTime<Utc>,
+) -> KinesisEventRecord {
+    KinesisEventRecord {
+        aws_region: None,
+        event_id: Some(format!("{shard_id}:{sequence_number}")),
+        event_name: Some("aws:kinesis:record".to_string()),
+        event_source: Some("aws:kinesis".to_string()),
+        event_source_arn: None,
+        event_version: Some("1.0".to_string()),
+        invoke_identity_arn: None,
+        kinesis: KinesisRecord {
+            approximate_arrival_timestamp: SecondTimestamp(arrived_at),
+            data: Base64Data(data),
+            encryption_type: None,
+            partition_key: Some(partition_key),
+            sequence_number: Some(sequence_number),
+            kinesis_schema_version: Some("1.0".to_string()),
+        },
+    }
+}
+
+impl<S: Stream<Record = KinesisEventRecord>> Runner<S> {
+    /// Poll the stream, handing each batch to a handler written for Kinesis Lambda events
+    pub async fn run<H, F>(&mut self, handler: H) -> Result<(), Error>
+    where
+        H: Fn(LambdaEvent<KinesisEvent>) -> F,
+        F: Future<Output = Result<KinesisEventResponse, lambda_runtime::Error>>,
+    {
+        self.poll(|records| {
+            let response = handler(LambdaEvent::new(
+                KinesisEvent { records },
+                Context::default(),
+            ));
+
+            async move {
+                Ok(response
+                    .await?
+                    .batch_item_failures
+                    .into_iter()
+                    .filter_map(|failure| failure.item_identifier)
+                    .collect())
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use aws_lambda_events::kinesis::KinesisEventRecord;
+use chrono::Utc;
+use tokio::sync::{Notify, RwLock};
+
+use super::{kinesis, Error, Page, Shard, StartingPosition, Stream, POLL_INTERVAL};
+
+/// The ID of the stream's only shard
+const SHARD_ID: &str = "shardId-000000000000";
+
+/// An in-process stream shaped like a single Kinesis shard, for running the whole pipeline in
+/// one process
+///
+/// Records are kept for as long as the process runs, and each runner reads them from its own
+/// position, so every consumer sees every record in the order it was appended. A single shard
+/// keeps the events for each aggregate in order, as partitioning by tenant does on Kinesis.
+#[derive(Clone, Default)]
+pub struct MemoryStream {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    records: RwLock<Vec<KinesisEventRecord>>,
+    appended: Notify,
+}
+
+impl MemoryStream {
+    /// Create a new instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a record, waking any runners waiting for one
+    pub async fn append(&self, partition_key: &str, data: Vec<u8>) {
+        let mut records = self.inner.records.write().await;
+        let sequence_number = format!("{:020}", records.len());
+
+        records.push(kinesis::event_record(
+            SHARD_ID,
+            sequence_number,
+            partition_key.to_string(),
+            data,
+            Utc::now(),
+        ));
+
+        drop(records);
+
+        self.inner.appended.notify_waiters();
+    }
+}
+
+#[async_trait]
+impl Stream for MemoryStream {
+    type Record = KinesisEventRecord;
+
+    async fn shards(&self) -> Result<Vec<Shard>, Error> {
+        Ok(vec![Shard {
+            id: SHARD_ID.to_string(),
+            parent_id: None,
+        }])
+    }
+
+    async fn iterator(
+        &self,
+        _shard_id: &str,
+        after: Option<&str>,
+        position: StartingPosition,
+    ) -> Result<String, Error> {
+        let index = match (after, position) {
+            (Some(sequence_number), _) => {
+                sequence_number.parse::<usize>().map_err(Error::stream)? + 1
+            }
+            (None, StartingPosition::TrimHorizon) => 0,
+            (None, StartingPosition::Latest) => self.inner.records.read().await.len(),
+        };
+
+        Ok(index.to_string())
+    }
+
+    async fn read(
+        &self,
+        _shard_id: &str,
+        iterator: &str,
+        limit: i32,
+    ) -> Result<Page<KinesisEventRecord>, Error> {
+        let start = iterator.parse::<usize>().map_err(Error::stream)?;
+        let records = self.inner.records.read().await;
+
+        let start = start.min(records.len());
+        let end = records.len().min(start + limit.max(1) as usize);
+
+        Ok(Page {
+            records: records[start..end].to_vec(),
+            next_iterator: Some(end.to_string()),
+        })
+    }
+
+    fn sequence_number(record: &KinesisEventRecord) -> String {
+        record.kinesis.sequence_number.clone().unwrap_or_default()
+    }
+
+    async fn wait(&self) {
+        // Fall back to polling, in case a record was appended just before this started waiting
+        let _ = tokio::time::timeout(POLL_INTERVAL, self.inner.appended.notified()).await;
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use crate::config::Config;
+
+mod checkpoint;
+mod dynamodb;
+mod kinesis;
+mod memory;
+
+pub use checkpoint::Checkpoints;
+pub use dynamodb::DynamoDbStream;
+pub use kinesis::KinesisStream;
+pub use memory::MemoryStream;
+
+/// The most records handed to the handler at once
+const BATCH_SIZE: i32 = 100;
+
+/// How long to wait before polling again when there were no new records
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times a failed record is retried before it's skipped, matching the event source
+/// mappings in Terraform
+const MAX_RETRIES: u32 = 5;
+
+/// The longest wait between retries
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Where a shard is read from when there's no checkpoint for it yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartingPosition {
+    /// The oldest record still in the shard
+    TrimHorizon,
+
+    /// Only records added after the runner starts
+    Latest,
+}
+
+/// A shard of a stream
+#[derive(Clone, Debug)]
+pub struct Shard {
+    /// The shard's ID
+    pub id: String,
+
+    /// The shard this one was split from, which is read to the end first
+    pub parent_id: Option<String>,
+}
+
+/// A page of records read from a shard
+#[derive(Debug)]
+pub struct Page<R> {
+    /// The records read
+    pub records: Vec<R>,
+
+    /// Where to read the next page from, or None once a closed shard has been read to the end
+    pub next_iterator: Option<String>,
+}
+
+/// A stream of records split into shards, read the way a Lambda event source mapping reads it
+#[async_trait]
+pub trait Stream: Send + Sync {
+    /// A record in the shape it's handed to a Lambda function
+    type Record: Clone + Send + Sync;
+
+    /// The stream's current shards
+    async fn shards(&self) -> Result<Vec<Shard>, Error>;
+
+    /// An iterator for a shard, just after the `after` sequence number if there is one
+    async fn iterator(
+        &self,
+        shard_id: &str,
+        after: Option<&str>,
+        position: StartingPosition,
+    ) -> Result<String, Error>;
+
+    /// Read up to `limit` records from a shard
+    async fn read(
+        &self,
+        shard_id: &str,
+        iterator: &str,
+        limit: i32,
+    ) -> Result<Page<Self::Record>, Error>;
+
+    /// A record's sequence number, which checkpoints are kept by
+    fn sequence_number(record: &Self::Record) -> String;
+
+    /// Whether a batch item failure's identifier refers to this record
+    fn identifies(record: &Self::Record, item_identifier: &str) -> bool {
+        Self::sequence_number(record) == item_identifier
+    }
+
+    /// Wait for new records after a poll that found none
+    async fn wait(&self) {
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Feeds a stream to a handler outside of Lambda, so the pipeline can run locally
+///
+/// Records are read from every shard in batches and handed to the handler in the same shape
+/// Lambda would use. Batch item failures are honoured as they are with `ReportBatchItemFailures`:
+/// the records before the first failure are checkpointed, and the rest are retried with backoff.
+/// A record that still fails after `MAX_RETRIES` retries is logged and skipped, as it would be
+/// sent to the dead-letter queue when deployed. Positions are checkpointed to a local file, so a
+/// restarted runner picks up where it left off.
+pub struct Runner<S: Stream> {
+    name: String,
+    stream: S,
+    checkpoints: Checkpoints,
+    position: StartingPosition,
+}
+
+impl<S: Stream> Runner<S> {
+    /// Create a new instance, loading its checkpoints from the config's checkpoint directory
+    pub fn new(name: &str, stream: S, config: &Config) -> Result<Self, Error> {
+        Ok(Self {
+            name: name.to_string(),
+            stream,
+            checkpoints: Checkpoints::load(&config.checkpoint_dir, name)?,
+            position: StartingPosition::TrimHorizon,
+        })
+    }
+
+    /// Create a new instance that only keeps its checkpoints in memory, for streams that don't
+    /// outlive the process
+    pub fn in_memory(name: &str, stream: S) -> Self {
+        Self {
+            name: name.to_string(),
+            stream,
+            checkpoints: Checkpoints::in_memory(),
+            position: StartingPosition::TrimHorizon,
+        }
+    }
+
+    /// Where to read shards from when there's no checkpoint for them yet
+    pub fn with_starting_position(mut self, position: StartingPosition) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Poll the stream forever, handing each batch of records to the handler, which returns the
+    /// identifiers of the records that failed
+    pub async fn poll<H, F>(&mut self, handler: H) -> Result<(), Error>
+    where
+        H: Fn(Vec<S::Record>) -> F,
+        F: Future<Output = Result<Vec<String>, lambda_runtime::Error>>,
+    {
+        info!("Running {} locally", self.name);
+
+        let mut iterators = HashMap::<String, String>::new();
+        let mut finished = HashSet::<String>::new();
+
+        loop {
+            match self.stream.shards().await {
+                Ok(shards) => {
+                    self.open(&shards, &mut iterators, &finished).await;
+                }
+                Err(error) => {
+                    warn!("Unable to list the shards for {}: {}", self.name, error);
+                }
+            }
+
+            let mut idle = true;
+
+            for (shard_id, iterator) in iterators.clone() {
+                let page = match self.stream.read(&shard_id, &iterator, BATCH_SIZE).await {
+                    Ok(page) => page,
+                    Err(error) => {
+                        // Iterators expire, so open a new one from the checkpoint next time
+                        warn!("Unable to read shard {}: {}", shard_id, error);
+                        iterators.remove(&shard_id);
+                        continue;
+                    }
+                };
+
+                if !page.records.is_empty() {
+                    idle = false;
+                    self.deliver(&shard_id, page.records, &handler).await?;
+                }
+
+                match page.next_iterator {
+                    Some(next) => {
+                        iterators.insert(shard_id, next);
+                    }
+                    None => {
+                        info!("Finished reading closed shard {}", shard_id);
+                        iterators.remove(&shard_id);
+                        finished.insert(shard_id);
+                    }
+                }
+            }
+
+            if idle {
+                self.stream.wait().await;
+            }
+        }
+    }
+
+    /// Open an iterator for each shard that isn't being read yet, once its parent is finished
+    async fn open(
+        &self,
+        shards: &[Shard],
+        iterators: &mut HashMap<String, String>,
+        finished: &HashSet<String>,
+    ) {
+        let listed: HashSet<&str> = shards.iter().map(|shard| shard.id.as_str()).collect();
+
+        for shard in shards {
+            if finished.contains(&shard.id) || iterators.contains_key(&shard.id) {
+                continue;
+            }
+
+            // Keep records in order across a split by finishing the parent first
+            if let Some(parent_id) = &shard.parent_id {
+                if listed.contains(parent_id.as_str()) && !finished.contains(parent_id) {
+                    continue;
+                }
+            }
+
+            let after = self.checkpoints.get(&shard.id);
+
+            match self.stream.iterator(&shard.id, after, self.position).await {
+                Ok(iterator) => {
+                    iterators.insert(shard.id.clone(), iterator);
+                }
+                Err(error) => {
+                    warn!("Unable to open shard {}: {}", shard.id, error);
+                }
+            }
+        }
+    }
+
+    /// Hand a batch to the handler until every record has succeeded or been skipped
+    async fn deliver<H, F>(
+        &mut self,
+        shard_id: &str,
+        mut records: Vec<S::Record>,
+        handler: &H,
+    ) -> Result<(), Error>
+    where
+        H: Fn(Vec<S::Record>) -> F,
+        F: Future<Output = Result<Vec<String>, lambda_runtime::Error>>,
+    {
+        let mut retries = 0;
+
+        while !records.is_empty() {
+            // A failed invocation fails the whole batch, as it does on Lambda
+            let first_failure = match handler(records.clone()).await {
+                Ok(failures) if failures.is_empty() => None,
+                Ok(failures) => Some(
+                    records
+                        .iter()
+                        .position(|record| failures.iter().any(|id| S::identifies(record, id)))
+                        .unwrap_or(0),
+                ),
+                Err(error) => {
+                    error!("{} failed to handle a batch: {}", self.name, error);
+                    Some(0)
+                }
+            };
+
+            let succeeded = first_failure.unwrap_or(records.len());
+
+            if succeeded > 0 {
+                let last = S::sequence_number(&records[succeeded - 1]);
+                self.checkpoints.save(shard_id, &last)?;
+                records.drain(..succeeded);
+                retries = 0;
+            }
+
+            let Some(failed) = records.first() else {
+                break;
+            };
+
+            if retries == MAX_RETRIES {
+                let sequence_number = S::sequence_number(failed);
+
+                error!(
+                    "{} skipped record {} after {} retries",
+                    self.name, sequence_number, MAX_RETRIES
+                );
+
+                self.checkpoints.save(shard_id, &sequence_number)?;
+                records.remove(0);
+                retries = 0;
+                continue;
+            }
+
+            retries += 1;
+
+            let backoff = Duration::from_secs(1 << retries.min(5)).min(MAX_BACKOFF);
+
+            warn!(
+                "{} is retrying {} records from {} in {}s (retry {} of {})",
+                self.name,
+                records.len(),
+                S::sequence_number(failed),
+                backoff.as_secs(),
+                retries,
+                MAX_RETRIES
+            );
+
+            tokio::time::sleep(backoff).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runner errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The stream couldn't be read
+    #[error("Stream error: {0}")]
+    Stream(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The table doesn't have a stream enabled
+    #[error("The {0} table doesn't have a stream")]
+    NoStream(String),
+
+    /// A checkpoint couldn't be read or written
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(#[from] std::io::Error),
+
+    /// A checkpoint file isn't valid
+    #[error("Invalid checkpoint file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl Error {
+    fn stream(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self::Stream(Box::new(error))
+    }
+}
@@ -0,0 +1,398 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use cqrs_es::{persist::PersistenceError, AggregateError, CqrsFramework, EventStore};
+use derive_new::new;
+use lambda_runtime::LambdaEvent;
+use ulid::Ulid;
+
+use crate::{
+    domains::{
+        self,
+        tasks::{
+            cqrs,
+            schedule::{Entry, Schedule},
+            Task,
+        },
+        tenants,
+    },
+    utils::telemetry::TracedExecute,
+};
+
+/// The subject recorded on commands issued by the scheduler
+pub const SCHEDULER_SUBJECT: &str = "system:scheduler";
+
+/// The most deadlines handled in a single run
+const BATCH_SIZE: i32 = 100;
+
+/// Issues the `Remind` and `MarkOverdue` commands for Task deadlines that have passed
+///
+/// Pending deadlines are kept in the durable Task Schedule, so a run picks up everything that
+/// passed while the scheduler wasn't running. The commands are rejected by the Task if the
+/// deadline no longer applies, so firing one twice is harmless.
+#[derive(Clone, new)]
+pub struct Deadlines<ES = cqrs::EventStore>
+where
+    ES: EventStore<Task>,
+{
+    cqrs: Arc<CqrsFramework<Task, ES>>,
+    schedule: Arc<dyn Schedule>,
+}
+
+impl<ES> Deadlines<ES>
+where
+    ES: EventStore<Task> + Send + Sync,
+    ES::AC: Send,
+{
+    /// Handle a scheduled invocation, firing every deadline that has passed
+    pub async fn handle(
+        &self,
+        _event: LambdaEvent<serde_json::Value>,
+    ) -> Result<(), lambda_runtime::Error> {
+        let fired = self.run(Utc::now()).await?;
+
+        tracing::info!("Fired {} Task deadlines", fired);
+
+        Ok(())
+    }
+
+    /// Fire the deadlines that are due at or before `now`, returning how many were fired
+    pub async fn run(&self, now: DateTime<Utc>) -> Result<usize, PersistenceError> {
+        let entries = self.schedule.due(now, BATCH_SIZE).await?;
+
+        let mut fired = 0;
+
+        for entry in entries {
+            match self.fire(&entry).await {
+                Ok(true) => fired += 1,
+                Ok(false) => {}
+                Err(error) => {
+                    // Leave the entry in place, so it's retried on the next run
+                    tracing::error!(
+                        error = ?error, aggregate_id = entry.aggregate_id,
+                        "Failed to fire {} deadline", entry.deadline
+                    );
+                }
+            }
+        }
+
+        Ok(fired)
+    }
+
+    /// Issue the command for a deadline, returning false if the Task rejected it as stale
+    async fn fire(&self, entry: &Entry) -> Result<bool, AggregateError<domains::Error>> {
+        let tenant = tenants::split_id(&entry.aggregate_id)
+            .map(|(tenant, _)| tenant)
+            .unwrap_or_default();
+
+        let command_id = Ulid::new().to_string();
+
+        let mut metadata = HashMap::<String, String>::new();
+        metadata.insert("command_id".to_string(), command_id.clone());
+        metadata.insert("subject".to_string(), SCHEDULER_SUBJECT.to_string());
+        metadata.insert("tenant".to_string(), tenant.to_string());
+
+        // A passing deadline isn't caused by any one request, so it starts a correlation of its own
+        metadata.insert("correlation_id".to_string(), command_id);
+
+        let result = self
+            .cqrs
+            .execute_traced(&entry.aggregate_id, entry.deadline.command(), metadata)
+            .await;
+
+        match result {
+            // The schedule query clears the entry once the Task records the event
+            Ok(()) => Ok(true),
+            Err(AggregateError::UserError(error)) => {
+                tracing::info!(
+                    error = ?error, aggregate_id = entry.aggregate_id,
+                    "Dropping stale {} deadline", entry.deadline
+                );
+
+                self.schedule.complete(entry).await?;
+
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use chrono::Duration;
+    use cqrs_es::{mem_store::MemStore, DomainEvent};
+
+    use super::*;
+    use crate::domains::{
+        projects::Project,
+        tasks::{inputs, schedule::Deadline, Command, Projects, Services, Tasks},
+    };
+
+    const OWNER: &str = "user-1";
+    const TENANT: &str = "acme";
+
+    /// Pending deadlines, in the order they were scheduled
+    #[derive(Default)]
+    struct FakeSchedule(Mutex<Vec<Entry>>);
+
+    impl FakeSchedule {
+        fn with(entries: Vec<Entry>) -> Arc<Self> {
+            Arc::new(Self(Mutex::new(entries)))
+        }
+
+        fn entries(&self) -> Vec<Entry> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Schedule for FakeSchedule {
+        async fn sync(&self, aggregate_id: &str, task: &Task) -> Result<(), PersistenceError> {
+            let mut entries = self.0.lock().unwrap();
+            entries.retain(|entry| entry.aggregate_id != aggregate_id);
+
+            for deadline in [Deadline::Reminder, Deadline::Overdue] {
+                if let Some(due_at) = deadline.due_at(task) {
+                    entries.push(Entry {
+                        aggregate_id: aggregate_id.to_string(),
+                        deadline,
+                        due_at,
+                    });
+                }
+            }
+
+            Ok(())
+        }
+
+        async fn due(
+            &self,
+            now: DateTime<Utc>,
+            limit: i32,
+        ) -> Result<Vec<Entry>, PersistenceError> {
+            let entries = self.0.lock().unwrap();
+
+            Ok(entries
+                .iter()
+                .filter(|entry| entry.due_at <= now)
+                .take(limit.max(0) as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn complete(&self, entry: &Entry) -> Result<(), PersistenceError> {
+            self.0.lock().unwrap().retain(|pending| pending != entry);
+
+            Ok(())
+        }
+    }
+
+    struct Nothing;
+
+    #[async_trait]
+    impl Projects for Nothing {
+        async fn find(&self, _aggregate_id: &str) -> Result<Option<Project>, domains::Error> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait]
+    impl Tasks for Nothing {
+        async fn find(&self, _aggregate_id: &str) -> Result<Option<Task>, domains::Error> {
+            Ok(None)
+        }
+    }
+
+    struct Fixture {
+        store: MemStore<Task>,
+        cqrs: Arc<CqrsFramework<Task, MemStore<Task>>>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let store = MemStore::<Task>::default();
+            let services = Services::new(Arc::new(Nothing), Arc::new(Nothing));
+            let cqrs = Arc::new(CqrsFramework::new(store.clone(), vec![], services));
+
+            Self { store, cqrs }
+        }
+
+        fn deadlines(&self, schedule: &Arc<FakeSchedule>) -> Deadlines<MemStore<Task>> {
+            Deadlines::new(self.cqrs.clone(), schedule.clone())
+        }
+
+        async fn execute(&self, aggregate_id: &str, command: Command) {
+            self.cqrs
+                .execute(aggregate_id, command)
+                .await
+                .expect("The command was accepted");
+        }
+
+        /// Create a Task with the given deadlines
+        async fn create(
+            &self,
+            aggregate_id: &str,
+            remind_at: Option<DateTime<Utc>>,
+            due_at: Option<DateTime<Utc>>,
+        ) {
+            let id = tenants::split_id(aggregate_id).unwrap().1;
+
+            self.execute(
+                aggregate_id,
+                Command::Create {
+                    id: id.to_string(),
+                    owner: OWNER.to_string(),
+                    input: inputs::Create {
+                        name: "Write the report".to_string(),
+                        remind_at,
+                        due_at,
+                        ..inputs::Create::default()
+                    },
+                },
+            )
+            .await;
+        }
+
+        /// The types of the events recorded for a Task after it was created
+        async fn fired(&self, aggregate_id: &str) -> Vec<String> {
+            self.store
+                .load_events(aggregate_id)
+                .await
+                .unwrap()
+                .iter()
+                .skip(1)
+                .map(|envelope| envelope.payload.event_type())
+                .collect()
+        }
+    }
+
+    fn scoped(id: &str) -> String {
+        tenants::scoped_id(TENANT, id)
+    }
+
+    fn entry(aggregate_id: &str, deadline: Deadline, due_at: DateTime<Utc>) -> Entry {
+        Entry {
+            aggregate_id: aggregate_id.to_string(),
+            deadline,
+            due_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn deadlines_that_passed_are_fired() {
+        let fixture = Fixture::new();
+        let passed = Utc::now() - Duration::hours(1);
+
+        fixture
+            .create(&scoped("task-1"), Some(passed), Some(passed))
+            .await;
+
+        let schedule = FakeSchedule::with(vec![
+            entry(&scoped("task-1"), Deadline::Reminder, passed),
+            entry(&scoped("task-1"), Deadline::Overdue, passed),
+        ]);
+
+        let fired = fixture.deadlines(&schedule).run(Utc::now()).await.unwrap();
+
+        assert_eq!(fired, 2);
+        assert_eq!(
+            fixture.fired(&scoped("task-1")).await,
+            ["Task:ReminderDue", "Task:BecameOverdue"]
+        );
+    }
+
+    #[tokio::test]
+    async fn deadlines_after_now_wait_for_a_later_run() {
+        let fixture = Fixture::new();
+        let now = Utc::now() - Duration::minutes(1);
+        let later = now + Duration::milliseconds(1);
+
+        fixture.create(&scoped("task-1"), Some(now), None).await;
+        fixture.create(&scoped("task-2"), Some(later), None).await;
+
+        let schedule = FakeSchedule::with(vec![
+            entry(&scoped("task-1"), Deadline::Reminder, now),
+            entry(&scoped("task-2"), Deadline::Reminder, later),
+        ]);
+
+        let fired = fixture.deadlines(&schedule).run(now).await.unwrap();
+
+        assert_eq!(fired, 1);
+        assert_eq!(fixture.fired(&scoped("task-1")).await, ["Task:ReminderDue"]);
+        assert!(fixture.fired(&scoped("task-2")).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stale_deadlines_are_dropped() {
+        let fixture = Fixture::new();
+        let passed = Utc::now() - Duration::hours(1);
+
+        // Completed before it became overdue
+        fixture.create(&scoped("done"), None, Some(passed)).await;
+        fixture
+            .execute(
+                &scoped("done"),
+                Command::Complete {
+                    subject: OWNER.to_string(),
+                    tenant: TENANT.to_string(),
+                },
+            )
+            .await;
+
+        // Deleted before the reminder
+        fixture.create(&scoped("deleted"), Some(passed), None).await;
+        fixture
+            .execute(
+                &scoped("deleted"),
+                Command::Delete {
+                    subject: OWNER.to_string(),
+                },
+            )
+            .await;
+
+        // Rescheduled after the entry was read
+        fixture
+            .create(&scoped("moved"), Some(Utc::now() + Duration::days(1)), None)
+            .await;
+
+        let entries = vec![
+            entry(&scoped("done"), Deadline::Overdue, passed),
+            entry(&scoped("deleted"), Deadline::Reminder, passed),
+            entry(&scoped("moved"), Deadline::Reminder, passed),
+            entry(&scoped("missing"), Deadline::Reminder, passed),
+        ];
+        let schedule = FakeSchedule::with(entries);
+
+        let fired = fixture.deadlines(&schedule).run(Utc::now()).await.unwrap();
+
+        assert_eq!(fired, 0);
+        assert!(schedule.entries().is_empty());
+        assert_eq!(fixture.fired(&scoped("done")).await, ["Task:Completed"]);
+        assert_eq!(fixture.fired(&scoped("deleted")).await, ["Task:Deleted"]);
+        assert!(fixture.fired(&scoped("moved")).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deadlines_fire_once() {
+        let fixture = Fixture::new();
+        let passed = Utc::now() - Duration::hours(1);
+
+        fixture.create(&scoped("task-1"), Some(passed), None).await;
+
+        let reminder = entry(&scoped("task-1"), Deadline::Reminder, passed);
+        let schedule = FakeSchedule::with(vec![reminder.clone()]);
+        let deadlines = fixture.deadlines(&schedule);
+
+        assert_eq!(deadlines.run(Utc::now()).await.unwrap(), 1);
+
+        // The schedule query hasn't cleared the entry yet, so it's read again
+        assert_eq!(schedule.entries(), [reminder]);
+        assert_eq!(deadlines.run(Utc::now()).await.unwrap(), 0);
+
+        assert!(schedule.entries().is_empty());
+        assert_eq!(fixture.fired(&scoped("task-1")).await, ["Task:ReminderDue"]);
+    }
+}
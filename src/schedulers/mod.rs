@@ -0,0 +1,4 @@
+/// The Task deadline scheduler
+pub mod deadlines;
+
+pub use deadlines::Deadlines;
@@ -0,0 +1,129 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::watch;
+
+/// Tracks the requests in flight, and whether the server is shutting down
+///
+/// Once a SIGTERM or SIGINT arrives the server drains: the listener stops accepting connections,
+/// new requests on open connections are rejected with 503 Service Unavailable, and the requests
+/// already in flight get until the drain timeout to finish. That keeps a deploy from cutting off
+/// a command after its events are committed but before the view is reloaded.
+pub struct Shutdown {
+    draining: watch::Sender<bool>,
+    in_flight: AtomicUsize,
+}
+
+impl Shutdown {
+    /// Create a new instance
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            draining: watch::Sender::new(false),
+            in_flight: AtomicUsize::new(0),
+        })
+    }
+
+    /// Whether the server has started shutting down
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Wait for a shutdown signal, then start draining
+    pub async fn listen(self: Arc<Self>) {
+        signal().await;
+
+        info!(
+            "Shutting down, waiting for {} in-flight requests",
+            self.in_flight.load(Ordering::SeqCst)
+        );
+
+        self.draining.send_replace(true);
+    }
+
+    /// Resolve once draining has gone on for longer than the timeout
+    pub async fn timed_out(&self, timeout: Duration) {
+        let mut draining = self.draining.subscribe();
+
+        // The sender lives as long as `self`, so this only fails if it's dropped while waiting
+        let _ = draining.wait_for(|draining| *draining).await;
+
+        tokio::time::sleep(timeout).await;
+
+        warn!(
+            "Drain timeout of {}s elapsed with {} requests still in flight",
+            timeout.as_secs(),
+            self.in_flight.load(Ordering::SeqCst)
+        );
+    }
+}
+
+/// Decrements the in-flight count when a request finishes, even if it's cancelled
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Count each request while it's in flight, and reject new ones once the server is draining
+pub async fn track(
+    State(shutdown): State<Arc<Shutdown>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if shutdown.is_draining() {
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The server is shutting down".to_string(),
+        )
+            .into_response();
+
+        let headers = response.headers_mut();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("close"));
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+
+        return response;
+    }
+
+    shutdown.in_flight.fetch_add(1, Ordering::SeqCst);
+    let _in_flight = InFlight(&shutdown.in_flight);
+
+    next.run(request).await
+}
+
+/// Resolve on SIGINT (Ctrl+C), or SIGTERM where there is one
+async fn signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Unable to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Unable to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => {},
+        () = terminate => {},
+    }
+}
@@ -1,10 +1,23 @@
-/// Format logs properly on Lambda
+use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt};
+
+use super::telemetry;
+
+/// Whether the process was started by the Lambda runtime, rather than locally
+pub fn is_running_on_lambda() -> bool {
+    std::env::var("AWS_LAMBDA_RUNTIME_API").is_ok()
+}
+
+/// Format logs properly on Lambda, and export spans with OpenTelemetry when it's configured
 pub fn tracing_subscriber_fmt() {
-    tracing_subscriber::fmt()
-        .json()
-        .with_max_level(tracing::Level::INFO)
-        .with_target(false)
-        .with_ansi(false)
-        .without_time()
+    tracing_subscriber::registry()
+        .with(telemetry::layer())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_target(false)
+                .with_ansi(false)
+                .without_time(),
+        )
+        .with(LevelFilter::INFO)
         .init();
 }
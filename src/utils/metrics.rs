@@ -0,0 +1,301 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    fmt::Write as _,
+    sync::{Mutex, MutexGuard, OnceLock},
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cqrs_es::{Aggregate, AggregateError, EventEnvelope, EventStore};
+
+/// Commands executed, by aggregate type, command type, and outcome
+pub const COMMANDS: &str = "commands_total";
+
+/// How long commands take to execute, by aggregate type and command type
+pub const COMMAND_DURATION: &str = "command_duration_seconds";
+
+/// How long aggregates take to load from the event store, by aggregate type
+pub const AGGREGATE_LOAD_DURATION: &str = "aggregate_load_duration_seconds";
+
+/// Domain Events published to the Kinesis stream, by aggregate type and event type
+pub const EVENTS_PUBLISHED: &str = "events_published_total";
+
+/// Stream records that failed and were reported back for a retry, by consumer
+pub const BATCH_ITEM_FAILURES: &str = "batch_item_failures_total";
+
+/// How long after an event was recorded a consumer handled it, by consumer
+pub const PROJECTION_LAG: &str = "projection_lag_seconds";
+
+/// The upper bounds of the duration histogram buckets, in seconds
+const BUCKETS: [f64; 14] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0,
+];
+
+/// The most observations of a histogram kept for a single Embedded Metric Format document,
+/// which is the most CloudWatch accepts for one metric
+const MAX_EMF_VALUES: usize = 100;
+
+/// A metric's name with its labels, sorted by label name
+type Key = (&'static str, Vec<(&'static str, String)>);
+
+/// The distribution of a duration
+#[derive(Default)]
+struct Histogram {
+    /// The count of observations at or below each of the `BUCKETS`
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+
+    /// The observations since the last flush
+    recent: Vec<f64>,
+}
+
+/// Every metric recorded by this process
+#[derive(Default)]
+struct Registry {
+    counters: BTreeMap<Key, u64>,
+    histograms: BTreeMap<Key, Histogram>,
+
+    /// Counter increments since the last flush
+    recent_counts: BTreeMap<Key, u64>,
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+    REGISTRY
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn key(name: &'static str, labels: &[(&'static str, &str)]) -> Key {
+    let mut labels: Vec<_> = labels
+        .iter()
+        .map(|(label, value)| (*label, value.to_string()))
+        .collect();
+    labels.sort();
+
+    (name, labels)
+}
+
+/// Add one to a counter
+pub fn increment(name: &'static str, labels: &[(&'static str, &str)]) {
+    increment_by(name, labels, 1);
+}
+
+/// Add to a counter
+pub fn increment_by(name: &'static str, labels: &[(&'static str, &str)], count: u64) {
+    let key = key(name, labels);
+    let mut registry = registry();
+
+    *registry.counters.entry(key.clone()).or_default() += count;
+    *registry.recent_counts.entry(key).or_default() += count;
+}
+
+/// Record a duration in seconds
+pub fn observe(name: &'static str, labels: &[(&'static str, &str)], seconds: f64) {
+    let mut registry = registry();
+    let histogram = registry.histograms.entry(key(name, labels)).or_default();
+
+    for (bucket, bound) in histogram.buckets.iter_mut().zip(BUCKETS) {
+        if seconds <= bound {
+            *bucket += 1;
+        }
+    }
+
+    histogram.sum += seconds;
+    histogram.count += 1;
+
+    if histogram.recent.len() < MAX_EMF_VALUES {
+        histogram.recent.push(seconds);
+    }
+}
+
+/// Record how far behind a consumer is, from the time the event it's handling was recorded
+pub fn observe_lag(consumer: &str, recorded_at: Option<DateTime<Utc>>) {
+    let Some(recorded_at) = recorded_at else {
+        return;
+    };
+
+    let lag = (Utc::now() - recorded_at).num_milliseconds().max(0) as f64 / 1000.0;
+
+    observe(PROJECTION_LAG, &[("consumer", consumer)], lag);
+}
+
+fn labels(labels: &[(&'static str, String)], extra: Option<(&str, &str)>) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(label, value)| (*label, value.as_str()))
+        .chain(extra)
+        .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+        .collect();
+
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render every metric in the Prometheus text exposition format
+pub fn render() -> String {
+    let registry = registry();
+    let mut output = String::new();
+    let mut last = "";
+
+    for ((name, labels_), value) in &registry.counters {
+        if *name != last {
+            let _ = writeln!(output, "# TYPE {name} counter");
+            last = name;
+        }
+
+        let _ = writeln!(output, "{name}{} {value}", labels(labels_, None));
+    }
+
+    for ((name, labels_), histogram) in &registry.histograms {
+        if *name != last {
+            let _ = writeln!(output, "# TYPE {name} histogram");
+            last = name;
+        }
+
+        for (count, bound) in histogram.buckets.iter().zip(BUCKETS) {
+            let bucket = labels(labels_, Some(("le", &bound.to_string())));
+            let _ = writeln!(output, "{name}_bucket{bucket} {count}");
+        }
+
+        let inf = labels(labels_, Some(("le", "+Inf")));
+        let _ = writeln!(output, "{name}_bucket{inf} {}", histogram.count);
+        let _ = writeln!(
+            output,
+            "{name}_sum{} {}",
+            labels(labels_, None),
+            histogram.sum
+        );
+        let _ = writeln!(
+            output,
+            "{name}_count{} {}",
+            labels(labels_, None),
+            histogram.count
+        );
+    }
+
+    output
+}
+
+/// Write the metrics recorded since the last flush to stdout in the CloudWatch Embedded Metric
+/// Format, which CloudWatch Logs turns into metrics. Lambda functions call this after each
+/// invocation. The namespace is set with `METRICS_NAMESPACE`.
+pub fn flush() {
+    let namespace = env::var("METRICS_NAMESPACE").unwrap_or("EventDrivenArchitecture".to_string());
+    let timestamp = Utc::now().timestamp_millis();
+
+    let documents: Vec<_> = {
+        let mut registry = registry();
+
+        let counts = std::mem::take(&mut registry.recent_counts)
+            .into_iter()
+            .map(|(key, count)| (key, "Count", serde_json::json!(count)));
+
+        let durations: Vec<_> = registry
+            .histograms
+            .iter_mut()
+            .filter(|(_, histogram)| !histogram.recent.is_empty())
+            .map(|(key, histogram)| {
+                let values = std::mem::take(&mut histogram.recent);
+
+                (key.clone(), "Seconds", serde_json::json!(values))
+            })
+            .collect();
+
+        counts.chain(durations).collect()
+    };
+
+    for ((name, labels), unit, value) in documents {
+        let dimensions: Vec<&str> = labels.iter().map(|(label, _)| *label).collect();
+
+        let mut document = serde_json::json!({
+            "_aws": {
+                "Timestamp": timestamp,
+                "CloudWatchMetrics": [{
+                    "Namespace": namespace,
+                    "Dimensions": [dimensions],
+                    "Metrics": [{"Name": name, "Unit": unit}],
+                }],
+            },
+            name: value,
+        });
+
+        for (label, value) in labels {
+            document[label] = value.into();
+        }
+
+        println!("{document}");
+    }
+}
+
+/// The name of a command's type, recorded with its metrics
+pub trait CommandType {
+    /// The command's type, such as `Create`
+    fn command_type(&self) -> &'static str;
+}
+
+/// An EventStore that records how long aggregates take to load
+pub struct MeasuredEventStore<ES> {
+    store: ES,
+}
+
+impl<ES> MeasuredEventStore<ES> {
+    /// Create a new instance
+    pub fn new(store: ES) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl<A, ES> EventStore<A> for MeasuredEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+    ES::AC: Send,
+{
+    type AC = ES::AC;
+
+    async fn load_events(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError<A::Error>> {
+        self.store.load_events(aggregate_id).await
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Result<ES::AC, AggregateError<A::Error>> {
+        let started = Instant::now();
+        let result = self.store.load_aggregate(aggregate_id).await;
+
+        observe(
+            AGGREGATE_LOAD_DURATION,
+            &[("aggregate_type", &A::aggregate_type())],
+            started.elapsed().as_secs_f64(),
+        );
+
+        result
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: ES::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError<A::Error>> {
+        self.store.commit(events, context, metadata).await
+    }
+}
@@ -4,4 +4,10 @@ pub mod update;
 /// Lambda helpers
 pub mod lambda;
 
+/// OpenTelemetry tracing
+pub mod telemetry;
+
+/// Prometheus and CloudWatch metrics
+pub mod metrics;
+
 pub use update::Update;
@@ -0,0 +1,421 @@
+use std::{
+    collections::HashMap,
+    env, fmt,
+    fs::OpenOptions,
+    future::Future,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::OnceLock,
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cqrs_es::{
+    persist::{PersistenceError, ViewContext, ViewRepository},
+    Aggregate, AggregateError, CqrsFramework, EventStore, View,
+};
+use opentelemetry::{
+    global,
+    trace::{SpanId, TraceError, TracerProvider as _},
+    KeyValue,
+};
+use opentelemetry_sdk::{
+    export::trace::{ExportResult, SpanData, SpanExporter},
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::TracerProvider,
+    Resource,
+};
+use tracing::{field, Instrument, Level, Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    registry::LookupSpan,
+    Layer,
+};
+
+use crate::{
+    domains::DomainEvent,
+    utils::metrics::{self, CommandType},
+};
+
+/// The provider installed by `layer`, kept so spans can be flushed before a Lambda is frozen
+static PROVIDER: OnceLock<TracerProvider> = OnceLock::new();
+
+/// Where finished spans are exported
+enum Exporter {
+    /// To an OpenTelemetry collector, configured by the standard `OTEL_EXPORTER_OTLP_*` variables
+    Otlp,
+
+    /// As JSON lines on stdout
+    Stdout,
+
+    /// As JSON lines appended to a file
+    File(PathBuf),
+}
+
+impl Exporter {
+    /// Choose an exporter from `OTEL_TRACES_EXPORTER`, which is `otlp`, `stdout`, `file`, or
+    /// `none`. When it isn't set, spans are sent with OTLP if an endpoint is configured.
+    fn from_env() -> Option<Self> {
+        let exporter = env::var("OTEL_TRACES_EXPORTER").unwrap_or_default();
+
+        match exporter.to_lowercase().as_str() {
+            "otlp" => Some(Exporter::Otlp),
+            "stdout" | "console" => Some(Exporter::Stdout),
+            "file" => Some(Exporter::File(
+                env::var("OTEL_TRACES_FILE")
+                    .unwrap_or("traces.jsonl".to_string())
+                    .into(),
+            )),
+            "" if env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_ok()
+                || env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_ok() =>
+            {
+                Some(Exporter::Otlp)
+            }
+            "" | "none" => None,
+            other => {
+                eprintln!("Unknown OTEL_TRACES_EXPORTER {other:?}, traces won't be exported");
+
+                None
+            }
+        }
+    }
+}
+
+/// The service name spans are reported under: `OTEL_SERVICE_NAME` if it's set, then the Lambda
+/// function name, then the name of the running binary
+fn service_name() -> String {
+    env::var("OTEL_SERVICE_NAME")
+        .or_else(|_| env::var("AWS_LAMBDA_FUNCTION_NAME"))
+        .ok()
+        .or_else(|| {
+            env::args()
+                .next()
+                .and_then(|arg| Some(Path::new(&arg).file_stem()?.to_str()?.to_string()))
+        })
+        .unwrap_or(env!("CARGO_PKG_NAME").to_string())
+}
+
+/// Build the tracing layer that exports spans with OpenTelemetry, or `None` if exporting isn't
+/// configured
+///
+/// The W3C Trace Context propagator is installed either way, so trace context that arrives with a
+/// request or event is passed along to the commands it causes. Spans from generating the OpenAPI
+/// document are left out, since they're only noise at startup.
+pub fn layer<S>() -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let service_name = service_name();
+
+    let builder = match Exporter::from_env()? {
+        Exporter::Otlp => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .build()
+                .map_err(|err| eprintln!("Unable to build the OTLP exporter: {err}"))
+                .ok()?;
+
+            TracerProvider::builder().with_batch_exporter(exporter, runtime::Tokio)
+        }
+        Exporter::Stdout => TracerProvider::builder()
+            .with_batch_exporter(JsonLines::new(&service_name, io::stdout()), runtime::Tokio),
+        Exporter::File(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|err| eprintln!("Unable to open {}: {err}", path.display()))
+                .ok()?;
+
+            TracerProvider::builder()
+                .with_batch_exporter(JsonLines::new(&service_name, file), runtime::Tokio)
+        }
+    };
+
+    let provider = builder
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name)]))
+        .build();
+
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+    global::set_tracer_provider(provider.clone());
+    let _ = PROVIDER.set(provider);
+
+    let targets = Targets::new()
+        .with_default(Level::INFO)
+        .with_target("aide", LevelFilter::OFF);
+
+    Some(
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(targets),
+    )
+}
+
+/// Export every finished span. Lambda functions call this after each invocation, since they
+/// may be frozen before the batch would otherwise be sent.
+pub async fn flush() {
+    let Some(provider) = PROVIDER.get().cloned() else {
+        return;
+    };
+
+    if let Ok(results) = tokio::task::spawn_blocking(move || provider.force_flush()).await {
+        for error in results.into_iter().filter_map(Result::err) {
+            tracing::warn!(error = ?error, "Failed to export spans");
+        }
+    }
+}
+
+/// Export every remaining span and stop exporting
+pub async fn shutdown() {
+    let Some(provider) = PROVIDER.get().cloned() else {
+        return;
+    };
+
+    if let Ok(Err(error)) = tokio::task::spawn_blocking(move || provider.shutdown()).await {
+        tracing::warn!(error = ?error, "Failed to shut down the tracer provider");
+    }
+}
+
+/// Add the current trace context to command metadata as W3C `traceparent` and `tracestate`
+/// values, so it's recorded with the events the command produces
+pub fn inject(metadata: &mut HashMap<String, String>) {
+    let context = Span::current().context();
+
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, metadata));
+
+    if metadata.get("tracestate").is_some_and(String::is_empty) {
+        metadata.remove("tracestate");
+    }
+}
+
+/// Continue the trace recorded in an event's metadata, making it the parent of the current span
+pub fn continue_trace(event: &DomainEvent) {
+    let Ok(metadata) = serde_json::from_str::<HashMap<String, String>>(&event.metadata) else {
+        return;
+    };
+
+    if !metadata.contains_key("traceparent") {
+        return;
+    }
+
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(&metadata));
+
+    Span::current().set_parent(context);
+}
+
+/// Executes commands within a span, carrying the trace context into the events they produce and
+/// recording their metrics
+#[async_trait]
+pub trait TracedExecute<A: Aggregate> {
+    /// Execute a command like `CqrsFramework::execute_with_metadata`, within a span
+    async fn execute_traced(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        metadata: HashMap<String, String>,
+    ) -> Result<(), AggregateError<A::Error>>;
+}
+
+#[async_trait]
+impl<A, ES> TracedExecute<A> for CqrsFramework<A, ES>
+where
+    A: Aggregate,
+    A::Command: CommandType + Send,
+    ES: EventStore<A> + Send + Sync,
+    ES::AC: Send,
+{
+    async fn execute_traced(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        mut metadata: HashMap<String, String>,
+    ) -> Result<(), AggregateError<A::Error>> {
+        let aggregate_type = A::aggregate_type();
+        let command_type = command.command_type();
+
+        let span = tracing::info_span!(
+            "cqrs.execute",
+            aggregate_type = aggregate_type,
+            aggregate_id = aggregate_id,
+            command_type = command_type,
+            command_id = metadata.get("command_id").map(String::as_str),
+            otel.status_code = field::Empty,
+        );
+
+        let started = Instant::now();
+
+        let result = async move {
+            inject(&mut metadata);
+
+            self.execute_with_metadata(aggregate_id, command, metadata)
+                .await
+        }
+        .instrument(span.clone())
+        .await;
+
+        let outcome = match &result {
+            Ok(()) => "ok",
+            Err(AggregateError::UserError(_)) => "rejected",
+            Err(AggregateError::AggregateConflict) => "conflict",
+            Err(_) => "error",
+        };
+
+        if result.is_err() {
+            span.record("otel.status_code", "ERROR");
+        }
+
+        let labels = [
+            ("aggregate_type", aggregate_type.as_str()),
+            ("command_type", command_type),
+        ];
+
+        metrics::increment(
+            metrics::COMMANDS,
+            &[labels[0], labels[1], ("outcome", outcome)],
+        );
+        metrics::observe(
+            metrics::COMMAND_DURATION,
+            &labels,
+            started.elapsed().as_secs_f64(),
+        );
+
+        result
+    }
+}
+
+/// A ViewRepository that records a span for each load and update
+pub struct TracedViewRepository<R> {
+    /// The name of the view, recorded on each span
+    view: &'static str,
+
+    repo: R,
+}
+
+impl<R> TracedViewRepository<R> {
+    /// Create a new instance
+    pub fn new(view: &'static str, repo: R) -> Self {
+        Self { view, repo }
+    }
+}
+
+#[async_trait]
+impl<V, A, R> ViewRepository<V, A> for TracedViewRepository<R>
+where
+    V: View<A> + 'static,
+    A: Aggregate,
+    R: ViewRepository<V, A>,
+{
+    async fn load(&self, view_id: &str) -> Result<Option<V>, PersistenceError> {
+        self.repo
+            .load(view_id)
+            .instrument(tracing::info_span!(
+                "view.load",
+                view = self.view,
+                view_id = view_id
+            ))
+            .await
+    }
+
+    async fn load_with_context(
+        &self,
+        view_id: &str,
+    ) -> Result<Option<(V, ViewContext)>, PersistenceError> {
+        self.repo
+            .load_with_context(view_id)
+            .instrument(tracing::info_span!(
+                "view.load",
+                view = self.view,
+                view_id = view_id
+            ))
+            .await
+    }
+
+    async fn update_view(&self, view: V, context: ViewContext) -> Result<(), PersistenceError> {
+        let span = tracing::info_span!(
+            "view.update",
+            view = self.view,
+            view_id = context.view_instance_id,
+        );
+
+        self.repo.update_view(view, context).instrument(span).await
+    }
+}
+
+/// Exports spans as JSON lines, for inspecting traces without a collector
+struct JsonLines {
+    service_name: String,
+    writer: Box<dyn Write + Send + Sync>,
+}
+
+impl JsonLines {
+    fn new(service_name: &str, writer: impl Write + Send + Sync + 'static) -> Self {
+        Self {
+            service_name: service_name.to_string(),
+            writer: Box::new(writer),
+        }
+    }
+
+    fn write(&mut self, span: &SpanData) -> Result<(), TraceError> {
+        let attributes: serde_json::Map<String, serde_json::Value> = span
+            .attributes
+            .iter()
+            .map(|kv| (kv.key.to_string(), kv.value.as_str().into()))
+            .collect();
+
+        let parent_span_id =
+            (span.parent_span_id != SpanId::INVALID).then(|| span.parent_span_id.to_string());
+
+        let line = serde_json::json!({
+            "service": self.service_name,
+            "trace_id": span.span_context.trace_id().to_string(),
+            "span_id": span.span_context.span_id().to_string(),
+            "parent_span_id": parent_span_id,
+            "name": span.name,
+            "kind": format!("{:?}", span.span_kind),
+            "start_time": DateTime::<Utc>::from(span.start_time),
+            "end_time": DateTime::<Utc>::from(span.end_time),
+            "status": format!("{:?}", span.status),
+            "attributes": attributes,
+        });
+
+        serde_json::to_writer(&mut self.writer, &line)
+            .map_err(|err| TraceError::Other(Box::new(err)))?;
+
+        self.writer
+            .write_all(b"\n")
+            .map_err(|err| TraceError::Other(Box::new(err)))
+    }
+}
+
+impl fmt::Debug for JsonLines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLines")
+            .field("service_name", &self.service_name)
+            .finish()
+    }
+}
+
+impl SpanExporter for JsonLines {
+    fn export(
+        &mut self,
+        batch: Vec<SpanData>,
+    ) -> Pin<Box<dyn Future<Output = ExportResult> + Send + 'static>> {
+        let result = batch
+            .iter()
+            .try_for_each(|span| self.write(span))
+            .and_then(|()| {
+                self.writer
+                    .flush()
+                    .map_err(|err| TraceError::Other(Box::new(err)))
+            });
+
+        Box::pin(std::future::ready(result))
+    }
+}
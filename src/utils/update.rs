@@ -3,6 +3,7 @@
 
 use std::ops::Deref;
 
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Similar to `Option`, but it has three states, `unchanged`, `empty` and `value`.
@@ -14,6 +15,8 @@ pub enum Update<T> {
     Value(T),
 }
 
+// Deriving this would require `T: Default`
+#[allow(clippy::derivable_impls)]
 impl<T> Default for Update<T> {
     fn default() -> Self {
         Self::Unchanged
@@ -149,7 +152,7 @@ impl<T> Update<T> {
     /// # Example
     ///
     /// ```rust
-    /// use crate::utils::Update;
+    /// use event_driven_architecture::utils::Update;
     ///
     /// let mut value = None;
     ///
@@ -216,6 +219,27 @@ where
     }
 }
 
+impl<T: JsonSchema> JsonSchema for Update<T> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        format!("Update_{}", T::schema_name())
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        // On the wire an Update is a nullable value, so describe it the way an Option is described
+        let mut schema = gen.subschema_for::<Option<T>>().into_object();
+
+        schema.metadata().description.get_or_insert_with(|| {
+            "Omit to leave unchanged, set to `null` to clear, or provide a value to set".to_string()
+        });
+
+        Schema::Object(schema)
+    }
+}
+
 impl<T> From<Update<T>> for Option<Option<T>> {
     fn from(update: Update<T>) -> Self {
         match update {